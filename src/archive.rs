@@ -0,0 +1,348 @@
+//! Lectura de archivos zip: listado del directorio central y extracción de
+//! entradas sin comprimir o comprimidas con DEFLATE. También se listan (sin
+//! extraer) archivos tar, opcionalmente comprimidos con gzip.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::hash::Crc32;
+use crate::inflate;
+
+const LOCAL_HEADER_SIGNATURE: u32 = 0x0403_4b50;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x0201_4b50;
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x0605_4b50;
+/// Tamaño fijo del registro de fin del directorio central, sin comentario.
+const END_OF_CENTRAL_DIRECTORY_LEN: usize = 22;
+
+const METHOD_STORED: u16 = 0;
+const METHOD_DEFLATE: u16 = 8;
+
+const TAR_BLOCK: usize = 512;
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+/// Tamaño máximo de un tar descomprimido: se lee entero en memoria.
+const MAX_GUNZIP_SIZE: usize = 256 << 20;
+
+/// Entrada de un archivo zip según su directorio central.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ZipEntry {
+    pub name: String,
+    method: u16,
+    crc32: u32,
+    compressed_size: u64,
+    size: u64,
+    local_header_offset: u64,
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("zip inválido: {}", message))
+}
+
+fn u16_at(data: &[u8], offset: usize) -> io::Result<u16> {
+    data.get(offset..offset + 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .ok_or_else(|| invalid("registro truncado"))
+}
+
+fn u32_at(data: &[u8], offset: usize) -> io::Result<u32> {
+    data.get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(|| invalid("registro truncado"))
+}
+
+/// Entradas (sin directorios) de un zip ya leído en memoria.
+pub fn entries(data: &[u8]) -> io::Result<Vec<ZipEntry>> {
+    // El registro de fin va al final, seguido de un comentario de hasta 64 KiB
+    let end = (0..=data.len().saturating_sub(END_OF_CENTRAL_DIRECTORY_LEN))
+        .rev()
+        .take(u16::MAX as usize + 1)
+        .find(|&pos| u32_at(data, pos).is_ok_and(|sig| sig == END_OF_CENTRAL_DIRECTORY_SIGNATURE))
+        .ok_or_else(|| invalid("no se encontró el directorio central"))?;
+
+    let count = u16_at(data, end + 10)? as usize;
+    let mut pos = u32_at(data, end + 16)? as usize;
+    let mut entries = Vec::with_capacity(count);
+
+    for _ in 0..count {
+        if u32_at(data, pos)? != CENTRAL_HEADER_SIGNATURE {
+            return Err(invalid("entrada del directorio central sin firma"));
+        }
+        let name_len = u16_at(data, pos + 28)? as usize;
+        let extra_len = u16_at(data, pos + 30)? as usize;
+        let comment_len = u16_at(data, pos + 32)? as usize;
+        let name = data.get(pos + 46..pos + 46 + name_len).ok_or_else(|| invalid("nombre truncado"))?;
+        let name = String::from_utf8_lossy(name).into_owned();
+
+        if !name.ends_with('/') {
+            entries.push(ZipEntry {
+                name,
+                method: u16_at(data, pos + 10)?,
+                crc32: u32_at(data, pos + 16)?,
+                compressed_size: u32_at(data, pos + 20)? as u64,
+                size: u32_at(data, pos + 24)? as u64,
+                local_header_offset: u32_at(data, pos + 42)? as u64,
+            });
+        }
+        pos += 46 + name_len + extra_len + comment_len;
+    }
+    Ok(entries)
+}
+
+/// Contenido descomprimido de `entry`, verificando su CRC32.
+pub fn extract(data: &[u8], entry: &ZipEntry) -> io::Result<Vec<u8>> {
+    let header = entry.local_header_offset as usize;
+    if u32_at(data, header)? != LOCAL_HEADER_SIGNATURE {
+        return Err(invalid("cabecera local sin firma"));
+    }
+    let start = header + 30 + u16_at(data, header + 26)? as usize + u16_at(data, header + 28)? as usize;
+    let raw = data.get(start..start + entry.compressed_size as usize)
+        .ok_or_else(|| invalid("datos de la entrada truncados"))?;
+
+    let content = match entry.method {
+        METHOD_STORED => raw.to_vec(),
+        METHOD_DEFLATE => inflate::inflate(raw, entry.size as usize).map_err(|e| invalid(&e.to_string()))?,
+        method => {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("método de compresión zip no soportado: {}", method),
+            ));
+        }
+    };
+
+    let mut crc = Crc32::new();
+    crc.update(&content);
+    if content.len() as u64 != entry.size || crc.finish() != entry.crc32 {
+        return Err(invalid("el contenido no coincide con su CRC32"));
+    }
+    Ok(content)
+}
+
+/// Nombres de las entradas de `path`.
+pub fn list(path: &Path) -> io::Result<Vec<String>> {
+    Ok(entries(&fs::read(path)?)?.into_iter().map(|entry| entry.name).collect())
+}
+
+/// Contenido de la entrada `name` de `path`.
+pub fn read_entry(path: &Path, name: &str) -> io::Result<Vec<u8>> {
+    let data = fs::read(path)?;
+    let entry = entries(&data)?
+        .into_iter()
+        .find(|entry| entry.name == name)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("la entrada {:?} no está en el zip", name)))?;
+    extract(&data, &entry)
+}
+
+/// Nombres de los archivos regulares de un tar ya leído en memoria. Se
+/// entienden los formatos ustar y GNU (nombres largos con `L`); las cabeceras
+/// pax se saltan.
+pub fn tar_entries(data: &[u8]) -> io::Result<Vec<String>> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, format!("tar inválido: {}", message));
+    let text = |field: &[u8]| {
+        let end = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+        String::from_utf8_lossy(&field[..end]).into_owned()
+    };
+
+    let mut names = Vec::new();
+    let mut long_name = None;
+    let mut pos = 0;
+    while let Some(header) = data.get(pos..pos + TAR_BLOCK) {
+        // Dos bloques vacíos marcan el final; basta con el primero
+        if header.iter().all(|&b| b == 0) {
+            break;
+        }
+        let size = text(&header[124..136]);
+        let size = u64::from_str_radix(size.trim(), 8).map_err(|_| invalid("tamaño inválido"))? as usize;
+        let content = data.get(pos + TAR_BLOCK..pos + TAR_BLOCK + size).ok_or_else(|| invalid("entrada truncada"))?;
+
+        match header[156] {
+            b'L' => long_name = Some(text(content)),
+            b'0' | 0 => {
+                let name = long_name.take().unwrap_or_else(|| {
+                    let (name, prefix) = (text(&header[..100]), text(&header[345..500]));
+                    if &header[257..262] == b"ustar" && !prefix.is_empty() {
+                        format!("{}/{}", prefix, name)
+                    } else {
+                        name
+                    }
+                });
+                names.push(name);
+            }
+            _ => long_name = None,
+        }
+        pos += TAR_BLOCK + size.div_ceil(TAR_BLOCK) * TAR_BLOCK;
+    }
+    Ok(names)
+}
+
+/// Contenido de un flujo gzip (RFC 1952), sin verificar su CRC.
+fn gunzip(data: &[u8]) -> io::Result<Vec<u8>> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, format!("gzip inválido: {}", message));
+    if data.get(2) != Some(&8) {
+        return Err(invalid("método de compresión no soportado"));
+    }
+    let flags = *data.get(3).ok_or_else(|| invalid("cabecera truncada"))?;
+    let mut pos = 10;
+    if flags & 0x04 != 0 {
+        pos += 2 + u16_at(data, pos)? as usize;
+    }
+    // Nombre y comentario originales, terminados en NUL
+    for flag in [0x08, 0x10] {
+        if flags & flag != 0 {
+            let len = data.get(pos..).and_then(|rest| rest.iter().position(|&b| b == 0));
+            pos += len.ok_or_else(|| invalid("cabecera truncada"))? + 1;
+        }
+    }
+    if flags & 0x02 != 0 {
+        pos += 2;
+    }
+    let raw = data.get(pos..).ok_or_else(|| invalid("cabecera truncada"))?;
+    inflate::inflate(raw, MAX_GUNZIP_SIZE).map_err(|e| invalid(&e.to_string()))
+}
+
+/// Nombres de los archivos de `path`, sea zip, tar o tar comprimido con gzip.
+pub fn list_any(path: &Path) -> io::Result<Vec<String>> {
+    let data = fs::read(path)?;
+    if u32_at(&data, 0).is_ok_and(|sig| sig == LOCAL_HEADER_SIGNATURE) {
+        return Ok(entries(&data)?.into_iter().map(|entry| entry.name).collect());
+    }
+    if data.starts_with(&GZIP_MAGIC) {
+        return tar_entries(&gunzip(&data)?);
+    }
+    tar_entries(&data)
+}
+
+/// Construye un tar ustar, para los tests.
+#[cfg(test)]
+pub fn build_tar(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut data = Vec::new();
+    for (name, content) in files {
+        let mut header = [0u8; TAR_BLOCK];
+        header[..name.len()].copy_from_slice(name.as_bytes());
+        header[100..107].copy_from_slice(b"0000644");
+        header[124..135].copy_from_slice(format!("{:011o}", content.len()).as_bytes());
+        header[156] = b'0';
+        header[257..263].copy_from_slice(b"ustar\0");
+        data.extend_from_slice(&header);
+        data.extend_from_slice(content);
+        data.resize(data.len().next_multiple_of(TAR_BLOCK), 0);
+    }
+    data.extend_from_slice(&[0; 2 * TAR_BLOCK]);
+    data
+}
+
+/// Construye un zip sin compresión, para los tests.
+#[cfg(test)]
+pub fn build_stored_zip(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let entries: Vec<(&str, &[u8], &[u8])> = files.iter().map(|&(name, content)| (name, content, content)).collect();
+    build_zip(METHOD_STORED, &entries)
+}
+
+/// Construye un zip con entradas DEFLATE ya comprimidas (`(nombre,
+/// comprimido, original)`), para los tests.
+#[cfg(test)]
+pub fn build_deflated_zip(files: &[(&str, &[u8], &[u8])]) -> Vec<u8> {
+    build_zip(METHOD_DEFLATE, files)
+}
+
+#[cfg(test)]
+fn build_zip(method: u16, files: &[(&str, &[u8], &[u8])]) -> Vec<u8> {
+    let mut data = Vec::new();
+    let mut central = Vec::new();
+    for (name, raw, content) in files {
+        let mut crc = Crc32::new();
+        crc.update(content);
+        let offset = data.len() as u32;
+
+        let mut fields = Vec::new();
+        fields.extend_from_slice(&20u16.to_le_bytes()); // versión necesaria
+        fields.extend_from_slice(&0u16.to_le_bytes()); // flags
+        fields.extend_from_slice(&method.to_le_bytes());
+        fields.extend_from_slice(&0u32.to_le_bytes()); // fecha y hora
+        fields.extend_from_slice(&crc.finish().to_le_bytes());
+        fields.extend_from_slice(&(raw.len() as u32).to_le_bytes());
+        fields.extend_from_slice(&(content.len() as u32).to_le_bytes());
+        fields.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        fields.extend_from_slice(&0u16.to_le_bytes()); // extra
+
+        data.extend_from_slice(&LOCAL_HEADER_SIGNATURE.to_le_bytes());
+        data.extend_from_slice(&fields);
+        data.extend_from_slice(name.as_bytes());
+        data.extend_from_slice(raw);
+
+        central.extend_from_slice(&CENTRAL_HEADER_SIGNATURE.to_le_bytes());
+        central.extend_from_slice(&20u16.to_le_bytes()); // versión creadora
+        central.extend_from_slice(&fields);
+        central.extend_from_slice(&[0; 10]); // comentario, disco, atributos
+        central.extend_from_slice(&offset.to_le_bytes());
+        central.extend_from_slice(name.as_bytes());
+    }
+
+    let central_offset = data.len() as u32;
+    data.extend_from_slice(&central);
+    data.extend_from_slice(&END_OF_CENTRAL_DIRECTORY_SIGNATURE.to_le_bytes());
+    data.extend_from_slice(&[0; 4]); // discos
+    data.extend_from_slice(&(files.len() as u16).to_le_bytes());
+    data.extend_from_slice(&(files.len() as u16).to_le_bytes());
+    data.extend_from_slice(&(central.len() as u32).to_le_bytes());
+    data.extend_from_slice(&central_offset.to_le_bytes());
+    data.extend_from_slice(&0u16.to_le_bytes()); // comentario
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_list_and_extract_stored_entries() -> io::Result<()> {
+        let data = build_stored_zip(&[("a/Show.S01E05.srt", b"1\n00:00:01,000 --> 00:00:02,000\nHola\n"), ("leeme.txt", b"x")]);
+
+        let entries = entries(&data)?;
+        let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["a/Show.S01E05.srt", "leeme.txt"]);
+        assert_eq!(extract(&data, &entries[1])?, b"x");
+        Ok(())
+    }
+
+    #[test]
+    fn test_tar_entries_with_gnu_long_name() -> io::Result<()> {
+        let long = format!("{}/Show.S01E01.srt", "carpeta".repeat(20));
+        let mut data = build_tar(&[("Show.S01E02.mkv", b"video")]);
+        data.truncate(data.len() - 2 * TAR_BLOCK);
+
+        let mut header = [0u8; TAR_BLOCK];
+        header[..13].copy_from_slice(b"././@LongLink");
+        header[124..135].copy_from_slice(format!("{:011o}", long.len()).as_bytes());
+        header[156] = b'L';
+        data.extend_from_slice(&header);
+        data.extend_from_slice(long.as_bytes());
+        data.resize(data.len().next_multiple_of(TAR_BLOCK), 0);
+        data.extend_from_slice(&build_tar(&[("truncado", b"sub")]));
+
+        assert_eq!(tar_entries(&data)?, ["Show.S01E02.mkv".to_string(), long]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_detects_corruption() -> io::Result<()> {
+        let mut data = build_stored_zip(&[("sub.srt", b"contenido")]);
+        let pos = data.windows(9).position(|w| w == b"contenido").unwrap();
+        data[pos] = b'C';
+
+        let entries = entries(&data)?;
+        assert!(extract(&data, &entries[0]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_stops_past_the_declared_size() -> io::Result<()> {
+        // "test test test test " comprimido, declarado como si fuera solo "test"
+        let deflated = [0x2b, 0x49, 0x2d, 0x2e, 0x51, 0x28, 0x41, 0x25, 0x00];
+        let data = build_deflated_zip(&[("sub.srt", &deflated, b"test")]);
+
+        let entries = entries(&data)?;
+        let error = extract(&data, &entries[0]).unwrap_err();
+        assert!(error.to_string().contains("tamaño máximo"), "{}", error);
+        Ok(())
+    }
+}
@@ -0,0 +1,193 @@
+//! Configuración por directorio en `.subrenamer.toml`.
+//!
+//! Se entiende un subconjunto de TOML: claves `clave = valor` con valores de
+//! cadena (`"..."` con escapes o `'...'` literal) o arreglos de cadenas, y
+//! comentarios con `#`. Cada archivo aplica a su directorio y subdirectorios;
+//! el más cercano gana por completo, sin mezclarse con los de directorios
+//! superiores, y lo que no define se toma de la línea de comandos.
+
+use crate::messages::{Lang, Msg};
+
+/// Nombre del archivo de configuración en cada directorio.
+pub const FILE_NAME: &str = ".subrenamer.toml";
+
+/// Valores leídos de un `.subrenamer.toml`, sin validar.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct DirConfig {
+    pub srt_regex: Option<String>,
+    pub mkv_regex: Option<String>,
+    pub srt_ext: Option<Vec<String>>,
+    pub video_ext: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigError {
+    pub line: usize,
+    pub kind: ErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorKind {
+    MissingEquals,
+    TrailingText(String),
+    UnknownKey(String),
+    ExpectedString,
+    UnclosedArray,
+    UnclosedQuote,
+    UnsupportedEscape(char),
+    ExpectedQuoted,
+}
+
+impl ConfigError {
+    /// Mensaje del error en `lang`, con la línea en la que ocurrió.
+    pub fn text(&self, lang: Lang) -> String {
+        let error = match &self.kind {
+            ErrorKind::MissingEquals => Msg::ConfigMissingEquals,
+            ErrorKind::TrailingText(rest) => Msg::ConfigTrailingText(rest),
+            ErrorKind::UnknownKey(key) => Msg::ConfigUnknownKey(key),
+            ErrorKind::ExpectedString => Msg::ConfigExpectedString,
+            ErrorKind::UnclosedArray => Msg::ConfigUnclosedArray,
+            ErrorKind::UnclosedQuote => Msg::ConfigUnclosedQuote,
+            ErrorKind::UnsupportedEscape(c) => Msg::ConfigUnsupportedEscape(*c),
+            ErrorKind::ExpectedQuoted => Msg::ConfigExpectedQuoted,
+        };
+        Msg::ConfigLine { line: self.line, error: error.text(lang) }.text(lang)
+    }
+}
+
+enum Value {
+    String(String),
+    Array(Vec<String>),
+}
+
+impl Value {
+    fn into_string(self) -> Result<String, ErrorKind> {
+        match self {
+            Value::String(s) => Ok(s),
+            Value::Array(_) => Err(ErrorKind::ExpectedString),
+        }
+    }
+
+    /// Lista de extensiones: un arreglo o una cadena separada por comas.
+    fn into_list(self) -> Vec<String> {
+        let items = match self {
+            Value::String(s) => s.split(',').map(str::to_string).collect(),
+            Value::Array(items) => items,
+        };
+        items
+            .iter()
+            .map(|item| item.trim().trim_start_matches('.').to_lowercase())
+            .filter(|item| !item.is_empty())
+            .collect()
+    }
+}
+
+/// Interpreta el contenido de un `.subrenamer.toml`.
+pub fn parse(content: &str) -> Result<DirConfig, ConfigError> {
+    let mut config = DirConfig::default();
+    for (index, line) in content.lines().enumerate() {
+        let error = |kind: ErrorKind| ConfigError { line: index + 1, kind };
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (key, rest) = line.split_once('=').ok_or_else(|| error(ErrorKind::MissingEquals))?;
+        let (value, rest) = parse_value(rest.trim()).map_err(error)?;
+        let rest = rest.trim();
+        if !rest.is_empty() && !rest.starts_with('#') {
+            return Err(error(ErrorKind::TrailingText(rest.to_string())));
+        }
+
+        match key.trim() {
+            "srt_regex" => config.srt_regex = Some(value.into_string().map_err(error)?),
+            "mkv_regex" => config.mkv_regex = Some(value.into_string().map_err(error)?),
+            "srt_ext" => config.srt_ext = Some(value.into_list()),
+            "video_ext" => config.video_ext = Some(value.into_list()),
+            other => return Err(error(ErrorKind::UnknownKey(other.to_string()))),
+        }
+    }
+    Ok(config)
+}
+
+/// Valor al inicio de `input` y lo que queda después.
+fn parse_value(input: &str) -> Result<(Value, &str), ErrorKind> {
+    if let Some(mut rest) = input.strip_prefix('[') {
+        let mut items = Vec::new();
+        loop {
+            rest = rest.trim_start();
+            if let Some(after) = rest.strip_prefix(']') {
+                return Ok((Value::Array(items), after));
+            }
+            let (item, after) = parse_string(rest)?;
+            items.push(item);
+            rest = after.trim_start();
+            if let Some(after) = rest.strip_prefix(',') {
+                rest = after;
+            } else if !rest.starts_with(']') {
+                return Err(ErrorKind::UnclosedArray);
+            }
+        }
+    }
+    let (value, rest) = parse_string(input)?;
+    Ok((Value::String(value), rest))
+}
+
+/// Cadena TOML básica (`"..."`) o literal (`'...'`) al inicio de `input`.
+fn parse_string(input: &str) -> Result<(String, &str), ErrorKind> {
+    let mut chars = input.char_indices();
+    match chars.next() {
+        Some((_, '\'')) => {
+            let end = input[1..].find('\'').ok_or(ErrorKind::UnclosedQuote)?;
+            Ok((input[1..end + 1].to_string(), &input[end + 2..]))
+        }
+        Some((_, '"')) => {
+            let mut value = String::new();
+            while let Some((i, c)) = chars.next() {
+                match c {
+                    '"' => return Ok((value, &input[i + 1..])),
+                    '\\' => match chars.next().map(|(_, c)| c) {
+                        Some('\\') => value.push('\\'),
+                        Some('"') => value.push('"'),
+                        Some('n') => value.push('\n'),
+                        Some('t') => value.push('\t'),
+                        other => return Err(ErrorKind::UnsupportedEscape(other.unwrap_or(' '))),
+                    },
+                    c => value.push(c),
+                }
+            }
+            Err(ErrorKind::UnclosedQuote)
+        }
+        _ => Err(ErrorKind::ExpectedQuoted),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_strings_and_arrays() {
+        let config = parse(
+            "# serie con numeración propia\n\
+             srt_regex = 'Cap(\\d+)'\n\
+             mkv_regex = \"Cap\\\\.(\\\\d+)\"  # escapes\n\
+             srt_ext = [\"SRT\", '.ass']\n\
+             video_ext = \"mkv, mp4\"\n",
+        )
+        .unwrap();
+
+        assert_eq!(config.srt_regex.as_deref(), Some(r"Cap(\d+)"));
+        assert_eq!(config.mkv_regex.as_deref(), Some(r"Cap\.(\d+)"));
+        assert_eq!(config.srt_ext, Some(vec!["srt".to_string(), "ass".to_string()]));
+        assert_eq!(config.video_ext, Some(vec!["mkv".to_string(), "mp4".to_string()]));
+    }
+
+    #[test]
+    fn test_parse_errors_report_the_line() {
+        assert_eq!(parse("srt_ext = 'srt'\nregex = 'x'").unwrap_err().line, 2);
+        assert_eq!(parse("srt_regex = \"abierta").unwrap_err().line, 1);
+        assert_eq!(parse("srt_regex = ['a']").unwrap_err().kind, ErrorKind::ExpectedString);
+        assert_eq!(parse("video_ext = mkv").unwrap_err().text(Lang::En), "line 1: expected a quoted string");
+    }
+}
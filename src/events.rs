@@ -0,0 +1,106 @@
+//! Flujo de eventos en vivo para interfaces gráficas: un objeto JSON por línea
+//! con un campo `event`, escrito a medida que ocurre cada paso.
+//!
+//! Secuencia de una ejecución:
+//!
+//! 1. `scan-start`, con el directorio analizado.
+//! 2. `file-matched` por cada subtítulo y video del que se extrajo un ID.
+//! 3. `op-planned` por cada renombrado planificado.
+//! 4. `op-done` (con su `status`) u `op-error` por cada operación ejecutada.
+//! 5. `summary`, con los mismos contadores que el reporte JSON final.
+//!
+//! Con `--apply-plan` o `--rename-one` no hay análisis, así que no se emiten
+//! `file-matched`.
+
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::json::{self, Json};
+
+pub enum Event<'a> {
+    ScanStart { directory: &'a Path },
+    FileMatched { path: &'a Path, kind: &'a str, episode_id: &'a str },
+    OpPlanned { from: &'a Path, to: &'a Path, episode_id: &'a str },
+    OpDone { from: &'a Path, to: &'a Path, status: &'a str },
+    OpError { from: &'a Path, to: &'a Path, error: &'a str },
+    Summary(Json),
+}
+
+fn path(path: &Path) -> Json {
+    Json::from(path.display().to_string())
+}
+
+impl Event<'_> {
+    pub fn to_json(&self) -> Json {
+        match self {
+            Event::ScanStart { directory } => {
+                Json::object([("event", Json::from("scan-start")), ("directory", path(directory))])
+            }
+            Event::FileMatched { path: file, kind, episode_id } => Json::object([
+                ("event", Json::from("file-matched")),
+                ("path", path(file)),
+                ("kind", Json::from(*kind)),
+                ("episode_id", Json::from(*episode_id)),
+            ]),
+            Event::OpPlanned { from, to, episode_id } => Json::object([
+                ("event", Json::from("op-planned")),
+                ("from", path(from)),
+                ("to", path(to)),
+                ("episode_id", Json::from(*episode_id)),
+            ]),
+            Event::OpDone { from, to, status } => Json::object([
+                ("event", Json::from("op-done")),
+                ("from", path(from)),
+                ("to", path(to)),
+                ("status", Json::from(*status)),
+            ]),
+            Event::OpError { from, to, error } => Json::object([
+                ("event", Json::from("op-error")),
+                ("from", path(from)),
+                ("to", path(to)),
+                ("error", Json::from(*error)),
+            ]),
+            Event::Summary(summary) => Json::object([("event", Json::from("summary")), ("summary", summary.clone())]),
+        }
+    }
+}
+
+/// Ruta para escribir en el descriptor de archivo `fd` ya abierto por el proceso padre.
+pub fn fd_path(fd: u32) -> Option<PathBuf> {
+    cfg!(unix).then(|| PathBuf::from(format!("/dev/fd/{}", fd)))
+}
+
+/// Abre `path` (un archivo, una tubería con nombre o un descriptor) para escribir eventos.
+pub fn open(path: &Path) -> io::Result<Box<dyn Write>> {
+    Ok(Box::new(OpenOptions::new().create(true).append(true).open(path)?))
+}
+
+/// Escribe `event` como una línea y la vacía enseguida, para que el lector la
+/// reciba en el momento.
+pub fn write<W: Write + ?Sized>(out: &mut W, event: &Event) -> io::Result<()> {
+    let mut line = Vec::new();
+    json::to_writer(&mut line, &event.to_json())?;
+    line.push(b'\n');
+    out.write_all(&line)?;
+    out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_events_are_one_json_object_per_line() -> io::Result<()> {
+        let mut out = Vec::new();
+        write(&mut out, &Event::ScanStart { directory: Path::new("series") })?;
+        write(&mut out, &Event::OpDone { from: Path::new("a.srt"), to: Path::new("b.srt"), status: "renamed" })?;
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "{\"event\":\"scan-start\",\"directory\":\"series\"}\n\
+             {\"event\":\"op-done\",\"from\":\"a.srt\",\"to\":\"b.srt\",\"status\":\"renamed\"}\n"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,161 @@
+//! Historial de ejecuciones en formato JSON Lines: una línea por ejecución
+//! con los renombrados realizados.
+
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::hash::Crc32;
+use crate::json::{self, Json};
+
+/// Nombre del archivo de historial dentro del directorio de datos.
+const FILE_NAME: &str = "history.jsonl";
+
+/// Ubicación por defecto del historial en el directorio de datos de la plataforma.
+pub fn default_path() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("sub-renamer").join(FILE_NAME))
+}
+
+/// Ubicación por defecto del último plan de `directory` para `--diff-last`:
+/// un archivo por directorio, nombrado por el CRC32 de su ruta.
+pub fn last_plan_path(directory: &Path) -> Option<PathBuf> {
+    let mut crc = Crc32::new();
+    crc.update(directory.as_os_str().as_encoded_bytes());
+    data_dir().map(|dir| dir.join("sub-renamer").join("plans").join(format!("{:08x}.json", crc.finish())))
+}
+
+fn data_dir() -> Option<PathBuf> {
+    let var = |name: &str| env::var_os(name).filter(|value| !value.is_empty()).map(PathBuf::from);
+
+    if cfg!(target_os = "windows") {
+        var("APPDATA")
+    } else if cfg!(target_os = "macos") {
+        var("HOME").map(|home| home.join("Library").join("Application Support"))
+    } else {
+        var("XDG_DATA_HOME").or_else(|| var("HOME").map(|home| home.join(".local").join("share")))
+    }
+}
+
+/// Abre el historial para añadir entradas, creando el archivo y sus
+/// directorios si no existen.
+pub fn open(path: &Path) -> io::Result<File> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+/// Añade `entry` al historial y lo recorta a las últimas `max` entradas.
+/// Todo ocurre con un bloqueo exclusivo sobre un archivo `.lock` junto al
+/// historial: este se comparte entre directorios, y sin el bloqueo una
+/// entrada añadida por otra ejecución durante la rotación se perdería.
+pub fn record(path: &Path, entry: &Json, max: usize) -> io::Result<()> {
+    let _lock = lock(path)?;
+    append(path, entry)?;
+    rotate(path, max)
+}
+
+/// Bloqueo exclusivo del historial, liberado al soltar el archivo. Se bloquea
+/// un archivo aparte porque la rotación reemplaza el historial.
+fn lock(path: &Path) -> io::Result<File> {
+    let mut lock_name = path.file_name().unwrap_or_default().to_os_string();
+    lock_name.push(".lock");
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+    let file = OpenOptions::new().create(true).truncate(false).write(true).open(path.with_file_name(lock_name))?;
+    file.lock()?;
+    Ok(file)
+}
+
+/// Añade `entry` como una nueva línea del historial.
+fn append(path: &Path, entry: &Json) -> io::Result<()> {
+    let mut line = Vec::new();
+    json::to_writer(&mut line, entry)?;
+    line.push(b'\n');
+    open(path)?.write_all(&line)
+}
+
+/// Deja solo las últimas `max` entradas del historial. El resultado se escribe
+/// en un temporal junto al historial y lo reemplaza con un renombrado atómico,
+/// así otra ejecución nunca ve el archivo a medio escribir.
+fn rotate(path: &Path, max: usize) -> io::Result<()> {
+    let content = fs::read_to_string(path)?;
+    let lines: Vec<&str> = content.lines().filter(|line| !line.trim().is_empty()).collect();
+    if lines.len() <= max {
+        return Ok(());
+    }
+
+    let mut kept = lines[lines.len() - max..].join("\n");
+    kept.push('\n');
+    let mut temp_name = path.file_name().unwrap_or_default().to_os_string();
+    temp_name.push(format!(".{}.tmp", std::process::id()));
+    let temp = path.with_file_name(temp_name);
+    fs::write(&temp, kept)?;
+    fs::rename(&temp, path).inspect_err(|_| {
+        let _ = fs::remove_file(&temp);
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_append_writes_one_line_per_entry() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().join("nested").join(FILE_NAME);
+
+        append(&path, &Json::object([("run", Json::from(1usize))]))?;
+        append(&path, &Json::object([("run", Json::from(2usize))]))?;
+
+        let content = fs::read_to_string(&path)?;
+        assert_eq!(content, "{\"run\":1}\n{\"run\":2}\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_rotate_keeps_most_recent_entries() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().join(FILE_NAME);
+        for run in 1..=4usize {
+            append(&path, &Json::object([("run", Json::from(run))]))?;
+        }
+
+        rotate(&path, 2)?;
+        assert_eq!(fs::read_to_string(&path)?, "{\"run\":3}\n{\"run\":4}\n");
+        assert_eq!(fs::read_dir(temp_dir.path())?.count(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_concurrent_records_keep_every_entry() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().join(FILE_NAME);
+
+        std::thread::scope(|scope| {
+            let writers: Vec<_> = (0..2usize)
+                .map(|writer| {
+                    let path = &path;
+                    scope.spawn(move || {
+                        (0..100usize).try_for_each(|run| {
+                            let entry = Json::object([("writer", Json::from(writer)), ("run", Json::from(run))]);
+                            record(path, &entry, 150)
+                        })
+                    })
+                })
+                .collect();
+            writers.into_iter().try_for_each(|writer| writer.join().unwrap())
+        })?;
+
+        // Tras la última entrada de cada hilo el otro añade menos de 150, así
+        // que ambas siguen en el historial salvo que una rotación las pisara
+        let content = fs::read_to_string(&path)?;
+        assert_eq!(content.lines().count(), 150);
+        assert!(content.contains("{\"writer\":0,\"run\":99}"), "{}", content);
+        assert!(content.contains("{\"writer\":1,\"run\":99}"), "{}", content);
+        Ok(())
+    }
+}
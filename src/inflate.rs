@@ -0,0 +1,305 @@
+//! Descompresor DEFLATE (RFC 1951) mínimo, suficiente para leer archivos zip.
+
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InflateError(&'static str);
+
+impl fmt::Display for InflateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "datos DEFLATE inválidos: {}", self.0)
+    }
+}
+
+impl std::error::Error for InflateError {}
+
+type Result<T> = std::result::Result<T, InflateError>;
+
+const MAX_BITS: usize = 15;
+
+/// Base y bits extra de los códigos de longitud 257..=285.
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+
+/// Base y bits extra de los códigos de distancia 0..=29.
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049,
+    3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13,
+];
+
+/// Orden en que se transmiten las longitudes del alfabeto de longitudes de código.
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    buffer: u32,
+    count: u32,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        BitReader { data, pos: 0, buffer: 0, count: 0 }
+    }
+
+    fn bits(&mut self, n: u32) -> Result<u32> {
+        while self.count < n {
+            let byte = *self.data.get(self.pos).ok_or(InflateError("fin inesperado de los datos"))?;
+            self.pos += 1;
+            self.buffer |= (byte as u32) << self.count;
+            self.count += 8;
+        }
+        let value = self.buffer & ((1u32 << n) - 1);
+        self.buffer >>= n;
+        self.count -= n;
+        Ok(value)
+    }
+
+    /// Descarta los bits hasta el siguiente límite de byte.
+    fn align(&mut self) {
+        self.buffer = 0;
+        self.count = 0;
+    }
+
+    fn bytes(&mut self, n: usize) -> Result<&'a [u8]> {
+        let end = self.pos.checked_add(n).filter(|&end| end <= self.data.len())
+            .ok_or(InflateError("bloque sin comprimir truncado"))?;
+        let slice = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+}
+
+/// Código de Huffman canónico: cantidad de códigos por longitud y símbolos
+/// ordenados por código.
+struct Huffman {
+    counts: [u16; MAX_BITS + 1],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Result<Self> {
+        let mut counts = [0u16; MAX_BITS + 1];
+        for &len in lengths {
+            counts[len as usize] += 1;
+        }
+        counts[0] = 0;
+
+        // Rechazar códigos sobresuscritos
+        let mut left: i32 = 1;
+        for &count in &counts[1..] {
+            left = (left << 1) - count as i32;
+            if left < 0 {
+                return Err(InflateError("código de Huffman sobresuscrito"));
+            }
+        }
+
+        let mut offsets = [0u16; MAX_BITS + 2];
+        for len in 1..=MAX_BITS {
+            offsets[len + 1] = offsets[len] + counts[len];
+        }
+        let mut symbols = vec![0u16; lengths.len()];
+        for (symbol, &len) in lengths.iter().enumerate() {
+            if len != 0 {
+                symbols[offsets[len as usize] as usize] = symbol as u16;
+                offsets[len as usize] += 1;
+            }
+        }
+        Ok(Huffman { counts, symbols })
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> Result<u16> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for len in 1..=MAX_BITS {
+            code |= reader.bits(1)? as i32;
+            let count = self.counts[len] as i32;
+            if code - count < first {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(InflateError("código de Huffman inexistente"))
+    }
+}
+
+/// Descomprime un flujo DEFLATE sin envoltorio (como el de las entradas zip).
+/// Falla en cuanto el resultado pasaría de `limit` bytes, para que un archivo
+/// manipulado no agote la memoria antes de poder comprobar su tamaño.
+pub fn inflate(data: &[u8], limit: usize) -> Result<Vec<u8>> {
+    let mut reader = BitReader::new(data);
+    let mut out = Vec::new();
+
+    loop {
+        let last = reader.bits(1)? == 1;
+        match reader.bits(2)? {
+            0 => {
+                reader.align();
+                let header = reader.bytes(4)?;
+                let len = u16::from_le_bytes([header[0], header[1]]);
+                let nlen = u16::from_le_bytes([header[2], header[3]]);
+                if len != !nlen {
+                    return Err(InflateError("longitud de bloque sin comprimir inválida"));
+                }
+                check_limit(out.len() + len as usize, limit)?;
+                out.extend_from_slice(reader.bytes(len as usize)?);
+            }
+            1 => {
+                let (lengths, distances) = fixed_codes()?;
+                inflate_block(&mut reader, &mut out, &lengths, &distances, limit)?;
+            }
+            2 => {
+                let (lengths, distances) = dynamic_codes(&mut reader)?;
+                inflate_block(&mut reader, &mut out, &lengths, &distances, limit)?;
+            }
+            _ => return Err(InflateError("tipo de bloque reservado")),
+        }
+        if last {
+            return Ok(out);
+        }
+    }
+}
+
+fn check_limit(len: usize, limit: usize) -> Result<()> {
+    if len > limit {
+        return Err(InflateError("el contenido supera el tamaño máximo"));
+    }
+    Ok(())
+}
+
+fn fixed_codes() -> Result<(Huffman, Huffman)> {
+    let mut lengths = [0u8; 288];
+    lengths[..144].fill(8);
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    lengths[280..].fill(8);
+    Ok((Huffman::new(&lengths)?, Huffman::new(&[5u8; 30])?))
+}
+
+fn dynamic_codes(reader: &mut BitReader) -> Result<(Huffman, Huffman)> {
+    let nlen = reader.bits(5)? as usize + 257;
+    let ndist = reader.bits(5)? as usize + 1;
+    let ncode = reader.bits(4)? as usize + 4;
+    if nlen > 286 || ndist > 30 {
+        return Err(InflateError("demasiados códigos"));
+    }
+
+    let mut code_lengths = [0u8; 19];
+    for &index in &CODE_LENGTH_ORDER[..ncode] {
+        code_lengths[index] = reader.bits(3)? as u8;
+    }
+    let code_huffman = Huffman::new(&code_lengths)?;
+
+    let mut lengths = vec![0u8; nlen + ndist];
+    let mut index = 0;
+    while index < lengths.len() {
+        let symbol = code_huffman.decode(reader)?;
+        let (value, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 => {
+                let previous = *index.checked_sub(1).and_then(|i| lengths.get(i))
+                    .ok_or(InflateError("repetición sin longitud previa"))?;
+                (previous, 3 + reader.bits(2)? as usize)
+            }
+            17 => (0, 3 + reader.bits(3)? as usize),
+            _ => (0, 11 + reader.bits(7)? as usize),
+        };
+        if index + repeat > lengths.len() {
+            return Err(InflateError("demasiadas longitudes de código"));
+        }
+        lengths[index..index + repeat].fill(value);
+        index += repeat;
+    }
+
+    if lengths[256] == 0 {
+        return Err(InflateError("falta el código de fin de bloque"));
+    }
+    Ok((Huffman::new(&lengths[..nlen])?, Huffman::new(&lengths[nlen..])?))
+}
+
+fn inflate_block(
+    reader: &mut BitReader,
+    out: &mut Vec<u8>,
+    lengths: &Huffman,
+    distances: &Huffman,
+    limit: usize,
+) -> Result<()> {
+    loop {
+        let symbol = lengths.decode(reader)? as usize;
+        match symbol {
+            0..=255 => {
+                check_limit(out.len() + 1, limit)?;
+                out.push(symbol as u8);
+            }
+            256 => return Ok(()),
+            257..=285 => {
+                let index = symbol - 257;
+                let len = LENGTH_BASE[index] as usize + reader.bits(LENGTH_EXTRA[index] as u32)? as usize;
+                let dist_symbol = distances.decode(reader)? as usize;
+                if dist_symbol >= DIST_BASE.len() {
+                    return Err(InflateError("código de distancia inválido"));
+                }
+                let dist = DIST_BASE[dist_symbol] as usize + reader.bits(DIST_EXTRA[dist_symbol] as u32)? as usize;
+                if dist > out.len() {
+                    return Err(InflateError("distancia más allá del inicio"));
+                }
+                check_limit(out.len() + len, limit)?;
+                // Copia byte a byte: el origen puede solaparse con lo que se escribe
+                let start = out.len() - dist;
+                for i in 0..len {
+                    out.push(out[start + i]);
+                }
+            }
+            _ => return Err(InflateError("código de longitud inválido")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inflate_stored_and_fixed_blocks() {
+        // Bloque sin comprimir con "hola"
+        assert_eq!(inflate(&[0x01, 0x04, 0x00, 0xfb, 0xff, b'h', b'o', b'l', b'a'], 4).unwrap(), b"hola");
+        // Huffman fijo con repeticiones, generado con zlib
+        let compressed = [0x2b, 0x49, 0x2d, 0x2e, 0x51, 0x28, 0x41, 0x25, 0x00];
+        assert_eq!(inflate(&compressed, 20).unwrap(), b"test test test test ");
+    }
+
+    #[test]
+    fn test_inflate_dynamic_block() {
+        // Huffman dinámico, generado con zlib
+        let compressed = [
+            0x25, 0x8b, 0x31, 0x0e, 0x00, 0x41, 0x08, 0x02, 0xdf, 0x4a, 0x31, 0xc5, 0x36, 0xd2, 0xf0, 0xff, 0x1c,
+            0x9e, 0x68, 0x42, 0x90, 0x11, 0x05, 0x14, 0x0b, 0x91, 0x0e, 0x93, 0x0a, 0xd3, 0x6d, 0x7a, 0x2d, 0xa9,
+            0x22, 0x66, 0x39, 0xfe, 0xb0, 0x77, 0xaf, 0x6b, 0x0b, 0x1d, 0xf6, 0xa6, 0x48, 0x54, 0xd0, 0xf8, 0x9e,
+            0x7c, 0xfc, 0x07,
+        ];
+        let expected = "eateeatoaeaeteteentttteoeeoeteeieeaeeeetaeneeateeeeeteeieoeeteaetaeaeeieeainenetaaenoeoaeeeetoteeeee";
+        assert_eq!(inflate(&compressed, usize::MAX).unwrap(), expected.as_bytes());
+    }
+
+    #[test]
+    fn test_inflate_rejects_truncated_data() {
+        assert!(inflate(&[0x2b, 0x49], usize::MAX).is_err());
+    }
+
+    #[test]
+    fn test_inflate_stops_at_the_limit() {
+        let hola = [0x01, 0x04, 0x00, 0xfb, 0xff, b'h', b'o', b'l', b'a'];
+        assert!(inflate(&hola, 3).is_err());
+        let compressed = [0x2b, 0x49, 0x2d, 0x2e, 0x51, 0x28, 0x41, 0x25, 0x00];
+        assert!(inflate(&compressed, 19).is_err());
+    }
+}
@@ -0,0 +1,432 @@
+//! Serialización JSON mínima para los reportes de la herramienta.
+//!
+//! Solo cubre lo que necesitan los reportes y planes: objetos con orden de
+//! claves estable, arreglos, cadenas, números y booleanos.
+
+use std::fmt;
+use std::io::{self, Write};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// Crea un objeto a partir de pares clave/valor, preservando el orden.
+    pub fn object<K: Into<String>>(fields: impl IntoIterator<Item = (K, Json)>) -> Self {
+        Json::Object(fields.into_iter().map(|(k, v)| (k.into(), v)).collect())
+    }
+
+    /// Valor de `key` si `self` es un objeto que la contiene.
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Json::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    fn write_compact<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        match self {
+            Json::Array(items) => {
+                w.write_all(b"[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        w.write_all(b",")?;
+                    }
+                    item.write_compact(w)?;
+                }
+                w.write_all(b"]")
+            }
+            Json::Object(fields) => {
+                w.write_all(b"{")?;
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        w.write_all(b",")?;
+                    }
+                    write_string(w, key)?;
+                    w.write_all(b":")?;
+                    value.write_compact(w)?;
+                }
+                w.write_all(b"}")
+            }
+            scalar => scalar.write_scalar(w),
+        }
+    }
+
+    fn write_pretty<W: Write>(&self, w: &mut W, indent: usize) -> io::Result<()> {
+        match self {
+            Json::Array(items) if !items.is_empty() => {
+                w.write_all(b"[\n")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        w.write_all(b",\n")?;
+                    }
+                    write_indent(w, indent + 1)?;
+                    item.write_pretty(w, indent + 1)?;
+                }
+                w.write_all(b"\n")?;
+                write_indent(w, indent)?;
+                w.write_all(b"]")
+            }
+            Json::Object(fields) if !fields.is_empty() => {
+                w.write_all(b"{\n")?;
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        w.write_all(b",\n")?;
+                    }
+                    write_indent(w, indent + 1)?;
+                    write_string(w, key)?;
+                    w.write_all(b": ")?;
+                    value.write_pretty(w, indent + 1)?;
+                }
+                w.write_all(b"\n")?;
+                write_indent(w, indent)?;
+                w.write_all(b"}")
+            }
+            other => other.write_compact(w),
+        }
+    }
+
+    fn write_scalar<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        match self {
+            Json::Null => w.write_all(b"null"),
+            Json::Bool(b) => write!(w, "{}", b),
+            Json::Number(n) if !n.is_finite() => w.write_all(b"null"),
+            Json::Number(n) if n.fract() == 0.0 && n.abs() < 1e15 => write!(w, "{}", *n as i64),
+            Json::Number(n) => write!(w, "{}", n),
+            Json::String(s) => write_string(w, s),
+            Json::Array(_) | Json::Object(_) => unreachable!("no es un escalar"),
+        }
+    }
+}
+
+impl From<u64> for Json {
+    fn from(value: u64) -> Self {
+        Json::Number(value as f64)
+    }
+}
+
+impl From<bool> for Json {
+    fn from(value: bool) -> Self {
+        Json::Bool(value)
+    }
+}
+
+impl From<usize> for Json {
+    fn from(value: usize) -> Self {
+        Json::Number(value as f64)
+    }
+}
+
+impl From<f64> for Json {
+    fn from(value: f64) -> Self {
+        Json::Number(value)
+    }
+}
+
+impl From<&str> for Json {
+    fn from(value: &str) -> Self {
+        Json::String(value.to_string())
+    }
+}
+
+impl From<String> for Json {
+    fn from(value: String) -> Self {
+        Json::String(value)
+    }
+}
+
+impl<T: Into<Json>> From<Option<T>> for Json {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Json::Null, Into::into)
+    }
+}
+
+/// Escribe `value` en una sola línea, apto para consumo por otras herramientas.
+pub fn to_writer<W: Write>(mut w: W, value: &Json) -> io::Result<()> {
+    value.write_compact(&mut w)
+}
+
+/// Escribe `value` indentado con dos espacios, pensado para lectura humana.
+pub fn to_writer_pretty<W: Write>(mut w: W, value: &Json) -> io::Result<()> {
+    value.write_pretty(&mut w, 0)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    message: String,
+    offset: usize,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (posición {})", self.message, self.offset)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Interpreta un documento JSON completo.
+pub fn from_str(input: &str) -> Result<Json, ParseError> {
+    let mut parser = Parser { chars: input.char_indices().peekable(), input };
+    let value = parser.parse_value()?;
+    parser.skip_whitespace();
+    match parser.chars.peek() {
+        None => Ok(value),
+        Some(&(offset, _)) => Err(ParseError { message: "contenido extra tras el documento".into(), offset }),
+    }
+}
+
+struct Parser<'a> {
+    chars: std::iter::Peekable<std::str::CharIndices<'a>>,
+    input: &'a str,
+}
+
+impl Parser<'_> {
+    fn offset(&mut self) -> usize {
+        self.chars.peek().map_or(self.input.len(), |&(i, _)| i)
+    }
+
+    fn error<T>(&mut self, message: &str) -> Result<T, ParseError> {
+        Err(ParseError { message: message.to_string(), offset: self.offset() })
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|&(_, c)| c.is_ascii_whitespace()).is_some() {}
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), ParseError> {
+        match self.chars.next() {
+            Some((_, c)) if c == expected => Ok(()),
+            _ => self.error(&format!("se esperaba '{}'", expected)),
+        }
+    }
+
+    fn expect_word(&mut self, word: &str, value: Json) -> Result<Json, ParseError> {
+        for expected in word.chars() {
+            self.expect(expected)?;
+        }
+        Ok(value)
+    }
+
+    fn parse_value(&mut self) -> Result<Json, ParseError> {
+        self.skip_whitespace();
+        match self.chars.peek().map(|&(_, c)| c) {
+            Some('{') => self.parse_object(),
+            Some('[') => self.parse_array(),
+            Some('"') => self.parse_string().map(Json::String),
+            Some('t') => self.expect_word("true", Json::Bool(true)),
+            Some('f') => self.expect_word("false", Json::Bool(false)),
+            Some('n') => self.expect_word("null", Json::Null),
+            Some(c) if c == '-' || c.is_ascii_digit() => self.parse_number(),
+            _ => self.error("valor JSON inesperado"),
+        }
+    }
+
+    fn parse_object(&mut self) -> Result<Json, ParseError> {
+        self.expect('{')?;
+        let mut fields = Vec::new();
+        self.skip_whitespace();
+        if self.chars.next_if(|&(_, c)| c == '}').is_some() {
+            return Ok(Json::Object(fields));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.parse_string()?;
+            self.skip_whitespace();
+            self.expect(':')?;
+            let value = self.parse_value()?;
+            fields.push((key, value));
+            self.skip_whitespace();
+            match self.chars.next() {
+                Some((_, ',')) => continue,
+                Some((_, '}')) => return Ok(Json::Object(fields)),
+                _ => return self.error("se esperaba ',' o '}'"),
+            }
+        }
+    }
+
+    fn parse_array(&mut self) -> Result<Json, ParseError> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.chars.next_if(|&(_, c)| c == ']').is_some() {
+            return Ok(Json::Array(items));
+        }
+        loop {
+            items.push(self.parse_value()?);
+            self.skip_whitespace();
+            match self.chars.next() {
+                Some((_, ',')) => continue,
+                Some((_, ']')) => return Ok(Json::Array(items)),
+                _ => return self.error("se esperaba ',' o ']'"),
+            }
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<String, ParseError> {
+        self.expect('"')?;
+        let mut out = String::new();
+        loop {
+            match self.chars.next() {
+                Some((_, '"')) => return Ok(out),
+                Some((_, '\\')) => match self.chars.next() {
+                    Some((_, '"')) => out.push('"'),
+                    Some((_, '\\')) => out.push('\\'),
+                    Some((_, '/')) => out.push('/'),
+                    Some((_, 'b')) => out.push('\u{8}'),
+                    Some((_, 'f')) => out.push('\u{c}'),
+                    Some((_, 'n')) => out.push('\n'),
+                    Some((_, 'r')) => out.push('\r'),
+                    Some((_, 't')) => out.push('\t'),
+                    Some((_, 'u')) => {
+                        let high = self.parse_hex4()?;
+                        let code = if (0xD800..0xDC00).contains(&high) {
+                            self.expect('\\')?;
+                            self.expect('u')?;
+                            let low = self.parse_hex4()?;
+                            0x10000 + ((high - 0xD800) << 10) + (low.wrapping_sub(0xDC00) & 0x3FF)
+                        } else {
+                            high
+                        };
+                        match char::from_u32(code) {
+                            Some(c) => out.push(c),
+                            None => return self.error("secuencia \\u inválida"),
+                        }
+                    }
+                    _ => return self.error("secuencia de escape inválida"),
+                },
+                Some((_, c)) => out.push(c),
+                None => return self.error("cadena sin terminar"),
+            }
+        }
+    }
+
+    fn parse_hex4(&mut self) -> Result<u32, ParseError> {
+        let mut code = 0;
+        for _ in 0..4 {
+            match self.chars.next().and_then(|(_, c)| c.to_digit(16)) {
+                Some(d) => code = code * 16 + d,
+                None => return self.error("secuencia \\u inválida"),
+            }
+        }
+        Ok(code)
+    }
+
+    fn parse_number(&mut self) -> Result<Json, ParseError> {
+        let start = self.offset();
+        while self
+            .chars
+            .next_if(|&(_, c)| c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E'))
+            .is_some()
+        {}
+        let end = self.offset();
+        match self.input[start..end].parse::<f64>() {
+            Ok(n) => Ok(Json::Number(n)),
+            Err(_) => Err(ParseError { message: "número inválido".into(), offset: start }),
+        }
+    }
+}
+
+fn write_indent<W: Write>(w: &mut W, level: usize) -> io::Result<()> {
+    for _ in 0..level {
+        w.write_all(b"  ")?;
+    }
+    Ok(())
+}
+
+fn write_string<W: Write>(w: &mut W, s: &str) -> io::Result<()> {
+    w.write_all(b"\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => w.write_all(b"\\\"")?,
+            '\\' => w.write_all(b"\\\\")?,
+            '\n' => w.write_all(b"\\n")?,
+            '\r' => w.write_all(b"\\r")?,
+            '\t' => w.write_all(b"\\t")?,
+            c if (c as u32) < 0x20 => write!(w, "\\u{:04x}", c as u32)?,
+            c => write!(w, "{}", c)?,
+        }
+    }
+    w.write_all(b"\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Json {
+        Json::object([
+            ("name", Json::from("Show \"S01E05\".srt")),
+            ("count", Json::from(3usize)),
+            ("dry_run", Json::from(true)),
+            ("items", Json::Array(vec![Json::from(1usize), Json::Null])),
+            ("empty", Json::Array(Vec::new())),
+        ])
+    }
+
+    #[test]
+    fn test_to_writer_compact() {
+        let mut out = Vec::new();
+        to_writer(&mut out, &sample()).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            r#"{"name":"Show \"S01E05\".srt","count":3,"dry_run":true,"items":[1,null],"empty":[]}"#
+        );
+    }
+
+    #[test]
+    fn test_to_writer_pretty() {
+        let mut out = Vec::new();
+        to_writer_pretty(&mut out, &sample()).unwrap();
+        let expected = "{\n  \"name\": \"Show \\\"S01E05\\\".srt\",\n  \"count\": 3,\n  \"dry_run\": true,\n  \"items\": [\n    1,\n    null\n  ],\n  \"empty\": []\n}";
+        assert_eq!(String::from_utf8(out).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_from_str_roundtrip() {
+        let original = sample();
+        let mut out = Vec::new();
+        to_writer_pretty(&mut out, &original).unwrap();
+        let parsed = from_str(std::str::from_utf8(&out).unwrap()).unwrap();
+        assert_eq!(parsed, original);
+        assert_eq!(parsed.get("count").and_then(Json::as_f64), Some(3.0));
+    }
+
+    #[test]
+    fn test_from_str_escapes_and_errors() {
+        let parsed = from_str(r#"{"s": "a\u00e9\n\ud83d\ude00", "n": -1.5e2}"#).unwrap();
+        assert_eq!(parsed.get("s").and_then(Json::as_str), Some("aé\n😀"));
+        assert_eq!(parsed.get("n").and_then(Json::as_f64), Some(-150.0));
+
+        assert!(from_str("{\"a\": 1,}").is_err());
+        assert!(from_str("[1, 2] 3").is_err());
+        assert!(from_str("\"sin cerrar").is_err());
+    }
+}
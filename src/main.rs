@@ -1,13 +1,34 @@
-use std::collections::HashMap;
-use std::ffi::OsStr;
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::ffi::{OsStr, OsString};
 use std::fs;
+use std::io;
 use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::LazyLock;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use anyhow::{Context, Result};
-use clap::{command, Parser};
-use regex::Regex;
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use regex::{Regex, RegexBuilder};
 use walkdir::WalkDir;
 
+mod archive;
+mod dirconfig;
+mod events;
+mod hash;
+mod history;
+mod inflate;
+mod json;
+mod messages;
+mod template;
+
+use events::Event;
+use json::Json;
+use messages::{Lang, Msg};
+use template::Template;
+
 /// Renombra subtítulos para que coincidan con los nombres de sus archivos de video correspondientes.
 #[derive(Parser, Debug)]
 #[command(
@@ -56,8 +77,13 @@ struct Args {
     )]
     directory: PathBuf,
 
-    /// Buscar en subdirectorios
-    #[arg(short, long, help = "Buscar recursivamente en subdirectorios")]
+    /// Buscar en subdirectorios. Un subtítulo empareja con su video aunque estén
+    /// a distinta profundidad, pero se renombra dentro de su propia carpeta
+    #[arg(
+        short,
+        long,
+        help = "Buscar recursivamente en subdirectorios. Subtítulos y videos emparejan a cualquier profundidad; el subtítulo queda en su carpeta salvo con --match-video-dir"
+    )]
     recursive: bool,
 
     /// Modo de prueba (no renombra archivos realmente)
@@ -74,432 +100,7854 @@ struct Args {
     /// Modo verbose (información detallada)
     #[arg(short, long, help = "Modo verbose: muestra información detallada")]
     verbose: bool,
-}
 
-#[derive(Debug, Clone)]
-struct FileInfo {
-    path: PathBuf,
-    episode_id: String,
-    extension: String,
-}
+    /// Formato del reporte final
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = OutputFormat::Text,
+        help = "Formato del reporte final: text (legible) o json"
+    )]
+    format: OutputFormat,
 
-#[derive(Debug)]
-struct RenameOperation {
-    from: PathBuf,
-    to: PathBuf,
-    episode_id: String,
-}
+    /// Orden de las operaciones en el listado y el reporte
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = SortOrder::Natural,
+        help = "Orden de las operaciones: natural (por ID de episodio, con E2 antes que E10) o path (por ruta, como en disco)"
+    )]
+    sort: SortOrder,
 
-struct SubtitleRenamer {
-    args: Args,
-    srt_regex: Regex,
-    mkv_regex: Regex,
-    srt_extensions: Vec<String>,
-    video_extensions: Vec<String>,
-}
+    /// Emitir el reporte JSON en una sola línea
+    #[arg(
+        long,
+        help = "Emitir el reporte JSON compacto en una sola línea (por defecto se indenta)"
+    )]
+    json_compact: bool,
 
-impl SubtitleRenamer {
-    fn new(args: Args) -> Result<Self> {
-        // Validar que al menos un regex esté presente
-        if args.srt_regex.is_none() && args.mkv_regex.is_none() {
-            anyhow::bail!("❌ Debes proporcionar al menos un regex (--srt-regex o --mkv-regex)");
-        }
+    /// Aplicar renombrados que solo cambian mayúsculas/minúsculas
+    #[arg(
+        long,
+        help = "Aplicar renombrados que solo difieren en mayúsculas/minúsculas cuando el sistema de archivos las ignora y el destino ya es el mismo archivo (por defecto se omiten)"
+    )]
+    fix_case: bool,
 
-        // Usar el regex disponible como fallback
-        let srt_re_str = args.srt_regex.as_ref()
-            .or(args.mkv_regex.as_ref())
-            .unwrap();
-        let mkv_re_str = args.mkv_regex.as_ref()
-            .or(args.srt_regex.as_ref())
-            .unwrap();
-
-        let srt_regex = Regex::new(srt_re_str)
-            .with_context(|| format!("Regex inválido para subtítulos: {}", srt_re_str))?;
-        
-        let mkv_regex = Regex::new(mkv_re_str)
-            .with_context(|| format!("Regex inválido para videos: {}", mkv_re_str))?;
+    /// Emparejar solo subtítulos y videos del mismo directorio
+    #[arg(
+        long,
+        help = "Solo renombrar subtítulos que estén en el mismo directorio que su video"
+    )]
+    colocated_only: bool,
 
-        let srt_extensions = Self::parse_extensions(&args.srt_ext);
-        let video_extensions = Self::parse_extensions(&args.video_ext);
+    /// Mover cada subtítulo a la carpeta de su video
+    #[arg(
+        long,
+        conflicts_with = "colocated_only",
+        help = "Mover el subtítulo renombrado a la carpeta de su video (por defecto queda en la suya, aunque el video esté en otra)"
+    )]
+    match_video_dir: bool,
 
-        // Validar que el directorio existe
-        if !args.directory.exists() {
-            anyhow::bail!("❌ El directorio {:?} no existe", args.directory);
-        }
+    /// Tomar el idioma de la carpeta del subtítulo (`subs/eng/S01E05.srt`)
+    #[arg(
+        long,
+        requires = "recursive",
+        conflicts_with = "colocated_only",
+        help = "Tomar el idioma del nombre de la carpeta del subtítulo (subs/eng, subs/spa, pt-BR) para {lang} y, sin plantilla, añadirlo al nombre (Video.eng.srt); el subtítulo se mueve junto a su video"
+    )]
+    lang_from_dir: bool,
 
-        Ok(Self {
-            args,
-            srt_regex,
-            mkv_regex,
-            srt_extensions,
-            video_extensions,
-        })
-    }
+    /// Numerar el destino cuando ya existe en lugar de omitirlo
+    #[arg(
+        long,
+        help = "Si el destino ya existe, probar nombres numerados hasta encontrar uno libre"
+    )]
+    number_conflicts: bool,
 
-    fn parse_extensions(ext_str: &str) -> Vec<String> {
-        ext_str
-            .split(',')
-            .map(|s| s.trim().to_lowercase())
-            .filter(|s| !s.is_empty())
-            .collect()
-    }
+    /// Formato del sufijo numérico para --number-conflicts
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = NumberFormat::Dot,
+        help = "Formato de numeración: dot ({stem}.2.{ext}) o paren ({stem} (2).{ext})"
+    )]
+    number_format: NumberFormat,
 
-    fn get_files(&self) -> Result<Vec<PathBuf>> {
-        let mut files = Vec::new();
-        
-        if self.args.recursive {
-            for entry in WalkDir::new(&self.args.directory) {
-                match entry {
-                    Ok(e) if e.file_type().is_file() => {
-                        files.push(e.path().to_path_buf());
-                    }
-                    Ok(_) => {} // Ignorar directorios
-                    Err(e) => {
-                        if !self.args.quiet {
-                            eprintln!("⚠️ Error accediendo a archivo: {}", e);
-                        }
-                    }
-                }
-            }
-        } else {
-            let dir_entries = fs::read_dir(&self.args.directory)
-                .with_context(|| format!("No se pudo leer el directorio {:?}", self.args.directory))?;
-            
-            for entry in dir_entries {
-                match entry {
-                    Ok(e) if e.file_type().map_or(false, |ft| ft.is_file()) => {
-                        files.push(e.path());
-                    }
-                    Ok(_) => {} // Ignorar directorios
-                    Err(e) => {
-                        if !self.args.quiet {
-                            eprintln!("⚠️ Error accediendo a archivo: {}", e);
-                        }
-                    }
-                }
-            }
-        }
+    /// Máximo de nombres numerados a probar
+    #[arg(
+        long,
+        default_value_t = 99,
+        help = "Número máximo de intentos de numeración antes de omitir el archivo"
+    )]
+    max_number_attempts: usize,
 
-        Ok(files)
-    }
+    /// Regex para capturar el número de parte (CD1, Part 2...)
+    #[arg(
+        long,
+        help = "Patrón regex para extraer el número de parte, combinado con el ID de episodio (ej: '(?i)(?:cd|part)\\s*(\\d+)')"
+    )]
+    part_regex: Option<String>,
 
-    fn extract_episode_id(&self, path: &Path, is_subtitle: bool) -> Option<String> {
-        let file_name = path.file_name()?.to_str()?;
-        let regex = if is_subtitle { &self.srt_regex } else { &self.mkv_regex };
-        
-        regex.captures(file_name)
-            .and_then(|cap| cap.get(1))
-            .map(|m| m.as_str().to_string())
-    }
+    /// Directorios a omitir durante la búsqueda recursiva
+    #[arg(
+        long,
+        value_name = "DIR_NAME_OR_GLOB",
+        help = "Omitir subdirectorios con este nombre o patrón (ej: Extras, 'Sample*'); repetible"
+    )]
+    prune: Vec<String>,
 
-    fn categorize_files(&self) -> Result<(Vec<FileInfo>, Vec<FileInfo>)> {
-        let mut subtitles = Vec::new();
-        let mut videos = Vec::new();
+    /// Guardar el plan de renombrado en un archivo sin ejecutarlo
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Guardar las operaciones planificadas en un archivo JSON sin ejecutarlas"
+    )]
+    plan_out: Option<PathBuf>,
 
-        let files = self.get_files()?;
+    /// Imprimir el plan como comandos de otra herramienta, sin ejecutarlo
+    #[arg(
+        long,
+        value_enum,
+        value_name = "FORMAT",
+        help = "Imprimir el plan sin ejecutarlo, en el formato de otra herramienta: rename = un comando `rename --no-overwrite -- DESDE HASTA ARCHIVO` de util-linux por operación, entre comillas simples de sh; se omiten con un aviso los destinos que ya existen o se repiten"
+    )]
+    emit_format: Option<EmitFormat>,
 
-        for path in files {
-            if let Some(extension) = path.extension()
-                .and_then(OsStr::to_str)
-                .map(str::to_lowercase)
-            {
-                if self.srt_extensions.contains(&extension) {
-                    if let Some(episode_id) = self.extract_episode_id(&path, true) {
-                        subtitles.push(FileInfo {
-                            path,
-                            episode_id,
-                            extension,
-                        });
-                    }
-                } else if self.video_extensions.contains(&extension) {
-                    if let Some(episode_id) = self.extract_episode_id(&path, false) {
-                        videos.push(FileInfo {
-                            path,
-                            episode_id,
-                            extension,
-                        });
-                    }
-                }
-            }
-        }
+    /// Ejecutar un plan guardado con --plan-out
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with = "plan_out",
+        help = "Ejecutar un plan guardado con --plan-out sin volver a escanear (rutas relativas a --directory)"
+    )]
+    apply_plan: Option<PathBuf>,
 
-        if self.args.verbose {
-            println!("📊 Encontrados {} subtítulos y {} videos", subtitles.len(), videos.len());
-        }
+    /// Normalizar dígitos de ancho completo y estilizados antes de aplicar el regex
+    #[arg(
+        long,
+        help = "Convertir dígitos de ancho completo (０１) y otros numerales Unicode a ASCII antes de aplicar el regex"
+    )]
+    normalize_digits: bool,
 
-        Ok((subtitles, videos))
-    }
+    /// Quitar tildes y otros diacríticos antes de aplicar los regex
+    #[arg(
+        long,
+        help = "Quitar los diacríticos (Pokémon -> Pokemon) antes de aplicar los regex, tanto para los IDs como para --show-key-regex"
+    )]
+    strip_accents: bool,
 
-    fn plan_renames(&self, subtitles: Vec<FileInfo>, videos: Vec<FileInfo>) -> Vec<RenameOperation> {
-        let video_map: HashMap<String, &FileInfo> = videos
-            .iter()
-            .map(|v| (v.episode_id.clone(), v))
-            .collect();
+    /// Escribir métricas de la ejecución en formato Prometheus
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Escribir los contadores de la ejecución en formato de texto Prometheus (ej: sub_renamer.prom)"
+    )]
+    metrics_out: Option<PathBuf>,
 
-        let mut operations = Vec::new();
+    /// Resumen de una línea JSON para agregadores de logs
+    #[arg(
+        long,
+        value_name = "FILE",
+        num_args = 0..=1,
+        help = "Al terminar, escribir una línea JSON con la fecha (segundos Unix), el directorio, los contadores y la duración; por stderr o, con FILE, añadida al final del archivo"
+    )]
+    summary_json: Option<Option<PathBuf>>,
 
-        for subtitle in &subtitles {
-            if let Some(video) = video_map.get(&subtitle.episode_id) {
-                let video_stem = video.path.file_stem()
-                    .and_then(OsStr::to_str)
-                    .unwrap_or("unknown");
-                
-                let new_name = format!("{}.{}", video_stem, subtitle.extension);
-                let new_path = subtitle.path.parent()
-                    .unwrap_or_else(|| Path::new("."))
-                    .join(&new_name);
+    /// Manifiesto que asocia hashes de video con IDs de episodio
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Archivo con líneas '<crc32> <id>' para identificar videos por su contenido en vez de por su nombre"
+    )]
+    video_hash_manifest: Option<PathBuf>,
 
-                // Evitar renombrar a sí mismo
-                if subtitle.path != new_path {
-                    operations.push(RenameOperation {
-                        from: subtitle.path.clone(),
-                        to: new_path,
-                        episode_id: subtitle.episode_id.clone(),
-                    });
-                }
-            } else if !self.args.quiet {
-                println!(
-                    "⚠️ No se encontró video para episodio '{}' (subtítulo: {:?})",
-                    subtitle.episode_id,
-                    subtitle.path.file_name().unwrap_or_default()
-                );
-            }
-        }
+    /// CSV que asocia títulos de episodio con IDs
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "CSV con líneas 'título,ID' (ej: The Reckoning,S01E05) para identificar por su título los subtítulos sin número; el ID debe tener la forma que captura el regex de videos"
+    )]
+    title_map: Option<PathBuf>,
 
-        operations
-    }
+    /// Limitar el hash de videos a los primeros N bytes
+    #[arg(
+        long,
+        value_name = "N",
+        requires = "video_hash_manifest",
+        help = "Calcular el hash solo sobre los primeros N bytes de cada video (debe coincidir con el manifiesto)"
+    )]
+    hash_prefix_bytes: Option<u64>,
 
-    fn execute_renames(&self, operations: Vec<RenameOperation>) -> Result<()> {
-        if operations.is_empty() {
-            if !self.args.quiet {
-                println!("ℹ️ No hay archivos para renombrar");
-            }
-            return Ok(());
-        }
+    /// Idioma de los mensajes
+    #[arg(
+        long,
+        value_enum,
+        help = "Idioma de los mensajes: en o es (por defecto según LANG)"
+    )]
+    lang: Option<Lang>,
 
-        let mut success_count = 0;
-        let mut error_count = 0;
+    /// Renombrar también el directorio de cada episodio
+    #[arg(
+        long,
+        help = "Renombrar también el directorio del episodio al nombre del video, si solo contiene ese episodio"
+    )]
+    rename_dir: bool,
 
-        for op in operations {
-            // Verificar si el archivo de destino ya existe
-            if op.to.exists() && op.from != op.to {
-                if !self.args.quiet {
-                    println!(
-                        "⚠️ El archivo de destino ya existe: {:?} (episodio: {})",
-                        op.to.file_name().unwrap_or_default(),
-                        op.episode_id
-                    );
-                }
-                continue;
-            }
+    /// Usar el campo Title de los subtítulos ASS/SSA para extraer el ID
+    #[arg(
+        long,
+        help = "En subtítulos .ass/.ssa, aplicar el regex al campo Title de [Script Info] en vez del nombre de archivo"
+    )]
+    ass_title_match: bool,
 
-            if self.args.dry_run {
-                println!(
-                    "🔄 [DRY RUN] {:?} -> {:?}",
-                    op.from.file_name().unwrap_or_default(),
-                    op.to.file_name().unwrap_or_default()
-                );
-                success_count += 1;
-            } else {
-                match fs::rename(&op.from, &op.to) {
-                    Ok(()) => {
-                        if !self.args.quiet {
-                            println!(
-                                "✅ Renombrado: {:?} -> {:?}",
-                                op.from.file_name().unwrap_or_default(),
-                                op.to.file_name().unwrap_or_default()
-                            );
-                        }
-                        success_count += 1;
-                    }
-                    Err(e) => {
-                        eprintln!(
-                            "❌ Error renombrando {:?}: {}",
-                            op.from.file_name().unwrap_or_default(),
-                            e
-                        );
-                        error_count += 1;
-                    }
-                }
-            }
-        }
+    /// Proporción mínima de subtítulos emparejados para continuar
+    #[arg(
+        long,
+        value_name = "RATE",
+        value_parser = parse_rate,
+        help = "Abortar si la proporción de subtítulos emparejados con un video es menor que RATE (0.0-1.0, ej: 0.8)"
+    )]
+    min_match_rate: Option<f64>,
 
-        if !self.args.quiet {
-            println!("\n📈 Resumen:");
-            println!("  ✅ Éxitos: {}", success_count);
-            if error_count > 0 {
-                println!("  ❌ Errores: {}", error_count);
-            }
-            if self.args.dry_run {
-                println!("  ℹ️ Modo de prueba activado - no se renombraron archivos realmente");
-            }
-        }
+    /// Tamaño máximo de un subtítulo
+    #[arg(
+        long,
+        value_name = "TAMAÑO",
+        value_parser = parse_size,
+        help = "Omitir con un aviso los archivos con extensión de subtítulo más grandes que TAMAÑO (ej: 5M, 500K): un subtítulo real pesa poco"
+    )]
+    max_subtitle_size: Option<u64>,
 
-        Ok(())
-    }
+    /// Tamaño mínimo de un video
+    #[arg(
+        long,
+        value_name = "TAMAÑO",
+        value_parser = parse_size,
+        help = "Omitir con un aviso los archivos con extensión de video más pequeños que TAMAÑO (ej: 50M), como muestras o descargas incompletas"
+    )]
+    min_video_size: Option<u64>,
 
-    fn run(&self) -> Result<()> {
-        let (subtitles, videos) = self.categorize_files()?;
-        let operations = self.plan_renames(subtitles, videos);
-        self.execute_renames(operations)?;
-        Ok(())
-    }
-}
+    /// Conservar la marca de desfase de tiempos (+2s, -1.5s) del subtítulo
+    #[arg(
+        long,
+        help = "Conservar en el nuevo nombre una marca final de desfase como '+2s' o '-1.5s'"
+    )]
+    preserve_offset: bool,
 
-fn main() -> Result<()> {
-    let args = Args::parse();
+    /// Renombrar un único par subtítulo/video indicado explícitamente
+    #[arg(
+        long,
+        num_args = 2,
+        value_names = ["SUBTITULO", "VIDEO"],
+        conflicts_with = "apply_plan",
+        help = "Renombrar SUBTITULO al nombre de VIDEO sin escanear el directorio ni usar regex"
+    )]
+    rename_one: Option<Vec<PathBuf>>,
 
-    // Mostrar ayuda si no se proporcionan regex
-    if args.srt_regex.is_none() && args.mkv_regex.is_none() {
-        eprintln!("❌ Debes proporcionar al menos un regex.");
-        eprintln!("\n📚 Ejemplos de uso:");
-        eprintln!("  # Básico con regex para ambos tipos de archivo:");
-        eprintln!("  sub-renamer --srt-regex 'S(\\d{{2}})E(\\d{{2}})' --mkv-regex 'S(\\d{{2}})E(\\d{{2}})'");
-        eprintln!("\n  # Con extensiones múltiples y modo recursivo:");
-        eprintln!("  sub-renamer --srt-regex 'S(\\d{{2}})E(\\d{{2}})' --srt-ext srt,ass,vtt --video-ext mkv,mp4,avi --recursive");
-        eprintln!("\n  # Modo de prueba (no renombra realmente):");
-        eprintln!("  sub-renamer --srt-regex 'S(\\d{{2}})E(\\d{{2}})' --dry-run");
-        eprintln!("\n  # En directorio específico:");
-        eprintln!("  sub-renamer --srt-regex 'S(\\d{{2}})E(\\d{{2}})' --directory /path/to/episodes");
-        
-        std::process::exit(1);
-    }
+    /// Confirmar las operaciones antes de ejecutarlas
+    #[arg(
+        long,
+        help = "Mostrar las operaciones por páginas y pedir confirmación antes de renombrar"
+    )]
+    interactive: bool,
 
-    let renamer = SubtitleRenamer::new(args)?;
-    renamer.run()
-}
+    /// Operaciones por página en modo interactivo
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 20,
+        value_parser = clap::value_parser!(u64).range(1..),
+        requires = "interactive",
+        help = "Número de operaciones mostradas por página en modo interactivo"
+    )]
+    page_size: u64,
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::fs;
-    use tempfile::TempDir;
+    /// Avisar de emparejamientos con nombres sospechosamente distintos
+    #[arg(
+        long,
+        help = "Avisar cuando un subtítulo y su video casi no comparten palabras además del ID (posible colisión)"
+    )]
+    sanity_check: bool,
 
-    #[test]
-    fn test_parse_extensions() {
-        assert_eq!(
-            SubtitleRenamer::parse_extensions("srt,ass,vtt"),
-            vec!["srt", "ass", "vtt"]
-        );
+    /// Registrar los renombrados en el historial
+    #[arg(
+        long,
+        help = "Registrar los renombrados realizados en el historial (por defecto en el directorio de datos del usuario)"
+    )]
+    history: bool,
+
+    /// Ubicación del archivo de historial
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Archivo de historial a usar en lugar del directorio de datos del usuario (activa --history)"
+    )]
+    history_file: Option<PathBuf>,
+
+    /// Comparar el plan con el de la ejecución anterior
+    #[arg(
+        long,
+        value_name = "ARCHIVO",
+        num_args = 0..=1,
+        help = "Mostrar qué cambió respecto al plan de la ejecución anterior en este directorio (emparejamientos nuevos, huérfanos resueltos, conflictos nuevos) y guardar el actual. El plan se guarda en el directorio de datos del usuario o en ARCHIVO"
+    )]
+    diff_last: Option<Option<PathBuf>>,
+
+    /// Guardar el mapa de emparejamientos como grafo GraphViz
+    #[arg(
+        long,
+        value_name = "ARCHIVO",
+        conflicts_with_all = ["apply_plan", "rename_one", "normalize_all_extensions"],
+        help = "Escribir en ARCHIVO un grafo DOT (GraphViz) con subtítulos y videos unidos por sus emparejamientos, marcando huérfanos y conflictos, y salir sin renombrar"
+    )]
+    dot: Option<PathBuf>,
+
+    /// Cantidad máxima de ejecuciones que se conservan en el historial
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 50,
+        value_parser = clap::value_parser!(u64).range(1..),
+        help = "Conservar solo las últimas N ejecuciones en el historial; las más antiguas se descartan"
+    )]
+    history_max: u64,
+
+    /// Regex para capturar el nombre de la serie.
+    ///
+    /// La clave de emparejamiento queda `serie:id`, y el ID puede ser una fecha
+    /// de `--date-format`: así, en una carpeta con varios programas diarios, dos
+    /// emisiones del mismo día solo se emparejan si además son del mismo
+    /// programa. Los dos regex se aplican por separado al mismo nombre: el de la
+    /// serie solo aporta su primer grupo, y el de episodio (o el de fecha por
+    /// defecto) sus grupos de siempre. Con `auto`, la serie es lo que precede a
+    /// la fecha según el regex por defecto de `--date-format`.
+    #[arg(
+        long,
+        value_name = "REGEX",
+        help = "Patrón regex cuyo primer grupo captura la serie (ej: '^(.+?)[. ]S\\d'); solo se emparejan archivos de la misma serie y episodio. Con --date-format, 'auto' toma como serie lo que precede a la fecha"
+    )]
+    show_key_regex: Option<String>,
+
+    /// Ignorar mayúsculas/minúsculas en los regex de episodio
+    #[arg(
+        long,
+        global = true,
+        help = "Aplicar --srt-regex y --mkv-regex sin distinguir mayúsculas de minúsculas"
+    )]
+    ignore_case: bool,
+
+    /// Grupo de captura que contiene el ID de episodio
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 1,
+        global = true,
+        help = "Número del grupo de captura que contiene el ID de episodio (0 = toda la coincidencia)"
+    )]
+    capture_group: usize,
+
+    /// Unir todos los grupos de captura en el ID
+    #[arg(
+        long,
+        value_name = "SEP",
+        global = true,
+        help = "Formar el ID uniendo todos los grupos de captura con SEP (ej: 'x' para S01E02 -> 01x02)"
+    )]
+    group_separator: Option<String>,
+
+    /// Grupos con nombre cuyos ceros a la izquierda no cuentan
+    #[arg(
+        long,
+        value_name = "NAMES",
+        value_delimiter = ',',
+        global = true,
+        help = "Quitar los ceros a la izquierda solo de estos grupos con nombre (ej: 'ep' con '(?<ep>\\d+)' iguala E5 y E05), dejando exactos los demás"
+    )]
+    normalize_groups: Vec<String>,
+
+    /// Números del ID sin ceros a la izquierda
+    #[arg(
+        long,
+        global = true,
+        help = "Quitar los ceros a la izquierda de cada número del ID, en todos los grupos: E005, E05 y E5 emparejan aunque subtítulos y videos rellenen distinto"
+    )]
+    normalize_numbers: bool,
+
+    /// Usar toda la coincidencia como ID si el regex no tiene el grupo pedido
+    #[arg(
+        long,
+        global = true,
+        help = "Si el regex no tiene el grupo de --capture-group (ej: 'S\\d{2}E\\d{2}' sin paréntesis), usar toda la coincidencia como ID; si lo tiene, manda --capture-group"
+    )]
+    use_full_match: bool,
+
+    /// Usar la última coincidencia del regex en el nombre en vez de la primera
+    #[arg(
+        long,
+        global = true,
+        help = "Si el regex coincide varias veces en el nombre (ej: Show.S01E05.Part.of.S01E01-E10), tomar el ID de la última coincidencia"
+    )]
+    match_last: bool,
+
+    /// Emparejar por fecha de emisión en lugar de temporada/episodio
+    #[arg(
+        long,
+        value_enum,
+        help = "Emparejar por fecha de emisión: ymd (2024.03.15), dmy (15.03.2024) o mdy (03.15.2024); los grupos del regex siguen ese orden"
+    )]
+    date_format: Option<DateFormat>,
+
+    /// Emparejar por el CRC32 entre corchetes del nombre
+    #[arg(
+        long,
+        conflicts_with_all = ["srt_regex", "mkv_regex", "date_format"],
+        help = "Emparejar por el CRC32 entre corchetes que los grupos de fansub ponen en el nombre (ej: [A1B2C3D4]), igual en el video y en su subtítulo"
+    )]
+    match_crc: bool,
+
+    /// Usar el primer número del nombre como ID, sin regex
+    #[arg(
+        long,
+        conflicts_with_all = ["srt_regex", "mkv_regex", "date_format", "match_crc"],
+        help = "Tomar como ID el primer número del nombre (sin ceros a la izquierda: 'Ep 05' y 'Show 5' emparejan), sin escribir ningún regex; no mira la extensión"
+    )]
+    first_number: bool,
+
+    /// Detenerse en el primer error de renombrado
+    #[arg(
+        long,
+        help = "Detenerse en el primer error de renombrado en lugar de continuar con el resto"
+    )]
+    fail_fast: bool,
+
+    /// Impedir que dos instancias trabajen a la vez en el mismo directorio
+    #[arg(
+        long,
+        help = "Tomar un bloqueo consultivo sobre .sub_renamer.lock en --directory durante la ejecución; una segunda instancia sobre el mismo directorio se niega a continuar"
+    )]
+    concurrency_safe: bool,
+
+    /// Renombrar archivos de solo lectura conservando el atributo
+    #[arg(
+        long,
+        help = "Quitar temporalmente el atributo de solo lectura para renombrar (en Windows falla si no) y volver a ponerlo en el destino"
+    )]
+    handle_readonly: bool,
+
+    /// Reemplazar caracteres inválidos en los nombres de destino
+    #[arg(
+        long,
+        help = "Reemplazar en el nombre de destino los caracteres no válidos en este sistema y quitar puntos/espacios finales"
+    )]
+    sanitize_names: bool,
+
+    /// Carácter de reemplazo para --sanitize-names
+    #[arg(
+        long,
+        value_name = "C",
+        default_value_t = '_',
+        requires = "sanitize_names",
+        help = "Carácter con el que reemplazar los caracteres no válidos (por defecto '_')"
+    )]
+    sanitize_char: char,
+
+    /// Mostrar primero el resultado en dry-run y pedir confirmación para aplicarlo
+    #[arg(
+        long,
+        conflicts_with = "dry_run",
+        help = "Ejecutar primero en modo de prueba y preguntar si aplicar los cambios"
+    )]
+    review: bool,
+
+    /// Revisar el plan una operación a la vez, pudiendo cambiar el destino
+    #[arg(
+        long,
+        conflicts_with_all = ["dry_run", "interactive", "review"],
+        help = "Recorrer el plan operación por operación para aceptarla, omitirla o escribir otro nombre de destino; luego mostrar el resultado en dry-run y preguntar si aplicarlo"
+    )]
+    dry_run_interactive: bool,
+
+    /// Tomar el ID de los videos de su archivo de metadatos `<nombre>.json`
+    #[arg(
+        long,
+        help = "Para videos, usar los campos season/episode de <nombre>.json junto al video como ID (S01E05); si no existe se usa el regex"
+    )]
+    meta_json: bool,
+
+    /// Rechazar operaciones que muevan archivos a otro directorio
+    #[arg(
+        long = "rename-subtitles-in-place-only",
+        visible_alias = "in-place-only",
+        help = "Abortar sin renombrar nada si alguna operación movería un archivo fuera de su directorio original"
+    )]
+    in_place_only: bool,
+
+    /// Abortar si dos operaciones comparten destino
+    #[arg(
+        long,
+        help = "Abortar sin renombrar nada si dos o más operaciones tienen el mismo destino, listando los archivos en conflicto"
+    )]
+    fail_on_duplicate_targets: bool,
+
+    /// Conservar las mayúsculas de la extensión original del subtítulo
+    #[arg(
+        long,
+        help = "Conservar la extensión del subtítulo tal como está (ej: .SRT) en lugar de pasarla a minúsculas"
+    )]
+    keep_ext_case: bool,
+
+    /// Mostrar solo las primeras N operaciones del dry-run
+    #[arg(
+        long,
+        value_name = "N",
+        help = "En dry-run, mostrar solo las primeras N operaciones (el resumen sigue contando todas)"
+    )]
+    sample: Option<usize>,
+
+    /// Ancho de línea para mostrar las operaciones
+    #[arg(
+        long,
+        value_name = "N",
+        help = "Recortar por el medio (inicio…fin.ext) los nombres mostrados para que cada operación quepa en N columnas; por defecto $COLUMNS si la salida es una terminal, 0 para no recortar. Los renombrados usan siempre el nombre completo"
+    )]
+    preview_width: Option<usize>,
+
+    /// Tratar carpetas VobSub como un subtítulo
+    #[arg(
+        long,
+        conflicts_with_all = ["scan_archive", "from_manifest", "match_by_mtime"],
+        help = "Tratar cada carpeta con un .idx (VobSub de DVD: .idx, .sub y extras) como un único subtítulo: si su nombre coincide con el regex, la carpeta entera se renombra al nombre base del video"
+    )]
+    subtitle_dirs: bool,
+
+    /// Extraer subtítulos de archivos zip por episodio
+    #[arg(
+        long,
+        help = "Tratar los .zip cuyo nombre coincide con el regex de subtítulos como paquetes: extraer sus subtítulos con el nombre del video"
+    )]
+    zip_subs: bool,
+
+    /// Solo pasar a minúsculas las extensiones de los subtítulos
+    #[arg(
+        long,
+        conflicts_with_all = ["apply_plan", "rename_one", "scan_archive", "from_manifest"],
+        help = "Mantenimiento: pasar a minúsculas la extensión de todos los subtítulos analizados (Show.SRT -> Show.srt), sin emparejar con videos ni necesitar regex. Implica --fix-case"
+    )]
+    normalize_all_extensions: bool,
+
+    /// Planificar sobre el contenido de un archivo en vez del directorio
+    #[arg(
+        long,
+        value_name = "ARCHIVO",
+        conflicts_with_all = ["apply_plan", "rename_one", "zip_subs", "match_by_mtime", "only_missing", "check_writable", "subtitle_dirs"],
+        help = "Planificar sobre las entradas de un zip, tar o tar.gz sin extraer ni escribir nada; útil para reproducir problemas de emparejamiento. Los destinos existentes se buscan solo entre las entradas, no en el disco. Implica --dry-run"
+    )]
+    scan_archive: Option<PathBuf>,
+
+    /// Planificar sobre un listado JSON de archivos (para reproducir reportes)
+    #[arg(
+        long,
+        value_name = "ARCHIVO",
+        hide = true,
+        conflicts_with_all = ["scan_archive", "apply_plan", "rename_one", "zip_subs", "match_by_mtime", "only_missing", "check_writable", "subtitle_dirs"],
+        help = "Planificar sobre un arreglo JSON de entradas {\"path\", \"extension\"} relativas entre sí, sin leer el disco. Implica --dry-run"
+    )]
+    from_manifest: Option<PathBuf>,
+
+    /// Solo para pruebas: considerar que todos los destinos ya existen
+    #[arg(
+        long,
+        hide_short_help = true,
+        help = "[AVANZADO/PRUEBAS] Considerar que el destino de cada operación ya existe, para ejercitar el manejo de conflictos"
+    )]
+    assume_targets_exist: bool,
+
+    /// Base numérica de los IDs capturados
+    #[arg(
+        long,
+        value_name = "BASE",
+        global = true,
+        value_parser = clap::value_parser!(u32).range(2..=36),
+        help = "Interpretar cada grupo capturado como un número en BASE (ej: 16) y compararlo en decimal, sin ceros a la izquierda"
+    )]
+    numeric_base: Option<u32>,
+
+    /// Qué archivos dan el nombre y cuáles se renombran
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = Source::Videos,
+        help = "Origen de los nombres: videos (se renombran los subtítulos, por defecto) o subs (se renombran los videos con el nombre de su subtítulo)"
+    )]
+    source: Source,
+
+    /// Comprobar al final que cada archivo renombrado existe en su destino
+    #[arg(
+        long,
+        help = "Tras renombrar, comprobar que cada destino existe en disco; las discrepancias cuentan como errores y la salida termina con error"
+    )]
+    verify_after: bool,
+
+    /// Plantilla del nombre de destino
+    #[arg(
+        long,
+        value_name = "PLANTILLA",
+        conflicts_with = "template_file",
+        help = "Plantilla del nombre de destino, ej: '{video_stem}.{offset}.{ext}' (marcadores: video_stem, subtitle_stem, id, offset, ext); si falta un valor se usa el nombre por defecto"
+    )]
+    output_template: Option<String>,
+
+    /// Archivo con plantillas alternativas, una por línea
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Archivo con una plantilla por línea en orden de preferencia: se usa la primera cuyos marcadores tienen valor (# para comentarios)"
+    )]
+    template_file: Option<PathBuf>,
+
+    /// Eliminar los directorios que quedaron vacíos tras mover archivos
+    #[arg(
+        long,
+        help = "Tras renombrar, eliminar los directorios que esta ejecución dejó vacíos (nunca los que ya lo estaban)"
+    )]
+    prune_empty_dirs: bool,
+
+    /// Con varios videos del mismo episodio, usar el de mayor resolución
+    #[arg(
+        long,
+        help = "Si varios videos comparten episodio, emparejar con el de mayor resolución según su nombre (720p, 1080p, 2160p, 4K)"
+    )]
+    prefer_resolution: bool,
+
+    /// Código de salida cuando no hay nada que renombrar
+    #[arg(
+        long,
+        value_name = "CODE",
+        help = "Terminar con el código CODE (ej: 3) en vez de 0 cuando no haya ningún renombrado que hacer"
+    )]
+    no_op_exit_code: Option<u8>,
+
+    /// Emparejar por orden de fecha de modificación en vez de por nombre
+    #[arg(
+        long,
+        conflicts_with_all = ["zip_subs", "dry_run"],
+        help = "Último recurso: emparejar el N-ésimo subtítulo más antiguo con el N-ésimo video más antiguo (pide confirmación)"
+    )]
+    match_by_mtime: bool,
+
+    /// Sufijos de varias partes que se tratan como una sola extensión de subtítulo
+    #[arg(
+        long,
+        value_name = "EXTS",
+        help = "Sufijos compuestos separados por coma (ej: en.srt,es.srt): los archivos que terminan así son subtítulos aunque la última parte no esté en --srt-ext, y el sufijo entero se conserva en el nuevo nombre"
+    )]
+    compound_ext: Option<String>,
+
+    /// Mostrar cómo se procesa un archivo concreto, sin renombrar nada
+    #[arg(
+        long,
+        value_name = "FILENAME",
+        conflicts_with_all = ["apply_plan", "rename_one"],
+        help = "Mostrar paso a paso qué se decide para el archivo FILENAME (extensión, captura del regex, emparejamiento) y salir sin renombrar"
+    )]
+    explain: Option<String>,
+
+    /// Regex con el ruido que se quita del nombre antes de buscar el episodio
+    #[arg(
+        long,
+        value_name = "REGEX",
+        help = "Quitar del nombre lo que coincida con REGEX (ej: '\\d{3,4}p') antes de aplicar el regex de episodio: primero se limpia, después se busca"
+    )]
+    strip_before_match: Option<String>,
+
+    /// Mostrar la configuración efectiva y salir
+    #[arg(
+        long,
+        help = "Mostrar la configuración efectiva (cada opción con su valor, ya resuelto el idioma, el regex que falta y las opciones implícitas) y salir sin hacer nada"
+    )]
+    dump_config: bool,
+
+    /// Directorio de los subtítulos, si están separados de los videos
+    #[arg(
+        long,
+        value_name = "DIR",
+        conflicts_with = "colocated_only",
+        help = "Buscar los subtítulos solo en DIR (por defecto --directory); los renombrados se mueven junto a su video. Con --recursive se recorren ambos árboles completos"
+    )]
+    subs_dir: Option<PathBuf>,
+
+    /// Directorio de los videos, si están separados de los subtítulos
+    #[arg(
+        long,
+        value_name = "DIR",
+        conflicts_with = "colocated_only",
+        help = "Buscar los videos solo en DIR (por defecto --directory)"
+    )]
+    videos_dir: Option<PathBuf>,
+
+    /// Dejar los subtítulos en su árbol, replicando la ruta de su video
+    #[arg(
+        long,
+        requires = "subs_dir",
+        help = "En vez de mover el subtítulo junto a su video, dejarlo bajo --subs-dir en la misma ruta relativa que el video tiene bajo --videos-dir (ej: Video/Show/S01/x.mkv -> Subs/Show/S01/x.srt), creando los directorios que falten"
+    )]
+    mirror_tree: bool,
+
+    /// Descriptor de archivo donde emitir eventos JSON en vivo
+    #[arg(
+        long,
+        value_name = "N",
+        conflicts_with = "events_file",
+        help = "Emitir en el descriptor de archivo N un evento JSON por línea a medida que ocurren (para interfaces gráficas)"
+    )]
+    events_fd: Option<u32>,
+
+    /// Archivo o tubería con nombre donde emitir eventos JSON en vivo
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Como --events-fd, pero escribiendo en un archivo o una tubería con nombre"
+    )]
+    events_file: Option<PathBuf>,
+
+    /// Omitir sin avisar de conflicto los episodios que ya tienen su subtítulo
+    #[arg(
+        long,
+        help = "Si el destino ya existe y es un subtítulo del mismo episodio, omitir la operación como ya hecha en vez de tratarla como conflicto"
+    )]
+    ignore_already_subtitled: bool,
+
+    /// En dry-run, comprobar que se puede escribir en cada directorio de destino
+    #[arg(
+        long,
+        help = "En dry-run, probar a crear un archivo temporal en cada directorio de destino y marcar como fallidas las operaciones que no podrían escribirse"
+    )]
+    check_writable: bool,
+
+    /// Ejecutar los renombrados en paralelo si el plan no tiene conflictos
+    #[arg(
+        long,
+        help = "Renombrar en paralelo cuando el plan no tiene destinos repetidos, encadenados ni ya existentes; si no, se ejecuta en orden"
+    )]
+    parallel_execute: bool,
+
+    /// Temporada de los subtítulos nombrados solo con el número de episodio
+    #[arg(
+        long,
+        value_name = "N",
+        help = "Si el ID de un subtítulo es solo un número (ej: 05.srt), tomarlo como el episodio de la temporada N: S0NE05, o temporada y episodio unidos con --group-separator"
+    )]
+    assume_season: Option<u32>,
+
+    /// Desfase aplicado al número de episodio de los subtítulos
+    #[arg(
+        long,
+        value_name = "N",
+        allow_negative_numbers = true,
+        help = "Sumar N al número de episodio de cada subtítulo antes de emparejar (ej: con -1, el E06 del subtítulo empareja con el E05 del video). Se aplica a todos por igual: para proveedores que numeran distinto, no para corregir casos sueltos"
+    )]
+    episode_offset: Option<i64>,
+
+    /// Regex adicionales que aportan IDs alternativos a cada archivo
+    #[arg(
+        long,
+        value_name = "REGEX",
+        help = "Regex adicional (repetible) que da a subtítulos y videos otro ID candidato, ej: numeración absoluta; se emparejan si comparten cualquiera de sus IDs"
+    )]
+    alt_regex: Vec<String>,
+
+    /// Máximo de operaciones a ejecutar sin --force (recomendado)
+    #[arg(
+        long,
+        value_name = "N",
+        help = "Abortar antes de renombrar si el plan tiene más de N operaciones, salvo con --force (sin límite por defecto; se recomienda fijarlo)"
+    )]
+    max_ops: Option<usize>,
+
+    /// Nombrar como el subtítulo que ya acompaña al video, cambiando el idioma
+    #[arg(
+        long,
+        help = "Si junto al video ya hay un subtítulo del episodio con etiqueta de idioma (ej: 'Show S01E05.es.srt'), usar su nombre base para los demás subtítulos, con su propia etiqueta"
+    )]
+    sidecar_stem: bool,
+
+    /// Listar los IDs de episodio con varios subtítulos o videos y salir
+    #[arg(
+        long,
+        conflicts_with_all = ["apply_plan", "rename_one"],
+        help = "Listar cada ID de episodio que corresponde a más de un subtítulo o más de un video, con sus archivos, y salir sin renombrar"
+    )]
+    report_duplicates: bool,
+
+    /// Contar los valores capturados por cada grupo del regex y salir
+    #[arg(
+        long,
+        conflicts_with_all = ["apply_plan", "rename_one", "report_duplicates"],
+        help = "Mostrar cuántos subtítulos y videos produjo cada valor de cada grupo del regex (por nombre si lo tiene o por número), para ver episodios faltantes o IDs inesperados, y salir sin renombrar"
+    )]
+    tally: bool,
+
+    /// Procesar solo los videos que todavía no tienen ningún subtítulo
+    #[arg(
+        long,
+        help = "Omitir los videos que ya tienen al lado un subtítulo con su nombre base (de cualquier extensión de subtítulo), aunque haya otro candidato"
+    )]
+    only_missing: bool,
+
+    /// Prefijo para renombrar los subtítulos sin video
+    #[arg(
+        long,
+        value_name = "PREFIJO",
+        help = "Renombrar los subtítulos sin video a PREFIJO + nombre original (ej: UNMATCHED.) para agruparlos y revisarlos a mano; sin esta opción solo se avisa"
+    )]
+    mark_unmatched: Option<String>,
+
+    /// Renombrar cada directorio entero o nada
+    #[arg(
+        long,
+        conflicts_with = "mark_unmatched",
+        help = "Si algún subtítulo de un directorio no tiene video, no renombrar nada en ese directorio (todo o nada por carpeta)"
+    )]
+    abort_if_video_missing_for_any_subtitle_in_dir: bool,
+
+    /// Ejecutar aunque el plan supere --max-ops
+    #[arg(long, requires = "max_ops", help = "Ejecutar aunque el plan supere el límite de --max-ops")]
+    force: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Probar un regex contra nombres de ejemplo
+    #[command(about = "Probar un regex de episodio contra nombres de ejemplo y mostrar el ID extraído")]
+    ValidateRegex {
+        /// Regex a probar
+        pattern: String,
+
+        /// Nombres de archivo de ejemplo
+        #[arg(required = true)]
+        samples: Vec<String>,
+    },
+}
+
+/// Valida una proporción entre 0.0 y 1.0 para la CLI.
+fn parse_rate(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(rate) if (0.0..=1.0).contains(&rate) => Ok(rate),
+        // clap valida antes de conocerse --lang, así que el idioma es el del entorno
+        _ => Err(Msg::InvalidRate(value).text(Lang::from_env())),
+    }
+}
+
+/// Tamaño en bytes, con sufijo opcional K, M o G (en potencias de 1024).
+fn parse_size(value: &str) -> Result<u64, String> {
+    let trimmed = value.trim();
+    let upper = trimmed.to_uppercase();
+    let digits = upper.trim_end_matches("IB").trim_end_matches('B');
+    let (number, multiplier) = match digits.chars().last() {
+        Some('K') => (&digits[..digits.len() - 1], 1 << 10),
+        Some('M') => (&digits[..digits.len() - 1], 1 << 20),
+        Some('G') => (&digits[..digits.len() - 1], 1 << 30),
+        _ => (digits, 1),
+    };
+    number
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(multiplier))
+        .ok_or_else(|| Msg::InvalidSize(value).text(Lang::from_env()))
+}
+
+impl Args {
+    /// Indica si se proporcionó algún patrón con el que identificar archivos.
+    fn has_matcher(&self) -> bool {
+        self.srt_regex.is_some()
+            || self.mkv_regex.is_some()
+            || self.part_regex.is_some()
+            || self.date_format.is_some()
+            || self.match_crc
+            || self.first_number
+            || self.match_by_mtime
+            || !self.alt_regex.is_empty()
+    }
+
+    /// Activa las opciones que otras implican.
+    fn apply_implied(&mut self) {
+        // Las rutas de un archivo son virtuales: nunca se toca el disco
+        if self.scan_archive.is_some() || self.from_manifest.is_some() {
+            self.dry_run = true;
+        }
+        // Sus renombrados solo cambian mayúsculas: no tendría sentido omitirlos
+        if self.normalize_all_extensions {
+            self.fix_case = true;
+        }
+        // Los subtítulos de cada idioma viven en su carpeta; el destino es el video
+        if self.lang_from_dir {
+            self.match_video_dir = true;
+        }
+    }
+
+    /// Regex de subtítulos y de videos que se usan: si falta uno se toma el
+    /// otro y, si faltan ambos, el que implique el modo de emparejamiento.
+    fn effective_regexes(&self) -> (Option<&str>, Option<&str>) {
+        let default_re = self.date_format
+            .map(DateFormat::default_regex)
+            .or(self.match_crc.then_some(CRC_REGEX))
+            .or(self.first_number.then_some(FIRST_NUMBER_REGEX));
+        let srt = self.srt_regex.as_deref().or(self.mkv_regex.as_deref()).or(default_re);
+        let mkv = self.mkv_regex.as_deref().or(self.srt_regex.as_deref()).or(default_re);
+        (srt, mkv)
+    }
+
+    /// Regex de `--strip-before-match`, si se indicó.
+    fn strip_regex(&self, lang: Lang) -> Result<Option<Regex>> {
+        self.strip_before_match.as_ref()
+            .map(|re| Regex::new(re).with_context(|| Msg::InvalidStripRegex(re).text(lang)))
+            .transpose()
+    }
+
+    /// Aplica las normalizaciones previas al regex que estén activadas.
+    fn normalize_for_match<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let mut text = Cow::Borrowed(text);
+        if self.normalize_digits {
+            text = Cow::Owned(normalize_digits(&text));
+        }
+        if self.strip_accents && !text.is_ascii() {
+            text = Cow::Owned(strip_accents(&text));
+        }
+        text
+    }
+
+    /// Texto sobre el que se aplica el regex: normalizado y sin lo que quite
+    /// `strip` (el regex de `--strip-before-match`).
+    fn prepare_match_text<'a>(&self, strip: Option<&Regex>, text: &'a str) -> Cow<'a, str> {
+        let text = self.normalize_for_match(text);
+        match strip {
+            Some(strip) => Cow::Owned(strip.replace_all(&text, "").into_owned()),
+            None => text,
+        }
+    }
+
+    /// Compila un regex de episodio respetando `--ignore-case`.
+    fn episode_regex(&self, pattern: &str) -> Result<Regex, regex::Error> {
+        RegexBuilder::new(pattern).case_insensitive(self.ignore_case).build()
+    }
+
+    /// Falla si `--capture-group` no existe en `regex` (salvo con `--group-separator`).
+    fn check_capture_group(&self, regex: &Regex, lang: Lang) -> Result<()> {
+        if self.date_format.is_some() {
+            if regex.captures_len() < 4 {
+                anyhow::bail!(Msg::DateRegexGroups(regex.as_str()).text(lang));
+            }
+            return Ok(());
+        }
+        if self.group_separator.is_none() && !self.use_full_match && regex.captures_len() <= self.capture_group {
+            anyhow::bail!(Msg::CaptureGroupOutOfRange { regex: regex.as_str(), group: self.capture_group }.text(lang));
+        }
+        if let Some(group) = self.normalize_groups.iter().find(|g| !regex.capture_names().flatten().any(|name| name == *g)) {
+            anyhow::bail!(Msg::UnknownNormalizeGroup { group, regex: regex.as_str() }.text(lang));
+        }
+        Ok(())
+    }
+
+    /// ID de episodio capturado en `text` según `--date-format`, `--capture-group`
+    /// o `--group-separator`.
+    fn capture_episode_id(&self, regex: &Regex, text: &str) -> Option<String> {
+        let captures = if self.match_last { regex.captures_iter(text).last() } else { regex.captures(text) }?;
+        if self.match_crc {
+            return Some(captures.get(1)?.as_str().to_uppercase());
+        }
+        if self.first_number {
+            return Some(strip_leading_zeros(captures.get(1)?.as_str()).to_string());
+        }
+        if let Some(format) = self.date_format {
+            let group = |i| captures.get(i).map(|m| m.as_str());
+            return format.normalize([group(1)?, group(2)?, group(3)?]);
+        }
+        let group = |index: usize| Some(self.episode_group(regex, index, captures.get(index)?.as_str()));
+        match &self.group_separator {
+            Some(_) if self.use_full_match && captures.len() == 1 => group(0),
+            Some(separator) => {
+                let groups: Vec<String> = (1..captures.len()).filter_map(group).collect();
+                (!groups.is_empty()).then(|| groups.join(separator))
+            }
+            None if self.use_full_match && captures.len() <= self.capture_group => group(0),
+            None => group(self.capture_group),
+        }
+    }
+
+    /// Normaliza el valor del grupo `index` de `regex` según `--numeric-base`,
+    /// `--normalize-numbers` y `--normalize-groups`.
+    fn episode_group(&self, regex: &Regex, index: usize, value: &str) -> String {
+        let value = self.normalize_number(value);
+        let name = regex.capture_names().nth(index).flatten();
+        if self.normalize_numbers {
+            return strip_number_padding(&value);
+        }
+        if name.is_some_and(|name| self.normalize_groups.iter().any(|g| g == name)) {
+            return strip_leading_zeros(&value).to_string();
+        }
+        value
+    }
+
+    /// ID de `--assume-season` con la forma que `regex` (el de los videos) da a
+    /// sus claves: con `--group-separator`, temporada y episodio se unen como
+    /// dos grupos capturados; si no, `S0NE05`.
+    fn assumed_episode_id(&self, regex: &Regex, season: u32, episode: u32) -> String {
+        match &self.group_separator {
+            Some(separator) => [season, episode]
+                .iter()
+                .enumerate()
+                .map(|(i, value)| self.episode_group(regex, i + 1, &format!("{:02}", value)))
+                .collect::<Vec<_>>()
+                .join(separator),
+            None => format!("S{:02}E{:02}", season, episode),
+        }
+    }
+
+    /// Con `--numeric-base`, reescribe en decimal un grupo que sea un número en
+    /// esa base (`0x` opcional en base 16); si no lo es, lo deja igual.
+    fn normalize_number(&self, group: &str) -> String {
+        let Some(base) = self.numeric_base else {
+            return group.to_string();
+        };
+        let digits = match base {
+            16 => group.strip_prefix("0x").or_else(|| group.strip_prefix("0X")).unwrap_or(group),
+            _ => group,
+        };
+        u64::from_str_radix(digits, base).map_or_else(|_| group.to_string(), |n| n.to_string())
+    }
+
+    /// Indica si el modo elegido necesita escanear y emparejar con regex.
+    fn requires_matcher(&self) -> bool {
+        self.apply_plan.is_none() && self.rename_one.is_none() && !self.normalize_all_extensions
+    }
+}
+
+/// CRC32 entre corchetes de `--match-crc`: `[A1B2C3D4]`.
+const CRC_REGEX: &str = r"\[([0-9A-Fa-f]{8})\]";
+
+/// Primer número de `--first-number`: debe quedar un punto detrás, para no
+/// tomar el de la extensión (`.mp4`).
+const FIRST_NUMBER_REGEX: &str = r"(\d+).*\.";
+
+/// Resultado de una ejecución sin errores, para elegir el código de salida.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RunStatus {
+    Done,
+    /// No había operaciones que ejecutar
+    NothingToDo,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum EmitFormat {
+    /// `rename(1)` de util-linux: reemplaza DESDE por HASTA en la ruta
+    Rename,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum SortOrder {
+    /// Por ID de episodio, comparando los números por su valor; a igual ID, en el orden del plan
+    Natural,
+    /// En el orden del plan, que sigue la ruta de origen
+    Path,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    Text,
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum DateFormat {
+    /// Año, mes, día
+    Ymd,
+    /// Día, mes, año
+    Dmy,
+    /// Mes, día, año
+    Mdy,
+}
+
+/// Valor de `--show-key-regex` que toma como serie lo que precede a la fecha.
+const SHOW_KEY_AUTO: &str = "auto";
+
+/// Regex de serie para `--show-key-regex auto`: el texto antes de la fecha,
+/// sin los separadores que la preceden.
+fn show_before_date_regex(format: DateFormat) -> String {
+    format!(r"^(.+?)[-._ ]*(?:{})", format.default_regex())
+}
+
+impl DateFormat {
+    /// Regex por defecto cuando no se indica `--srt-regex` ni `--mkv-regex`.
+    fn default_regex(self) -> &'static str {
+        match self {
+            DateFormat::Ymd => r"(\d{4})[-._ ](\d{1,2})[-._ ](\d{1,2})",
+            DateFormat::Dmy | DateFormat::Mdy => r"(\d{1,2})[-._ ](\d{1,2})[-._ ](\d{4})",
+        }
+    }
+
+    /// Fecha normalizada `YYYY-MM-DD` a partir de los tres grupos en el orden
+    /// del formato, o `None` si no es una fecha válida.
+    fn normalize(self, groups: [&str; 3]) -> Option<String> {
+        let [a, b, c] = groups.map(|g| g.parse::<u32>().ok());
+        let (year, month, day) = match self {
+            DateFormat::Ymd => (a?, b?, c?),
+            DateFormat::Dmy => (c?, b?, a?),
+            DateFormat::Mdy => (c?, a?, b?),
+        };
+        if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+            return None;
+        }
+        Some(format!("{:04}-{:02}-{:02}", year, month, day))
+    }
+}
+
+/// Rol de cada conjunto de archivos: el origen aporta el nombre base y el otro
+/// conjunto se renombra conservando su extensión.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Source {
+    /// Los subtítulos toman el nombre de su video
+    Videos,
+    /// Los videos toman el nombre de su subtítulo; con varios subtítulos por
+    /// episodio manda el primero en orden alfabético
+    Subs,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum NumberFormat {
+    /// `{stem}.2.{ext}`
+    Dot,
+    /// `{stem} (2).{ext}`
+    Paren,
+}
+
+#[derive(Debug, Clone)]
+struct FileInfo {
+    path: PathBuf,
+    episode_id: String,
+    /// Extensión tal como aparece en el nombre del archivo
+    extension: String,
+    /// Extensión en minúsculas, para comparar con las listas de extensiones
+    normalized_extension: String,
+    /// Entrada dentro de `path` cuando el subtítulo viene en un zip
+    archive_entry: Option<String>,
+    /// Otros IDs candidatos (`--alt-regex`), además de `episode_id`
+    alt_ids: Vec<String>,
+}
+
+impl FileInfo {
+    fn new(path: PathBuf, episode_id: String) -> Self {
+        let extension = path.extension()
+            .map(|ext| ext.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let normalized_extension = extension.to_lowercase();
+        FileInfo { path, episode_id, extension, normalized_extension, archive_entry: None, alt_ids: Vec::new() }
+    }
+
+    /// Subtítulo `entry` dentro del zip `archive`; la extensión es la de la entrada.
+    fn in_archive(archive: PathBuf, entry: String, episode_id: String) -> Self {
+        let extension = Path::new(&entry).extension()
+            .map(|ext| ext.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let normalized_extension = extension.to_lowercase();
+        FileInfo { path: archive, episode_id, extension, normalized_extension, archive_entry: Some(entry), alt_ids: Vec::new() }
+    }
+
+    /// Archivo con los IDs candidatos `ids`: el primero es el principal.
+    fn with_candidates(path: PathBuf, mut ids: Vec<String>) -> Option<Self> {
+        if ids.is_empty() {
+            return None;
+        }
+        let episode_id = ids.remove(0);
+        Some(FileInfo { alt_ids: ids, ..FileInfo::new(path, episode_id) })
+    }
+
+    /// Sustituye la extensión detectada, para los sufijos compuestos.
+    fn with_extension(mut self, extension: String) -> Self {
+        self.normalized_extension = extension.to_lowercase();
+        self.extension = extension;
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+struct RenameOperation {
+    from: PathBuf,
+    to: PathBuf,
+    episode_id: String,
+    /// Si se indica, `from` es un zip y se extrae esta entrada en `to`
+    archive_entry: Option<String>,
+}
+
+#[derive(Debug, Default)]
+struct RenamePlan {
+    operations: Vec<RenameOperation>,
+    unmatched: Vec<PathBuf>,
+    /// Subtítulos con ID considerados al planificar
+    subtitles: usize,
+    /// Pares (subtítulo, video) emparejados, aunque no haga falta renombrar
+    matches: Vec<(PathBuf, PathBuf)>,
+}
+
+/// Un plan reducido a lo que compara `--diff-last`, con rutas relativas al directorio.
+#[derive(Debug, Default)]
+struct PlanSnapshot {
+    operations: BTreeSet<(String, String)>,
+    unmatched: BTreeSet<String>,
+}
+
+impl PlanSnapshot {
+    fn from_json(plan: &Json) -> Option<Self> {
+        let operations = plan.get("operations")?
+            .as_array()?
+            .iter()
+            .map(|op| Some((op.get("from")?.as_str()?.to_string(), op.get("to")?.as_str()?.to_string())))
+            .collect::<Option<_>>()?;
+        // Los planes de versiones anteriores no guardaban los huérfanos
+        let unmatched = plan.get("unmatched")
+            .and_then(Json::as_array)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|path| path.as_str().map(str::to_string))
+            .collect();
+        Some(PlanSnapshot { operations, unmatched })
+    }
+
+    /// Destinos a los que irían varios archivos.
+    fn conflicts(&self) -> BTreeSet<&str> {
+        let mut seen = HashSet::new();
+        self.operations.iter().map(|(_, to)| to.as_str()).filter(|to| !seen.insert(*to)).collect()
+    }
+}
+
+/// Respuesta del usuario en la revisión interactiva.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ReviewAnswer {
+    Yes,
+    No,
+    All,
+    OneByOne,
+    Quit,
+}
+
+impl ReviewAnswer {
+    /// Interpreta la primera letra de la respuesta, solo entre las que muestra
+    /// el prompt en `lang`: en inglés `s` no significa "sí".
+    fn parse(line: &str, lang: Lang) -> Option<Self> {
+        match (lang, line.trim().chars().next()?.to_ascii_lowercase()) {
+            (Lang::Es, 's') | (Lang::En, 'y') => Some(ReviewAnswer::Yes),
+            (_, 'n') => Some(ReviewAnswer::No),
+            (Lang::Es, 't') | (Lang::En, 'a') => Some(ReviewAnswer::All),
+            (Lang::Es, 'u') | (Lang::En, 'o') => Some(ReviewAnswer::OneByOne),
+            (_, 'q') => Some(ReviewAnswer::Quit),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OperationStatus {
+    Renamed,
+    DryRun,
+    Skipped,
+    /// Omitida porque el destino ya es el subtítulo de ese episodio
+    AlreadyDone,
+    Failed,
+}
+
+impl OperationStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            OperationStatus::Renamed => "renamed",
+            OperationStatus::DryRun => "dry_run",
+            OperationStatus::Skipped => "skipped",
+            OperationStatus::AlreadyDone => "already_done",
+            OperationStatus::Failed => "failed",
+        }
+    }
+}
+
+#[derive(Debug)]
+struct OperationOutcome {
+    op: RenameOperation,
+    status: OperationStatus,
+    error: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct StatusCounts {
+    succeeded: usize,
+    skipped: usize,
+    errors: usize,
+}
+
+impl StatusCounts {
+    fn add(&mut self, status: OperationStatus) {
+        match status {
+            OperationStatus::Renamed | OperationStatus::DryRun => self.succeeded += 1,
+            OperationStatus::Skipped | OperationStatus::AlreadyDone => self.skipped += 1,
+            OperationStatus::Failed => self.errors += 1,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+struct RunSummary {
+    succeeded: usize,
+    skipped: usize,
+    errors: usize,
+    unmatched: usize,
+    duration: Duration,
+    /// Contadores por extensión de origen (en minúsculas; vacía para directorios)
+    by_extension: BTreeMap<String, StatusCounts>,
+}
+
+impl RunSummary {
+    fn from_outcomes(outcomes: &[OperationOutcome], unmatched: usize) -> Self {
+        let mut summary = RunSummary {
+            unmatched,
+            ..Default::default()
+        };
+        let mut totals = StatusCounts::default();
+        for outcome in outcomes {
+            totals.add(outcome.status);
+            // Lo renombrado ya no está en `from`: si era una carpeta, se ve en `to`
+            let current = match outcome.status {
+                OperationStatus::Renamed => &outcome.op.to,
+                _ => &outcome.op.from,
+            };
+            let extension = outcome.op.from.extension()
+                .filter(|_| !current.is_dir())
+                .map(|ext| ext.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
+            summary.by_extension.entry(extension).or_default().add(outcome.status);
+        }
+        summary.succeeded = totals.succeeded;
+        summary.skipped = totals.skipped;
+        summary.errors = totals.errors;
+        summary
+    }
+
+    fn to_json(&self) -> Json {
+        Json::object([
+            ("succeeded", Json::from(self.succeeded)),
+            ("skipped", Json::from(self.skipped)),
+            ("errors", Json::from(self.errors)),
+            ("unmatched", Json::from(self.unmatched)),
+            ("duration_seconds", Json::from(self.duration.as_secs_f64())),
+        ])
+    }
+
+    /// Contadores por extensión como objeto JSON `{ext: {succeeded, skipped, errors}}`.
+    fn extensions_to_json(&self) -> Json {
+        Json::object(self.by_extension.iter().map(|(ext, counts)| {
+            let counts = Json::object([
+                ("succeeded", Json::from(counts.succeeded)),
+                ("skipped", Json::from(counts.skipped)),
+                ("errors", Json::from(counts.errors)),
+            ]);
+            (ext.clone(), counts)
+        }))
+    }
+
+    /// Tabla alineada con los contadores por extensión y el total.
+    fn to_table(&self, lang: Lang) -> String {
+        let row = |label: String, counts: StatusCounts| {
+            vec![label, counts.succeeded.to_string(), counts.skipped.to_string(), counts.errors.to_string()]
+        };
+        let mut rows = vec![
+            [Msg::TableExtension, Msg::TableSucceeded, Msg::TableSkipped, Msg::TableErrors]
+                .iter()
+                .map(|msg| msg.text(lang))
+                .collect(),
+        ];
+        for (ext, counts) in &self.by_extension {
+            let label = if ext.is_empty() { "-".to_string() } else { format!(".{}", ext) };
+            rows.push(row(label, *counts));
+        }
+        let totals = StatusCounts { succeeded: self.succeeded, skipped: self.skipped, errors: self.errors };
+        rows.push(row(Msg::TableTotal.text(lang), totals));
+        format_table(&rows)
+    }
+
+    /// Serializa los contadores en el formato de exposición de texto de Prometheus.
+    /// Los `# HELP` van siempre en inglés, como en los exportadores habituales:
+    /// el archivo lo lee un recolector, y no debe cambiar según el idioma de la
+    /// ejecución que lo escribió.
+    fn to_prometheus(&self, dry_run: bool) -> String {
+        let metrics: [(&str, &str, String); 6] = [
+            ("renamed", "Subtitles renamed (or that would be renamed in dry-run)", self.succeeded.to_string()),
+            ("skipped", "Operations skipped", self.skipped.to_string()),
+            ("errors", "Operations that failed", self.errors.to_string()),
+            ("unmatched", "Subtitles without a matching video", self.unmatched.to_string()),
+            ("duration_seconds", "Run duration in seconds", self.duration.as_secs_f64().to_string()),
+            ("dry_run", "1 if the run was a dry-run", u8::from(dry_run).to_string()),
+        ];
+
+        let mut out = String::new();
+        for (name, help, value) in metrics {
+            out.push_str(&format!("# HELP sub_renamer_{} {}\n", name, help));
+            out.push_str(&format!("# TYPE sub_renamer_{} gauge\n", name));
+            out.push_str(&format!("sub_renamer_{} {}\n", name, value));
+        }
+        out
+    }
+}
+
+/// Versión del formato de archivo de `--plan-out`.
+const PLAN_VERSION: u64 = 1;
+
+struct SubtitleRenamer {
+    args: Args,
+    lang: Lang,
+    srt_regex: Option<Regex>,
+    mkv_regex: Option<Regex>,
+    part_regex: Option<Regex>,
+    show_key_regex: Option<Regex>,
+    strip_regex: Option<Regex>,
+    alt_regexes: Vec<Regex>,
+    video_hashes: Option<HashMap<String, String>>,
+    /// `--title-map`: (título normalizado, ID), los títulos más largos primero
+    title_map: Vec<(String, String)>,
+    srt_extensions: Vec<String>,
+    video_extensions: Vec<String>,
+    /// `--compound-ext`, de más largo a más corto para reconocer el sufijo más específico
+    compound_extensions: Vec<String>,
+    history_path: Option<PathBuf>,
+    /// Plantillas de `--output-template` o `--template-file`, en orden de preferencia
+    templates: Vec<Template>,
+    /// Ancho máximo de cada nombre mostrado, según `--preview-width`
+    name_width: Option<usize>,
+    /// Destino de la salida estándar (sustituible en los tests)
+    stdout: RefCell<Box<dyn io::Write>>,
+    /// Flujo de `--events-fd`/`--events-file`
+    events: Option<RefCell<Box<dyn io::Write>>>,
+    /// Reglas de cada `.subrenamer.toml` encontrado, por directorio
+    dir_rules: RefCell<HashMap<PathBuf, Rc<DirRules>>>,
+    /// Rutas de `--scan-archive` o `--from-manifest`, que sustituyen al disco
+    /// al comprobar si un destino existe
+    virtual_files: RefCell<HashSet<PathBuf>>,
+    /// `--concurrency-safe`: el bloqueo se libera al cerrar el archivo
+    _lock: Option<fs::File>,
+}
+
+/// Reglas de un `.subrenamer.toml` ya validadas. Lo que no define se toma de
+/// la línea de comandos.
+struct DirRules {
+    srt_regex: Option<Regex>,
+    mkv_regex: Option<Regex>,
+    srt_extensions: Option<Vec<String>>,
+    video_extensions: Option<Vec<String>>,
+}
+
+impl SubtitleRenamer {
+    fn new(mut args: Args) -> Result<Self> {
+        let lang = args.lang.unwrap_or_else(Lang::from_env);
+        args.apply_implied();
+
+        // Validar que al menos un regex esté presente
+        if args.requires_matcher() && !args.has_matcher() {
+            anyhow::bail!(Msg::NoMatcher.text(lang));
+        }
+
+        // Usar el regex disponible como fallback
+        let (srt_re_str, mkv_re_str) = args.effective_regexes();
+
+        let srt_regex = srt_re_str
+            .map(|re| args.episode_regex(re).with_context(|| Msg::InvalidSubtitleRegex(re).text(lang)))
+            .transpose()?;
+
+        let mkv_regex = mkv_re_str
+            .map(|re| args.episode_regex(re).with_context(|| Msg::InvalidVideoRegex(re).text(lang)))
+            .transpose()?;
+
+        let alt_regexes = args.alt_regex
+            .iter()
+            .map(|re| args.episode_regex(re).with_context(|| Msg::InvalidAltRegex(re).text(lang)))
+            .collect::<Result<Vec<_>>>()?;
+
+        for regex in srt_regex.iter().chain(&mkv_regex).chain(&alt_regexes) {
+            args.check_capture_group(regex, lang)?;
+        }
+
+        let part_regex = args.part_regex.as_ref()
+            .map(|re| Regex::new(re).with_context(|| Msg::InvalidPartRegex(re).text(lang)))
+            .transpose()?;
+
+        let show_key_regex = match args.show_key_regex.as_deref() {
+            Some(SHOW_KEY_AUTO) => {
+                let format = args.date_format.ok_or_else(|| anyhow::anyhow!(Msg::ShowKeyAutoNeedsDate.text(lang)))?;
+                Some(Regex::new(&show_before_date_regex(format))?)
+            }
+            re => re
+                .map(|re| Regex::new(re).with_context(|| Msg::InvalidShowKeyRegex(re).text(lang)))
+                .transpose()?,
+        };
+
+        let strip_regex = args.strip_regex(lang)?;
+
+        let video_hashes = args.video_hash_manifest.as_ref()
+            .map(|path| {
+                let content = fs::read_to_string(path)
+                    .with_context(|| Msg::ReadHashManifest(path).text(lang))?;
+                parse_hash_manifest(&content, lang)
+                    .with_context(|| Msg::InvalidHashManifest(path).text(lang))
+            })
+            .transpose()?;
+
+        let mut title_map = match &args.title_map {
+            Some(path) => {
+                let content = fs::read_to_string(path).with_context(|| Msg::ReadTitleMap(path).text(lang))?;
+                parse_title_map(&content).map_err(|(line, content)| {
+                    anyhow::anyhow!(Msg::TitleMapLine { path, line, content: &content }.text(lang))
+                })?
+            }
+            None => Vec::new(),
+        };
+        title_map.sort_by_key(|(title, _)| std::cmp::Reverse(title.len()));
+
+        let srt_extensions = Self::parse_extensions(&args.srt_ext);
+        let video_extensions = Self::parse_extensions(&args.video_ext);
+        let mut compound_extensions = Self::parse_extensions(args.compound_ext.as_deref().unwrap_or_default());
+        compound_extensions.sort_by_key(|ext| std::cmp::Reverse(ext.len()));
+
+        // Una extensión en ambas listas haría ambigua la clasificación
+        let overlapping: Vec<&str> = srt_extensions
+            .iter()
+            .filter(|ext| video_extensions.contains(ext))
+            .map(String::as_str)
+            .collect();
+        if !overlapping.is_empty() {
+            anyhow::bail!(Msg::OverlappingExtensions(&overlapping.join(", ")).text(lang));
+        }
+
+        if let Some(prefix) = &args.mark_unmatched
+            && (prefix.is_empty() || prefix.chars().any(is_invalid_name_char))
+        {
+            anyhow::bail!(Msg::InvalidMarkPrefix(prefix).text(lang));
+        }
+
+        if args.sanitize_names && is_invalid_name_char(args.sanitize_char) {
+            anyhow::bail!(Msg::InvalidSanitizeChar(args.sanitize_char).text(lang));
+        }
+
+        // Validar que el directorio existe
+        for dir in [Some(&args.directory), args.subs_dir.as_ref(), args.videos_dir.as_ref()].into_iter().flatten() {
+            if !dir.exists() {
+                anyhow::bail!(Msg::DirectoryNotFound(dir).text(lang));
+            }
+        }
+
+        // El historial se valida al inicio para no descubrir al final que no se puede escribir
+        let history_path = match (&args.history_file, args.history) {
+            (Some(path), _) => Some(path.clone()),
+            (None, true) => Some(history::default_path().with_context(|| Msg::NoHistoryLocation.text(lang))?),
+            (None, false) => None,
+        };
+        if let Some(path) = &history_path {
+            history::open(path).with_context(|| Msg::HistoryNotWritable(path).text(lang))?;
+        }
+
+        let templates = Self::load_templates(&args, lang)?;
+
+        let lock = args.concurrency_safe
+            .then(|| acquire_lock(&args.directory.join(LOCK_FILE_NAME), lang))
+            .transpose()?;
+
+        let events_path = match (args.events_fd, &args.events_file) {
+            (Some(fd), _) => Some(events::fd_path(fd).with_context(|| Msg::EventsFdUnsupported.text(lang))?),
+            (None, Some(path)) => Some(path.clone()),
+            (None, None) => None,
+        };
+        let events = events_path
+            .map(|path| events::open(&path).with_context(|| Msg::OpenEvents(&path).text(lang)))
+            .transpose()?
+            .map(RefCell::new);
+
+        let name_width = args.preview_width
+            .or_else(terminal_width)
+            .filter(|&width| width > 0)
+            .map(|width| (width.saturating_sub(PREVIEW_LINE_OVERHEAD) / 2).max(MIN_NAME_WIDTH));
+
+        Ok(Self {
+            args,
+            lang,
+            srt_regex,
+            mkv_regex,
+            part_regex,
+            show_key_regex,
+            strip_regex,
+            alt_regexes,
+            video_hashes,
+            title_map,
+            srt_extensions,
+            video_extensions,
+            compound_extensions,
+            history_path,
+            templates,
+            name_width,
+            stdout: RefCell::new(Box::new(io::stdout())),
+            events,
+            dir_rules: RefCell::new(HashMap::new()),
+            virtual_files: RefCell::new(HashSet::new()),
+            _lock: lock,
+        })
+    }
+
+    /// Plantillas de `--output-template` o `--template-file`, validando que solo
+    /// usen marcadores conocidos.
+    fn load_templates(args: &Args, lang: Lang) -> Result<Vec<Template>> {
+        let templates = if let Some(source) = &args.output_template {
+            vec![Template::parse(source)
+                .map_err(|e| anyhow::anyhow!(Msg::InvalidTemplate { template: source, error: e.to_string() }.text(lang)))?]
+        } else if let Some(path) = &args.template_file {
+            let content = fs::read_to_string(path)
+                .with_context(|| Msg::ReadTemplateFile(path).text(lang))?;
+            template::parse_list(&content).map_err(|(line, e)| {
+                anyhow::anyhow!(Msg::InvalidTemplateLine { path, line, error: e.to_string() }.text(lang))
+            })?
+        } else {
+            Vec::new()
+        };
+
+        for template in &templates {
+            if let Some(token) = template.tokens().find(|token| !TEMPLATE_TOKENS.contains(token)) {
+                anyhow::bail!(Msg::UnknownTemplateToken {
+                    token,
+                    template: template.source(),
+                    known: TEMPLATE_TOKENS.join(", "),
+                }
+                .text(lang));
+            }
+        }
+        Ok(templates)
+    }
+
+    /// Texto para el usuario por stdout. Con `--format json` va a stderr, para
+    /// que stdout contenga únicamente el documento JSON.
+    fn say(&self, text: &str) {
+        if self.args.format == OutputFormat::Json {
+            eprintln!("{}", text);
+        } else {
+            // Un stdout cerrado (ej: `| head`) no debe interrumpir los renombrados
+            let _ = writeln!(self.stdout.borrow_mut(), "{}", text);
+        }
+    }
+
+    /// Emite `event` si hay un flujo de eventos. Un lector que se cierra no
+    /// debe interrumpir los renombrados.
+    fn emit(&self, event: Event) {
+        if let Some(events) = &self.events {
+            let _ = events::write(&mut **events.borrow_mut(), &event);
+        }
+    }
+
+    /// Añade `outcome` a `outcomes` y lo emite como `op-done` u `op-error`.
+    fn push_outcome(&self, outcomes: &mut Vec<OperationOutcome>, outcome: OperationOutcome) {
+        let (from, to) = (outcome.op.from.as_path(), outcome.op.to.as_path());
+        self.emit(match &outcome.error {
+            Some(error) => Event::OpError { from, to, error },
+            None => Event::OpDone { from, to, status: outcome.status.as_str() },
+        });
+        outcomes.push(outcome);
+    }
+
+    /// Mensaje informativo por stdout, salvo en modo silencioso.
+    fn info(&self, msg: Msg) {
+        if !self.args.quiet {
+            self.say(&msg.text(self.lang));
+        }
+    }
+
+    /// Detalle por stdout, solo en modo verbose.
+    fn detail(&self, msg: Msg) {
+        if self.args.verbose {
+            self.say(&msg.text(self.lang));
+        }
+    }
+
+    /// Aviso por stderr, salvo en modo silencioso.
+    fn warn(&self, msg: Msg) {
+        if !self.args.quiet {
+            eprintln!("{}", msg.text(self.lang));
+        }
+    }
+
+    /// Error por stderr, visible incluso en modo silencioso.
+    fn error(&self, msg: Msg) {
+        eprintln!("{}", msg.text(self.lang));
+    }
+
+    /// Lista de extensiones en minúsculas y sin punto inicial, para compararlas
+    /// con las de los archivos (que también se pasan a minúsculas).
+    fn parse_extensions(ext_str: &str) -> Vec<String> {
+        ext_str
+            .split(',')
+            .map(|s| s.trim().trim_start_matches('.').to_lowercase())
+            .filter(|s| !s.is_empty())
+            .collect()
+    }
+
+    /// Con `--subs-dir`/`--videos-dir` los subtítulos y los videos están en
+    /// directorios distintos, y cada subtítulo se mueve junto a su video.
+    fn split_dirs(&self) -> bool {
+        self.args.subs_dir.is_some() || self.args.videos_dir.is_some()
+    }
+
+    /// `--mirror-tree`: directorio bajo `--subs-dir` con la misma ruta relativa
+    /// que el de `video` bajo `--videos-dir`.
+    fn mirrored_dir(&self, video: &Path) -> PathBuf {
+        let dir = parent_dir(video);
+        let relative = dir.strip_prefix(self.videos_root()).unwrap_or(Path::new(""));
+        self.subs_root().join(relative)
+    }
+
+    fn subs_root(&self) -> &Path {
+        self.virtual_root().or(self.args.subs_dir.as_deref()).unwrap_or(&self.args.directory)
+    }
+
+    fn videos_root(&self) -> &Path {
+        self.virtual_root().or(self.args.videos_dir.as_deref()).unwrap_or(&self.args.directory)
+    }
+
+    /// Raíz de las rutas virtuales: el propio archivo o manifiesto. Al ser un
+    /// archivo normal, nada por debajo de él puede existir en el disco.
+    fn virtual_root(&self) -> Option<&Path> {
+        self.args.scan_archive.as_deref().or(self.args.from_manifest.as_deref())
+    }
+
+    /// Si `path` existe: entre las rutas virtuales si las hay, o en el disco.
+    fn path_exists(&self, path: &Path) -> bool {
+        match self.virtual_root() {
+            Some(_) => self.virtual_files.borrow().contains(path),
+            None => path.exists(),
+        }
+    }
+
+    fn get_files(&self) -> Result<Vec<PathBuf>> {
+        let virtual_files = if let Some(archive) = &self.args.scan_archive {
+            let names = archive::list_any(archive).with_context(|| Msg::ScanArchive(archive).text(self.lang))?;
+            Some(names.iter().map(|name| archive.join(name)).collect::<Vec<_>>())
+        } else if let Some(manifest) = &self.args.from_manifest {
+            Some(self.load_file_manifest(manifest)?)
+        } else {
+            None
+        };
+        if let Some(mut files) = virtual_files {
+            files.sort();
+            *self.virtual_files.borrow_mut() = files.iter().cloned().collect();
+            return Ok(files);
+        }
+        if !self.split_dirs() {
+            return self.get_files_in(&self.args.directory);
+        }
+        let mut files = self.get_files_in(self.subs_root())?;
+        files.extend(self.get_files_in(self.videos_root())?);
+        files.sort();
+        files.dedup();
+        Ok(files)
+    }
+
+    /// Rutas virtuales de `--from-manifest`: cada entrada `{"path", "extension"}`
+    /// se une al manifiesto, añadiendo la extensión si la ruta no la trae.
+    fn load_file_manifest(&self, path: &Path) -> Result<Vec<PathBuf>> {
+        let invalid = |reason: &str| anyhow::anyhow!(Msg::InvalidFileManifest { path, reason }.text(self.lang));
+        let content = fs::read_to_string(path).with_context(|| Msg::ReadFileManifest(path).text(self.lang))?;
+        let manifest = json::from_str(&content).map_err(|e| invalid(&e.to_string()))?;
+        let entries = manifest.as_array().ok_or_else(|| invalid(&Msg::ManifestExpectedArray.text(self.lang)))?;
+
+        let mut files = Vec::with_capacity(entries.len());
+        for entry in entries {
+            let name = entry.get("path").and_then(Json::as_str).ok_or_else(|| invalid(&Msg::ManifestMissingPath.text(self.lang)))?;
+            let mut name = name.to_string();
+            if let Some(extension) = entry.get("extension").and_then(Json::as_str) {
+                let extension = extension.trim_start_matches('.');
+                let has_extension = Path::new(&name).extension()
+                    .and_then(OsStr::to_str)
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case(extension));
+                if !has_extension && !extension.is_empty() {
+                    name = format!("{}.{}", name, extension);
+                }
+            }
+            files.push(path.join(name));
+        }
+        Ok(files)
+    }
+
+    fn get_files_in(&self, root: &Path) -> Result<Vec<PathBuf>> {
+        let mut files = Vec::new();
+        
+        if self.args.recursive {
+            let walker = WalkDir::new(root)
+                .into_iter()
+                .filter_entry(|e| e.depth() == 0 || !self.is_pruned(e));
+            for entry in walker {
+                match entry {
+                    Ok(e) if e.file_type().is_file() => {
+                        files.push(e.path().to_path_buf());
+                    }
+                    Ok(_) => {} // Ignorar directorios
+                    Err(e) => self.warn(Msg::FileAccess(e.to_string())),
+                }
+            }
+        } else {
+            let dir_entries = fs::read_dir(root)
+                .with_context(|| Msg::ReadDirectory(root).text(self.lang))?;
+            
+            for entry in dir_entries {
+                match entry {
+                    Ok(e) if e.file_type().is_ok_and(|ft| ft.is_file()) => {
+                        files.push(e.path());
+                    }
+                    Ok(_) => {} // Ignorar directorios
+                    Err(e) => self.warn(Msg::FileAccess(e.to_string())),
+                }
+            }
+        }
+
+        Ok(files)
+    }
+
+    /// Indica si `entry` es un directorio que coincide con algún `--prune`.
+    fn is_pruned(&self, entry: &walkdir::DirEntry) -> bool {
+        if !entry.file_type().is_dir() {
+            return false;
+        }
+        let name = entry.file_name().to_string_lossy().to_lowercase();
+        let pruned = self.args.prune
+            .iter()
+            .any(|pattern| glob_match(&pattern.to_lowercase(), &name));
+        if pruned {
+            self.detail(Msg::PruningDirectory(entry.path()));
+        }
+        pruned
+    }
+
+    /// Nombre de archivo sobre el que se aplican los regex, ya normalizado.
+    fn match_name<'a>(&self, path: &'a Path) -> Option<Cow<'a, str>> {
+        let file_name = path.file_name()?.to_str()?;
+        Some(self.normalize_for_match(file_name))
+    }
+
+    fn normalize_for_match<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        self.args.normalize_for_match(text)
+    }
+
+    fn extract_episode_id(&self, path: &Path, is_subtitle: bool) -> Option<String> {
+        if is_subtitle
+            && self.args.ass_title_match
+            && let Some(id) = read_ass_title(path).and_then(|title| self.capture_id(path, &title, true))
+        {
+            return Some(id);
+        }
+
+        if !is_subtitle
+            && self.args.meta_json
+            && let Some(id) = self.meta_json_id(path)
+        {
+            return Some(id);
+        }
+
+        let file_name = path.file_name()?.to_str()?;
+        self.capture_id(path, file_name, is_subtitle)
+    }
+
+    /// ID `S01E05` según los campos `season` y `episode` del JSON que acompaña
+    /// al video (`<nombre>.json`).
+    fn meta_json_id(&self, path: &Path) -> Option<String> {
+        let sidecar = path.with_extension("json");
+        let content = fs::read_to_string(&sidecar).ok()?;
+        let meta = match json::from_str(&content) {
+            Ok(meta) => meta,
+            Err(e) => {
+                self.warn(Msg::InvalidMetaJson { path: &sidecar, error: e.to_string() });
+                return None;
+            }
+        };
+
+        let number = |key: &str| match meta.get(key)? {
+            Json::Number(n) if *n >= 0.0 && n.fract() == 0.0 => Some(*n as u64),
+            Json::String(s) => s.trim().parse::<u64>().ok(),
+            _ => None,
+        };
+        Some(format!("S{:02}E{:02}", number("season")?, number("episode")?))
+    }
+
+    fn prepare_match_text<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        self.args.prepare_match_text(self.strip_regex.as_ref(), text)
+    }
+
+    /// Aplica `f` al regex de episodio que corresponde a `path`.
+    fn with_episode_regex<T>(&self, path: &Path, is_subtitle: bool, f: impl FnOnce(&Regex) -> Option<T>) -> Option<T> {
+        // Un regex de .subrenamer.toml sirve para ambos tipos si solo define uno
+        let rules = self.dir_rules_for(path);
+        let local = rules.as_deref().and_then(|rules| {
+            let (own, other) = if is_subtitle { (&rules.srt_regex, &rules.mkv_regex) } else { (&rules.mkv_regex, &rules.srt_regex) };
+            own.as_ref().or(other.as_ref())
+        });
+        match local {
+            Some(regex) => f(regex),
+            None => f(if is_subtitle { &self.srt_regex } else { &self.mkv_regex }.as_ref()?),
+        }
+    }
+
+    /// ID capturado por el regex de subtítulos o videos sobre `text`.
+    fn capture_id(&self, path: &Path, text: &str, is_subtitle: bool) -> Option<String> {
+        let text = self.prepare_match_text(text);
+        let id = self.with_episode_regex(path, is_subtitle, |regex| {
+            self.report_matched_groups(path, regex, &text);
+            self.args.capture_episode_id(regex, &text)
+        })?;
+        if is_subtitle
+            && let Some(season) = self.args.assume_season
+            && let Ok(episode) = id.parse::<u32>()
+        {
+            let id = self.with_episode_regex(path, false, |regex| Some(self.args.assumed_episode_id(regex, season, episode)))?;
+            return self.offset_episode(path, id);
+        }
+        if is_subtitle { self.offset_episode(path, id) } else { Some(id) }
+    }
+
+    /// En modo verbose, muestra qué grupos del regex capturaron algo (índice,
+    /// nombre si lo tiene y valor), para saber qué alternativa de `A|B|C` coincidió.
+    fn report_matched_groups(&self, path: &Path, regex: &Regex, text: &str) {
+        if !self.args.verbose {
+            return;
+        }
+        let captures = if self.args.match_last { regex.captures_iter(text).last() } else { regex.captures(text) };
+        let Some(captures) = captures else { return };
+        let groups: Vec<String> = regex
+            .capture_names()
+            .enumerate()
+            .skip(1)
+            .filter_map(|(index, name)| {
+                let value = captures.get(index).map(|m| m.as_str()).filter(|value| !value.is_empty())?;
+                Some(match name {
+                    Some(name) => format!("{} ({})={:?}", index, name, value),
+                    None => format!("{}={:?}", index, value),
+                })
+            })
+            .collect();
+        if !groups.is_empty() {
+            self.detail(Msg::MatchedGroups { name: file_name(path), groups: groups.join(", ") });
+        }
+    }
+
+    /// Aplica `--episode-offset` al último número de `id`, conservando su ancho
+    /// (`S01E06` con -1 queda `S01E05`).
+    fn offset_episode(&self, path: &Path, id: String) -> Option<String> {
+        let Some(offset) = self.args.episode_offset.filter(|&offset| offset != 0) else {
+            return Some(id);
+        };
+        let end = id.rfind(|c: char| c.is_ascii_digit())? + 1;
+        let start = id[..end].rfind(|c: char| !c.is_ascii_digit()).map_or(0, |i| i + 1);
+        let digits = &id[start..end];
+        let shifted = digits.parse::<i64>().ok()?.checked_add(offset).filter(|&n| n >= 0);
+        let Some(shifted) = shifted else {
+            self.detail(Msg::EpisodeOffsetOutOfRange { name: file_name(path), id: &id });
+            return None;
+        };
+        Some(format!("{}{:0width$}{}", &id[..start], shifted, &id[end..], width = digits.len()))
+    }
+
+    /// Extrae el número de parte con `--part-regex`, sin ceros a la izquierda.
+    fn extract_part(&self, path: &Path) -> Option<String> {
+        let file_name = self.match_name(path)?;
+        let part = self.part_regex.as_ref()?
+            .captures(&file_name)?
+            .get(1)?
+            .as_str();
+        let trimmed = part.trim_start_matches('0');
+        Some(if trimmed.is_empty() { "0" } else { trimmed }.to_lowercase())
+    }
+
+    /// ID canónico de un video según `--video-hash-manifest`, si su hash está registrado.
+    fn video_manifest_id(&self, path: &Path) -> Option<String> {
+        let hashes = self.video_hashes.as_ref()?;
+        match hash::file_crc32(path, self.args.hash_prefix_bytes) {
+            Ok(crc) => {
+                let id = hashes.get(&format!("{:08x}", crc)).cloned();
+                if id.is_none() {
+                    self.detail(Msg::HashNotInManifest { crc, name: file_name(path) });
+                }
+                id
+            }
+            Err(e) => {
+                self.warn(Msg::HashFailed { path, error: e.to_string() });
+                None
+            }
+        }
+    }
+
+    /// Serie según `--show-key-regex`, normalizada para que `Breaking.Bad` y
+    /// `breaking bad` coincidan.
+    fn extract_show_key(&self, path: &Path) -> Option<String> {
+        let file_name = self.match_name(path)?;
+        let show = self.show_key_regex.as_ref()?
+            .captures(&file_name)?
+            .get(1)?
+            .as_str();
+        let key = show.split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(str::to_lowercase)
+            .collect::<Vec<_>>()
+            .join(" ");
+        (!key.is_empty()).then_some(key)
+    }
+
+    /// Clave de emparejamiento: el ID de episodio combinado con la parte, si la
+    /// hay, y precedido por la serie con `--show-key-regex`.
+    fn extract_match_key(&self, path: &Path, is_subtitle: bool) -> Option<String> {
+        let manifest_id = if is_subtitle { None } else { self.video_manifest_id(path) };
+        let key = match manifest_id {
+            Some(id) => id,
+            None => match (self.extract_episode_id(path, is_subtitle), self.extract_part(path)) {
+                (Some(id), Some(part)) => format!("{}.part{}", id, part),
+                (Some(id), None) => id,
+                (None, Some(part)) => format!("part{}", part),
+                (None, None) => return None,
+            },
+        };
+
+        if self.show_key_regex.is_none() {
+            return Some(key);
+        }
+        Some(format!("{}:{}", self.extract_show_key(path)?, key))
+    }
+
+    /// IDs candidatos de `path`: primero la clave normal y después, sin
+    /// repetir, los capturados por cada `--alt-regex`.
+    fn candidate_ids(&self, path: &Path, is_subtitle: bool) -> Vec<String> {
+        let mut ids: Vec<String> = self.extract_match_key(path, is_subtitle).into_iter().collect();
+        if ids.is_empty()
+            && is_subtitle
+            && let Some(id) = self.title_map_id(path)
+        {
+            self.detail(Msg::TitleMapped { name: file_name(path), id: &id });
+            ids.push(id);
+        }
+        let Some(name) = self.match_name(path).filter(|_| !self.alt_regexes.is_empty()) else {
+            return ids;
+        };
+        for regex in &self.alt_regexes {
+            let Some(id) = self.args.capture_episode_id(regex, &name) else { continue };
+            let id = match &self.show_key_regex {
+                Some(_) => match self.extract_show_key(path) {
+                    Some(show) => format!("{}:{}", show, id),
+                    None => continue,
+                },
+                None => id,
+            };
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+        ids
+    }
+
+    /// ID de `--title-map` para un subtítulo cuyo nombre contiene el título del
+    /// episodio como palabras completas, sin distinguir mayúsculas ni signos.
+    fn title_map_id(&self, path: &Path) -> Option<String> {
+        if self.title_map.is_empty() {
+            return None;
+        }
+        let name = format!(" {} ", title_words(path.file_stem()?.to_str()?));
+        self.title_map
+            .iter()
+            .find(|(title, _)| name.contains(&format!(" {} ", title)))
+            .map(|(_, id)| id.clone())
+    }
+
+    /// Lee y valida los `.subrenamer.toml` que hay entre `files`.
+    fn load_dir_rules(&self, files: &[PathBuf]) -> Result<()> {
+        let mut dir_rules = self.dir_rules.borrow_mut();
+        for path in files.iter().filter(|path| file_name(path) == dirconfig::FILE_NAME) {
+            let invalid = |error: String| anyhow::anyhow!(Msg::InvalidDirConfig { path, error }.text(self.lang));
+            let content = fs::read_to_string(path).map_err(|e| invalid(e.to_string()))?;
+            let config = dirconfig::parse(&content).map_err(|e| invalid(e.text(self.lang)))?;
+            let compile = |pattern: Option<String>| -> Result<Option<Regex>> {
+                let Some(pattern) = pattern else { return Ok(None) };
+                let regex = self.args.episode_regex(&pattern).map_err(|e| invalid(e.to_string()))?;
+                self.args.check_capture_group(&regex, self.lang)?;
+                Ok(Some(regex))
+            };
+            let rules = DirRules {
+                srt_regex: compile(config.srt_regex)?,
+                mkv_regex: compile(config.mkv_regex)?,
+                srt_extensions: config.srt_ext,
+                video_extensions: config.video_ext,
+            };
+            dir_rules.insert(parent_dir(path).to_path_buf(), Rc::new(rules));
+        }
+        Ok(())
+    }
+
+    /// `--subtitle-dirs`: carpetas bajo la raíz de subtítulos que contienen un
+    /// `.idx`. Sin `--recursive` solo se miran las del primer nivel, y no se
+    /// buscan otras dentro de una que ya lo es.
+    fn vobsub_dirs(&self) -> Vec<PathBuf> {
+        let max_depth = if self.args.recursive { usize::MAX } else { 1 };
+        let has_idx = |dir: &Path| {
+            fs::read_dir(dir).is_ok_and(|entries| {
+                entries.flatten().any(|entry| {
+                    entry.path().extension().and_then(OsStr::to_str).is_some_and(|ext| ext.eq_ignore_ascii_case("idx"))
+                })
+            })
+        };
+
+        let mut dirs = Vec::new();
+        let mut walker = WalkDir::new(self.subs_root())
+            .min_depth(1)
+            .max_depth(max_depth)
+            .into_iter()
+            .filter_entry(|e| !self.is_pruned(e));
+        while let Some(entry) = walker.next() {
+            let Ok(entry) = entry else { continue };
+            if entry.file_type().is_dir() && has_idx(entry.path()) {
+                dirs.push(entry.into_path());
+                walker.skip_current_dir();
+            }
+        }
+        dirs
+    }
+
+    /// Reglas del `.subrenamer.toml` más cercano a `path`, si hay alguno.
+    fn dir_rules_for(&self, path: &Path) -> Option<Rc<DirRules>> {
+        let dir_rules = self.dir_rules.borrow();
+        if dir_rules.is_empty() {
+            return None;
+        }
+        parent_dir(path).ancestors().find_map(|dir| dir_rules.get(dir).cloned())
+    }
+
+    fn categorize_files(&self) -> Result<(Vec<FileInfo>, Vec<FileInfo>)> {
+        let mut subtitles = Vec::new();
+        let mut videos = Vec::new();
+
+        let files = self.get_files()?;
+        // Los `.subrenamer.toml` de un archivo o manifiesto no se pueden leer sin extraerlo
+        if self.virtual_root().is_none() {
+            self.load_dir_rules(&files)?;
+        }
+        if self.args.match_by_mtime {
+            return self.categorize_by_mtime(files);
+        }
+
+        let units = if self.args.subtitle_dirs { self.vobsub_dirs() } else { Vec::new() };
+        for dir in &units {
+            let ids = self.candidate_ids(dir, true);
+            subtitles.extend(FileInfo::with_candidates(dir.clone(), ids).map(|file| file.with_extension(String::new())));
+        }
+
+        for path in files {
+            // Lo que hay dentro de una carpeta VobSub viaja con ella
+            if units.iter().any(|dir| path.starts_with(dir)) {
+                continue;
+            }
+            if let Some(extension) = path.extension()
+                .and_then(OsStr::to_str)
+                .map(str::to_lowercase)
+            {
+                let rules = self.dir_rules_for(&path);
+                let srt_extensions = rules.as_ref().and_then(|r| r.srt_extensions.as_ref()).unwrap_or(&self.srt_extensions);
+                let video_extensions = rules.as_ref().and_then(|r| r.video_extensions.as_ref()).unwrap_or(&self.video_extensions);
+                let is_subtitle = self.compound_extension(&path).is_some() || srt_extensions.contains(&extension);
+                if (is_subtitle || video_extensions.contains(&extension)) && self.outside_size_limits(&path, is_subtitle) {
+                    continue;
+                }
+                if let Some(compound) = self.compound_extension(&path) {
+                    let ids = self.candidate_ids(&path, true);
+                    if let Some(file) = FileInfo::with_candidates(path, ids) {
+                        subtitles.push(file.with_extension(compound));
+                    }
+                } else if self.args.zip_subs && extension == "zip" {
+                    subtitles.extend(self.archive_subtitles(&path));
+                } else if srt_extensions.contains(&extension) {
+                    if path.starts_with(self.subs_root()) {
+                        let ids = self.candidate_ids(&path, true);
+                        subtitles.extend(FileInfo::with_candidates(path, ids));
+                    }
+                } else if video_extensions.contains(&extension) && path.starts_with(self.videos_root()) {
+                    let ids = self.candidate_ids(&path, false);
+                    videos.extend(FileInfo::with_candidates(path, ids));
+                }
+            }
+        }
+
+        // Error típico: anclar el regex como si el nombre fuera solo el ID
+        let mut hinted = HashSet::new();
+        for (found, regex) in [(subtitles.len(), &self.srt_regex), (videos.len(), &self.mkv_regex)] {
+            if found == 0
+                && let Some(regex) = regex
+                && is_anchored(regex.as_str())
+                && hinted.insert(regex.as_str())
+            {
+                self.warn(Msg::AnchoredRegexHint(regex.as_str()));
+            }
+        }
+
+        self.detail(Msg::FoundFiles { subtitles: subtitles.len(), videos: videos.len() });
+
+        Ok((subtitles, videos))
+    }
+
+    /// `--max-subtitle-size` y `--min-video-size`: descarta archivos que por su
+    /// tamaño difícilmente son lo que dice su extensión.
+    fn outside_size_limits(&self, path: &Path, is_subtitle: bool) -> bool {
+        let limit = if is_subtitle { self.args.max_subtitle_size } else { self.args.min_video_size };
+        let Some(limit) = limit else {
+            return false;
+        };
+        // Con --scan-archive o --from-manifest no hay tamaños que leer
+        let Ok(size) = fs::metadata(path).map(|m| m.len()) else {
+            return false;
+        };
+        let name = file_name(path);
+        if is_subtitle && size > limit {
+            self.warn(Msg::SubtitleTooLarge { name, size, max: limit });
+            return true;
+        }
+        if !is_subtitle && size < limit {
+            self.warn(Msg::VideoTooSmall { name, size, min: limit });
+            return true;
+        }
+        false
+    }
+
+    /// Subtítulos dentro de un zip cuyo nombre coincide con el regex de subtítulos,
+    /// con las extensiones que valen en el directorio del zip.
+    fn archive_subtitles(&self, path: &Path) -> Vec<FileInfo> {
+        let Some(episode_id) = self.extract_match_key(path, true) else {
+            return Vec::new();
+        };
+        let entries = match archive::list(path) {
+            Ok(entries) => entries,
+            Err(e) => {
+                self.warn(Msg::ReadArchive { path, error: e.to_string() });
+                return Vec::new();
+            }
+        };
+
+        let rules = self.dir_rules_for(path);
+        let srt_extensions = rules.as_ref().and_then(|r| r.srt_extensions.as_ref()).unwrap_or(&self.srt_extensions);
+        entries
+            .into_iter()
+            .filter(|entry| {
+                Path::new(entry).extension()
+                    .and_then(OsStr::to_str)
+                    .is_some_and(|ext| srt_extensions.contains(&ext.to_lowercase()))
+            })
+            .map(|entry| FileInfo::in_archive(path.to_path_buf(), entry, episode_id.clone()))
+            .collect()
+    }
+
+    fn plan_renames(&self, subtitles: Vec<FileInfo>, mut videos: Vec<FileInfo>) -> RenamePlan {
+        if self.args.source == Source::Subs {
+            return self.plan_video_renames(subtitles, videos);
+        }
+
+        // --only-missing: episodios cuyos videos ya están subtitulados
+        let mut covered: HashSet<String> = HashSet::new();
+        if self.args.only_missing {
+            videos.retain(|video| {
+                if !self.has_sidecar_subtitle(&video.path) {
+                    return true;
+                }
+                self.detail(Msg::VideoAlreadySubtitled(file_name(&video.path)));
+                covered.insert(video.episode_id.clone());
+                false
+            });
+        }
+
+        // Sin --prefer-resolution gana el último video de cada episodio
+        let replaces = |candidate: &FileInfo, current: &FileInfo| {
+            !self.args.prefer_resolution
+                || (resolution_rank(&candidate.path), std::cmp::Reverse(&candidate.path))
+                    > (resolution_rank(&current.path), std::cmp::Reverse(&current.path))
+        };
+        let mut video_map: HashMap<String, &FileInfo> = HashMap::new();
+        let mut colocated_map: HashMap<(&Path, &str), &FileInfo> = HashMap::new();
+        for video in &videos {
+            video_map
+                .entry(video.episode_id.clone())
+                .and_modify(|current| if replaces(video, current) { *current = video })
+                .or_insert(video);
+            colocated_map
+                .entry((parent_dir(&video.path), video.episode_id.as_str()))
+                .and_modify(|current| if replaces(video, current) { *current = video })
+                .or_insert(video);
+        }
+        // IDs alternativos: solo se usan si no hay un video con ese ID principal
+        let mut alt_map: HashMap<&str, &FileInfo> = HashMap::new();
+        let mut colocated_alt_map: HashMap<(&Path, &str), &FileInfo> = HashMap::new();
+        for video in &videos {
+            for id in &video.alt_ids {
+                alt_map.entry(id).or_insert(video);
+                colocated_alt_map.entry((parent_dir(&video.path), id)).or_insert(video);
+            }
+        }
+        if self.args.prefer_resolution {
+            for video in &videos {
+                let chosen = if self.args.colocated_only {
+                    colocated_map[&(parent_dir(&video.path), video.episode_id.as_str())]
+                } else {
+                    video_map[&video.episode_id]
+                };
+                if chosen.path != video.path {
+                    self.warn(Msg::DuplicateVideoSkipped {
+                        id: &video.episode_id,
+                        chosen: file_name(&chosen.path),
+                        skipped: file_name(&video.path),
+                    });
+                }
+            }
+        }
+
+        // --sidecar-stem: nombre base en uso (sin idioma) por directorio y episodio.
+        // Solo sirve de modelo un subtítulo con etiqueta junto a su video y cuyo
+        // nombre base no añade palabras al del video (así una descarga nueva como
+        // `Addic7ed.S01E05.en` no se impone); entre varios, el primero alfabéticamente
+        let mut sidecar_stems: HashMap<(&Path, &str), (&Path, &str)> = HashMap::new();
+        if self.args.sidecar_stem {
+            for subtitle in subtitles.iter().filter(|s| s.archive_entry.is_none()) {
+                let Some(stem) = subtitle.path.file_stem().and_then(OsStr::to_str) else { continue };
+                let dir = parent_dir(&subtitle.path);
+                let video = if self.args.colocated_only {
+                    colocated_map.get(&(dir, subtitle.episode_id.as_str()))
+                } else {
+                    video_map.get(&subtitle.episode_id)
+                };
+                let Some(video_stem) = video
+                    .filter(|video| parent_dir(&video.path) == dir)
+                    .and_then(|video| video.path.file_stem())
+                    .and_then(OsStr::to_str)
+                else {
+                    continue;
+                };
+                if let Some((base, _)) = split_language_tag(stem)
+                    && is_sidecar_of(base, video_stem, &subtitle.episode_id)
+                {
+                    sidecar_stems
+                        .entry((parent_dir(&subtitle.path), subtitle.episode_id.as_str()))
+                        .and_modify(|current| {
+                            if subtitle.path.as_path() < current.0 {
+                                *current = (&subtitle.path, base);
+                            }
+                        })
+                        .or_insert((&subtitle.path, base));
+                }
+            }
+        }
+
+        let mut plan = RenamePlan {
+            subtitles: subtitles.len(),
+            ..Default::default()
+        };
+        // Candidatos para --rename-dir: (directorio, episodio, nombre del video)
+        let mut episode_dirs: Vec<(&Path, &str, &str)> = Vec::new();
+
+        for subtitle in &subtitles {
+            let dir = parent_dir(&subtitle.path);
+            let matched = std::iter::once(&subtitle.episode_id).chain(&subtitle.alt_ids).find_map(|id| {
+                if self.args.colocated_only {
+                    colocated_map.get(&(dir, id.as_str())).or_else(|| colocated_alt_map.get(&(dir, id.as_str())))
+                } else {
+                    video_map.get(id).or_else(|| alt_map.get(id.as_str()))
+                }
+            });
+
+            if let Some(video) = matched {
+                plan.matches.push((subtitle.path.clone(), video.path.clone()));
+                let video_stem = video.path.file_stem()
+                    .and_then(OsStr::to_str)
+                    .unwrap_or("unknown");
+                
+                if self.args.sanity_check
+                    && let Some(subtitle_stem) = subtitle.path.file_stem().and_then(OsStr::to_str)
+                    && name_similarity(subtitle_stem, video_stem, &subtitle.episode_id)
+                        .is_some_and(|similarity| similarity < MIN_NAME_SIMILARITY)
+                {
+                    self.warn(Msg::SuspiciousMatch {
+                        subtitle: file_name(&subtitle.path),
+                        video: file_name(&video.path),
+                        id: &subtitle.episode_id,
+                    });
+                }
+
+                let sidecar = sidecar_stems.get(&(parent_dir(&video.path), subtitle.episode_id.as_str()));
+                // El subtítulo que sirve de modelo ya tiene el nombre en uso
+                if sidecar.is_some_and(|(template, _)| *template == subtitle.path.as_path()) {
+                    continue;
+                }
+                let new_name = match sidecar {
+                    Some((_, base)) => {
+                        let stem = subtitle.path.file_stem().and_then(OsStr::to_str).unwrap_or_default();
+                        match split_language_tag(stem) {
+                            Some((_, tag)) => self.subtitle_name(&format!("{}.{}", base, tag), subtitle),
+                            None => self.subtitle_name(base, subtitle),
+                        }
+                    }
+                    None => self.subtitle_name(video_stem, subtitle),
+                };
+                let target_dir = if self.args.mirror_tree {
+                    self.mirrored_dir(&video.path)
+                } else if self.split_dirs() || self.args.match_video_dir {
+                    parent_dir(&video.path).to_path_buf()
+                } else {
+                    parent_dir(&subtitle.path).to_path_buf()
+                };
+                let new_path = target_dir.join(&new_name);
+
+                // Evitar renombrar a sí mismo
+                if subtitle.path != new_path {
+                    plan.operations.push(RenameOperation {
+                        from: subtitle.path.clone(),
+                        to: new_path,
+                        episode_id: subtitle.episode_id.clone(),
+                        archive_entry: subtitle.archive_entry.clone(),
+                    });
+                }
+
+                let dir = parent_dir(&subtitle.path);
+                if self.args.rename_dir
+                    && dir == parent_dir(&video.path)
+                    && !episode_dirs.iter().any(|(d, _, _)| *d == dir)
+                {
+                    episode_dirs.push((dir, &subtitle.episode_id, video_stem));
+                }
+            } else if covered.contains(&subtitle.episode_id) {
+                continue;
+            } else {
+                let (id, name) = (subtitle.episode_id.as_str(), file_name(&subtitle.path));
+                if self.args.colocated_only && video_map.contains_key(&subtitle.episode_id) {
+                    self.info(Msg::VideoInOtherDirectory { id, name });
+                } else {
+                    self.info(Msg::NoVideoForEpisode { id, name });
+                }
+                plan.unmatched.push(subtitle.path.clone());
+
+                if let Some(prefix) = &self.args.mark_unmatched
+                    && subtitle.archive_entry.is_none()
+                    && let Some(name) = name.to_str()
+                    && !name.starts_with(prefix.as_str())
+                {
+                    plan.operations.push(RenameOperation {
+                        from: subtitle.path.clone(),
+                        to: parent_dir(&subtitle.path).join(format!("{}{}", prefix, name)),
+                        episode_id: subtitle.episode_id.clone(),
+                        archive_entry: None,
+                    });
+                }
+            }
+        }
+
+        if self.args.abort_if_video_missing_for_any_subtitle_in_dir {
+            let mut missing: BTreeMap<&Path, usize> = BTreeMap::new();
+            for path in &plan.unmatched {
+                *missing.entry(parent_dir(path)).or_default() += 1;
+            }
+            for (&dir, &count) in &missing {
+                let skipped = plan.operations.iter().filter(|op| parent_dir(&op.from) == dir).count();
+                self.info(Msg::IncompleteDirectorySkipped { dir: self.relative_to_root(dir), missing: count, skipped });
+            }
+            plan.operations.retain(|op| !missing.contains_key(parent_dir(&op.from)));
+            episode_dirs.retain(|(dir, _, _)| !missing.contains_key(dir));
+        }
+
+        // Los directorios se renombran al final, cuando ya no quedan archivos por mover dentro
+        for (dir, episode_id, video_stem) in episode_dirs {
+            let new_dir = dir.with_file_name(video_stem);
+            if dir == self.args.directory || dir.file_name().is_none() || new_dir == dir {
+                continue;
+            }
+            if !self.is_single_episode_dir(dir, episode_id) {
+                self.info(Msg::DirectoryHasOtherFiles(dir));
+                continue;
+            }
+            plan.operations.push(RenameOperation {
+                from: dir.to_path_buf(),
+                to: new_dir,
+                episode_id: episode_id.to_string(),
+                archive_entry: None,
+            });
+        }
+
+        plan
+    }
+
+    /// `--source subs`: renombra cada video al nombre base de su subtítulo.
+    fn plan_video_renames(&self, subtitles: Vec<FileInfo>, videos: Vec<FileInfo>) -> RenamePlan {
+        let key = |file: &FileInfo| {
+            let dir = self.args.colocated_only.then(|| parent_dir(&file.path).to_path_buf());
+            (dir, file.episode_id.clone())
+        };
+        // Con varios subtítulos por episodio manda el primero en orden alfabético,
+        // para que el resultado no dependa del orden del directorio
+        let mut subtitle_map: HashMap<(Option<PathBuf>, String), &FileInfo> = HashMap::new();
+        for subtitle in subtitles.iter().filter(|s| s.archive_entry.is_none()) {
+            subtitle_map
+                .entry(key(subtitle))
+                .and_modify(|current| {
+                    if subtitle.path < current.path {
+                        *current = subtitle;
+                    }
+                })
+                .or_insert(subtitle);
+        }
+
+        let mut plan = RenamePlan {
+            subtitles: videos.len(),
+            ..Default::default()
+        };
+        for video in &videos {
+            let Some(subtitle) = subtitle_map.get(&key(video)) else {
+                self.info(Msg::NoSubtitleForVideo { id: &video.episode_id, name: file_name(&video.path) });
+                plan.unmatched.push(video.path.clone());
+                continue;
+            };
+
+            plan.matches.push((subtitle.path.clone(), video.path.clone()));
+            let stem = subtitle.path.file_stem().and_then(OsStr::to_str).unwrap_or("unknown");
+            let new_path = parent_dir(&video.path).join(format!("{}.{}", stem, video.extension));
+            if video.path != new_path {
+                plan.operations.push(RenameOperation {
+                    from: video.path.clone(),
+                    to: new_path,
+                    episode_id: video.episode_id.clone(),
+                    archive_entry: None,
+                });
+            }
+        }
+        plan
+    }
+
+    /// Nombre de destino de `subtitle` para el video con nombre base `video_stem`.
+    /// Con plantillas se usa la primera aplicable; si ninguna lo es, el nombre por defecto.
+    fn subtitle_name(&self, video_stem: &str, subtitle: &FileInfo) -> String {
+        let video_stem = if self.args.sanitize_names {
+            sanitize_name(video_stem, self.args.sanitize_char)
+        } else {
+            video_stem.to_string()
+        };
+        let subtitle_stem = subtitle.path.file_stem().and_then(OsStr::to_str);
+        let offset = subtitle_stem.and_then(extract_offset);
+        let extension = if self.args.keep_ext_case {
+            &subtitle.extension
+        } else {
+            &subtitle.normalized_extension
+        };
+
+        let dir_language = self.dir_language(&subtitle.path);
+
+        if !self.templates.is_empty() {
+            let mut values = HashMap::from([
+                ("video_stem", video_stem.clone()),
+                ("id", subtitle.episode_id.clone()),
+            ]);
+            let language = subtitle_stem.and_then(split_language_tag).map(|(_, tag)| tag).or(dir_language.as_deref());
+            let optional = [
+                ("subtitle_stem", subtitle_stem),
+                ("offset", offset),
+                ("lang", language),
+                ("ext", Some(extension.as_str())),
+            ];
+            for (token, value) in optional {
+                if let Some(value) = value.filter(|v| !v.is_empty()) {
+                    values.insert(token, value.to_string());
+                }
+            }
+            if let Some(name) = template::render_first(&self.templates, &values) {
+                return name;
+            }
+        }
+
+        let mut name = video_stem;
+        if self.args.preserve_offset && let Some(offset) = offset {
+            name.push('.');
+            name.push_str(offset);
+        }
+        if let Some(language) = dir_language {
+            name.push('.');
+            name.push_str(&language);
+        }
+        if !extension.is_empty() {
+            name.push('.');
+            name.push_str(extension);
+        }
+        name
+    }
+
+    /// Idioma de `--lang-from-dir`: el nombre de la carpeta del propio subtítulo
+    /// o, dentro de una carpeta `subs`/`subtitles`, la carpeta con idioma más
+    /// cercana por debajo de ella (`subs/eng/forced`). Nunca sale del directorio
+    /// de subtítulos.
+    fn dir_language(&self, path: &Path) -> Option<String> {
+        if !self.args.lang_from_dir {
+            return None;
+        }
+        let relative = parent_dir(path).strip_prefix(self.subs_root()).ok()?;
+        let names: Vec<&str> = relative.components().filter_map(|component| component.as_os_str().to_str()).collect();
+        let below_subs = names
+            .iter()
+            .rposition(|name| ["subs", "subtitles"].contains(&name.to_lowercase().as_str()))
+            .map_or(&names[names.len().saturating_sub(1)..], |subs| &names[subs + 1..]);
+        below_subs.iter().rev().find_map(|name| dir_language_tag(name))
+    }
+
+    /// Plan de una sola operación para `--rename-one`, sin escanear ni usar regex.
+    fn plan_single(&self, subtitle: &Path, video: &Path) -> Result<RenamePlan> {
+        for path in [subtitle, video] {
+            if !path.is_file() {
+                anyhow::bail!(Msg::RenameOneMissing(path).text(self.lang));
+            }
+        }
+
+        let info = FileInfo::new(
+            subtitle.to_path_buf(),
+            self.extract_match_key(subtitle, true).unwrap_or_default(),
+        );
+        let video_stem = video.file_stem()
+            .and_then(OsStr::to_str)
+            .unwrap_or("unknown");
+        let new_path = parent_dir(subtitle).join(self.subtitle_name(video_stem, &info));
+
+        let mut plan = RenamePlan {
+            subtitles: 1,
+            ..Default::default()
+        };
+        if new_path != subtitle {
+            plan.operations.push(RenameOperation {
+                from: info.path,
+                to: new_path,
+                episode_id: info.episode_id,
+                archive_entry: None,
+            });
+        }
+        Ok(plan)
+    }
+
+    /// Sufijo de `--compound-ext` con el que termina el nombre de `path`, tal
+    /// como aparece en el nombre. Tiene que quedar algo delante del sufijo.
+    fn compound_extension(&self, path: &Path) -> Option<String> {
+        let name = path.file_name()?.to_str()?;
+        self.compound_extensions.iter().find_map(|ext| {
+            let start = name.len().checked_sub(ext.len())?;
+            let suffix = name.get(start..)?;
+            let stem = &name[..start];
+            (suffix.to_lowercase() == *ext && stem.len() > 1 && stem.ends_with('.')).then(|| suffix.to_string())
+        })
+    }
+
+    /// `--explain`: traza de las decisiones tomadas para el archivo `name`.
+    fn explain(&self, name: &str, plan: &RenamePlan) -> Result<()> {
+        let path = self.get_files()?
+            .into_iter()
+            .find(|path| path.file_name().is_some_and(|n| n == name) || path.ends_with(name))
+            .with_context(|| Msg::ExplainNotFound(name).text(self.lang))?;
+        self.say(&Msg::ExplainFile(&path).text(self.lang));
+
+        let extension = path.extension().and_then(OsStr::to_str).map(str::to_lowercase).unwrap_or_default();
+        let compound = self.compound_extension(&path);
+        let rules = self.dir_rules_for(&path);
+        let srt_extensions = rules.as_ref().and_then(|r| r.srt_extensions.as_ref()).unwrap_or(&self.srt_extensions);
+        let video_extensions = rules.as_ref().and_then(|r| r.video_extensions.as_ref()).unwrap_or(&self.video_extensions);
+        let (list, is_subtitle) = if compound.is_some() {
+            ("--compound-ext", true)
+        } else if srt_extensions.contains(&extension) {
+            ("--srt-ext", true)
+        } else if video_extensions.contains(&extension) {
+            ("--video-ext", false)
+        } else {
+            self.say(&Msg::ExplainIgnoredExtension(&extension).text(self.lang));
+            return Ok(());
+        };
+        let extension = compound.unwrap_or(extension);
+        self.say(&Msg::ExplainExtension { extension: &extension, list }.text(self.lang));
+
+        let regex = if is_subtitle { &self.srt_regex } else { &self.mkv_regex };
+        let pattern = regex.as_ref().map_or("-", Regex::as_str);
+        let key = self.extract_match_key(&path, is_subtitle);
+        self.say(&Msg::ExplainKey { pattern, key: key.as_deref() }.text(self.lang));
+        let Some(key) = key else {
+            return Ok(());
+        };
+
+        let decision = if let Some(op) = plan.operations.iter().find(|op| op.from == path) {
+            Msg::ExplainRenamed(&op.to)
+        } else if plan.unmatched.contains(&path) {
+            Msg::ExplainUnmatched
+        } else if is_subtitle == (self.args.source == Source::Videos) {
+            Msg::ExplainAlreadyNamed
+        } else {
+            Msg::ExplainVideoTarget(plan.operations.iter().filter(|op| op.episode_id == key).count())
+        };
+        self.say(&decision.text(self.lang));
+        Ok(())
+    }
+
+    /// `--report-duplicates`: grupos de archivos que comparten ID, primero los
+    /// subtítulos y después los videos, ordenados por ID.
+    fn report_duplicates(&self, subtitles: &[FileInfo], videos: &[FileInfo]) {
+        fn groups(files: &[FileInfo]) -> BTreeMap<&str, Vec<&Path>> {
+            let mut by_id: BTreeMap<&str, Vec<&Path>> = BTreeMap::new();
+            for file in files {
+                by_id.entry(&file.episode_id).or_default().push(&file.path);
+            }
+            by_id.retain(|_, paths| paths.len() > 1);
+            by_id
+        }
+        let (subtitle_groups, video_groups) = (groups(subtitles), groups(videos));
+        if subtitle_groups.is_empty() && video_groups.is_empty() {
+            self.say(&Msg::NoDuplicates.text(self.lang));
+            return;
+        }
+
+        for (is_subtitle, groups) in [(true, subtitle_groups), (false, video_groups)] {
+            for (id, mut paths) in groups {
+                paths.sort();
+                let count = paths.len();
+                let header = if is_subtitle { Msg::DuplicateSubtitles { id, count } } else { Msg::DuplicateVideos { id, count } };
+                self.say(&header.text(self.lang));
+                for path in paths {
+                    self.say(&format!("   {}", self.relative_to_root(path).display()));
+                }
+            }
+        }
+    }
+
+    /// `--tally`: cuántos subtítulos y videos produjo cada valor de cada grupo
+    /// de captura, con una tabla por grupo y los valores en orden natural.
+    fn tally(&self, subtitles: &[FileInfo], videos: &[FileInfo]) {
+        // (índice del grupo, etiqueta) -> valor -> [subtítulos, videos]
+        let mut tally: BTreeMap<(usize, String), HashMap<String, [usize; 2]>> = BTreeMap::new();
+        for (column, files, is_subtitle) in [(0, subtitles, true), (1, videos, false)] {
+            for file in files.iter().filter(|file| file.archive_entry.is_none()) {
+                let Some(name) = self.match_name(&file.path) else { continue };
+                let text = self.prepare_match_text(&name);
+                let groups = self.with_episode_regex(&file.path, is_subtitle, |regex| {
+                    let captures = if self.args.match_last { regex.captures_iter(&text).last() } else { regex.captures(&text) }?;
+                    let names = regex.capture_names().enumerate().skip(1);
+                    Some(names
+                        .filter_map(|(index, name)| {
+                            let value = captures.get(index)?.as_str().to_string();
+                            Some(((index, name.map_or_else(|| index.to_string(), str::to_string)), value))
+                        })
+                        .collect::<Vec<_>>())
+                });
+                for (group, value) in groups.into_iter().flatten() {
+                    tally.entry(group).or_default().entry(value).or_default()[column] += 1;
+                }
+            }
+        }
+        if tally.is_empty() {
+            self.say(&Msg::TallyEmpty.text(self.lang));
+            return;
+        }
+
+        for ((_, label), counts) in tally {
+            let mut values: Vec<_> = counts.into_iter().collect();
+            values.sort_by(|(a, _), (b, _)| natural_cmp(a, b));
+            let mut rows = vec![[Msg::TableValue, Msg::TableSubtitles, Msg::TableVideos].iter().map(|msg| msg.text(self.lang)).collect()];
+            rows.extend(values.into_iter().map(|(value, [subs, vids])| vec![value, subs.to_string(), vids.to_string()]));
+            self.say(&Msg::TallyGroup(&label).text(self.lang));
+            self.say(format_table(&rows).trim_end());
+        }
+    }
+
+    /// Indica si junto a `video` hay un subtítulo con su nombre base, con o sin
+    /// algo más antes de la extensión (`Show.srt`, `Show.es.srt`).
+    fn has_sidecar_subtitle(&self, video: &Path) -> bool {
+        let Some(stem) = video.file_stem().and_then(OsStr::to_str) else {
+            return false;
+        };
+        let Ok(entries) = fs::read_dir(parent_dir(video)) else {
+            return false;
+        };
+        entries.flatten().any(|entry| {
+            let path = entry.path();
+            let name = entry.file_name();
+            let Some(rest) = name.to_str().and_then(|name| name.strip_prefix(stem)) else {
+                return false;
+            };
+            let extension = path.extension().and_then(OsStr::to_str).map(str::to_lowercase).unwrap_or_default();
+            rest.starts_with('.') && (self.srt_extensions.contains(&extension) || self.compound_extension(&path).is_some())
+        })
+    }
+
+    /// `--normalize-all-extensions`: un renombrado por cada subtítulo cuya
+    /// extensión tiene mayúsculas, tenga o no video. Si ya existe otro archivo
+    /// con la extensión en minúsculas, se avisa y se deja como está.
+    fn plan_lowercase_extensions(&self) -> Result<RenamePlan> {
+        let mut plan = RenamePlan::default();
+        for path in self.get_files()? {
+            let Some(extension) = path.extension().and_then(OsStr::to_str) else {
+                continue;
+            };
+            let lowercase = extension.to_lowercase();
+            if lowercase != extension && self.srt_extensions.contains(&lowercase) {
+                let to = path.with_extension(&lowercase);
+                if self.path_exists(&to) && !is_same_file(&path, &to) {
+                    self.warn(Msg::LowercaseTwinExists { from: file_name(&path), to: file_name(&to) });
+                    continue;
+                }
+                plan.operations.push(RenameOperation {
+                    to,
+                    from: path,
+                    episode_id: String::new(),
+                    archive_entry: None,
+                });
+            }
+        }
+        Ok(plan)
+    }
+
+    /// `--match-by-mtime`: ordena subtítulos y videos por fecha de modificación y
+    /// da a cada par la misma clave (`#1`, `#2`...). Falla si no hay tantos de
+    /// unos como de otros, porque el emparejamiento quedaría desplazado.
+    fn categorize_by_mtime(&self, files: Vec<PathBuf>) -> Result<(Vec<FileInfo>, Vec<FileInfo>)> {
+        self.warn(Msg::MtimeMatchWarning);
+
+        let mut subtitles = Vec::new();
+        let mut videos = Vec::new();
+        for path in files {
+            let Some(extension) = path.extension().and_then(OsStr::to_str).map(str::to_lowercase) else {
+                continue;
+            };
+            let group = if self.srt_extensions.contains(&extension) {
+                &mut subtitles
+            } else if self.video_extensions.contains(&extension) {
+                &mut videos
+            } else {
+                continue;
+            };
+            let modified = fs::metadata(&path)
+                .and_then(|m| m.modified())
+                .with_context(|| Msg::ReadModifiedTime(&path).text(self.lang))?;
+            group.push((modified, path));
+        }
+
+        if subtitles.len() != videos.len() {
+            anyhow::bail!(Msg::MtimeCountMismatch { subtitles: subtitles.len(), videos: videos.len() }.text(self.lang));
+        }
+        let by_age = |mut files: Vec<(SystemTime, PathBuf)>| -> Vec<FileInfo> {
+            files.sort();
+            files
+                .into_iter()
+                .enumerate()
+                .map(|(i, (_, path))| FileInfo::new(path, format!("#{}", i + 1)))
+                .collect()
+        };
+        let (subtitles, videos) = (by_age(subtitles), by_age(videos));
+
+        self.detail(Msg::FoundFiles { subtitles: subtitles.len(), videos: videos.len() });
+        Ok((subtitles, videos))
+    }
+
+    /// Indica si `dir` solo contiene subtítulos y videos del episodio `episode_id`.
+    fn is_single_episode_dir(&self, dir: &Path, episode_id: &str) -> bool {
+        let Ok(mut entries) = fs::read_dir(dir) else {
+            return false;
+        };
+        entries.all(|entry| {
+            let Ok(entry) = entry else {
+                return false;
+            };
+            let path = entry.path();
+            let extension = path.extension().and_then(OsStr::to_str).map(str::to_lowercase);
+            let is_subtitle = match extension {
+                Some(ext) if self.srt_extensions.contains(&ext) => true,
+                Some(ext) if self.video_extensions.contains(&ext) => false,
+                _ => return false,
+            };
+            entry.file_type().is_ok_and(|ft| ft.is_file())
+                && self.extract_match_key(&path, is_subtitle).as_deref() == Some(episode_id)
+        })
+    }
+
+    fn execute_renames(&self, operations: Vec<RenameOperation>) -> Vec<OperationOutcome> {
+        self.execute_operations(operations, self.args.dry_run)
+    }
+
+    /// `--review`: ejecuta `operations` en dry-run y, si el usuario confirma,
+    /// aplica esas mismas operaciones sin volver a escanear. Si no, devuelve el
+    /// resultado de la prueba.
+    fn review_then_execute<R: io::BufRead, W: io::Write>(
+        &self,
+        operations: Vec<RenameOperation>,
+        mut input: R,
+        mut output: W,
+    ) -> Result<Vec<OperationOutcome>> {
+        let preview = self.execute_operations(operations.clone(), true);
+        let pending = preview.iter().filter(|o| o.status == OperationStatus::DryRun).count();
+        if pending == 0 {
+            return Ok(preview);
+        }
+
+        match self.ask(&mut input, &mut output, Msg::ReviewApplyPrompt(pending))? {
+            ReviewAnswer::Yes | ReviewAnswer::All => Ok(self.execute_operations(operations, false)),
+            _ => {
+                self.info(Msg::ReviewCancelled);
+                Ok(preview)
+            }
+        }
+    }
+
+    fn execute_operations(&self, operations: Vec<RenameOperation>, dry_run: bool) -> Vec<OperationOutcome> {
+        if operations.is_empty() {
+            self.info(Msg::NothingToRename);
+            return Vec::new();
+        }
+
+        if self.args.parallel_execute && !dry_run {
+            if self.is_parallel_safe(&operations) {
+                return self.execute_parallel(operations);
+            }
+            self.warn(Msg::ParallelFallback);
+        }
+
+        let mut outcomes = Vec::with_capacity(operations.len());
+        // Destinos ya asignados en esta ejecución (en dry-run no existen en disco)
+        let mut claimed: HashSet<PathBuf> = HashSet::new();
+        let total = operations.len();
+        // Líneas del dry-run (operación, texto, arriesgada), que se muestran al final en el orden de --sort
+        let mut preview: Vec<(RenameOperation, String, bool)> = Vec::new();
+        // Resultado de --check-writable por directorio de destino
+        let mut writable: HashMap<PathBuf, Option<String>> = HashMap::new();
+
+        for mut op in operations {
+            // En sistemas de archivos que ignoran mayúsculas el destino "existe" y es
+            // el mismo archivo; si es otro archivo, es un conflicto como cualquier otro
+            let case_only = differs_only_by_case(&op.from, &op.to) && is_same_file(&op.from, &op.to);
+            let mut renumbered = false;
+
+            if case_only && !self.args.fix_case {
+                self.info(Msg::CaseOnlyRename { from: file_name(&op.from), to: file_name(&op.to) });
+                self.push_outcome(&mut outcomes, OperationOutcome { op, status: OperationStatus::Skipped, error: None });
+                continue;
+            }
+            // Una carpeta de --rename-dir solo se renombra si todo lo de dentro se renombró
+            let stuck = op.from.is_dir().then(|| {
+                outcomes.iter().find(|outcome| {
+                    matches!(outcome.status, OperationStatus::Skipped | OperationStatus::Failed)
+                        && outcome.op.from.starts_with(&op.from)
+                })
+            });
+            if let Some(Some(stuck)) = stuck {
+                self.warn(Msg::DirectoryRenameBlocked { dir: file_name(&op.from), name: file_name(&stuck.op.from) });
+                self.push_outcome(&mut outcomes, OperationOutcome { op, status: OperationStatus::Skipped, error: None });
+                continue;
+            }
+
+            // Verificar si el archivo de destino ya existe
+            let occupied = self.args.assume_targets_exist
+                || self.path_exists(&op.to)
+                || (self.args.number_conflicts && claimed.contains(&op.to));
+            if !case_only && occupied && op.from != op.to {
+                if self.args.ignore_already_subtitled
+                    && self.path_exists(&op.to)
+                    && self.extract_match_key(&op.to, true).as_deref() == Some(op.episode_id.as_str())
+                {
+                    self.info(Msg::AlreadySubtitled { name: file_name(&op.to), id: &op.episode_id, from: file_name(&op.from) });
+                    self.push_outcome(&mut outcomes, OperationOutcome { op, status: OperationStatus::AlreadyDone, error: None });
+                    continue;
+                }
+                let numbered = if self.args.number_conflicts {
+                    resolve_numbered_destination(
+                        &op.to,
+                        self.args.number_format,
+                        self.args.max_number_attempts,
+                        |p| self.path_exists(p) || claimed.contains(p),
+                    )
+                } else {
+                    None
+                };
+
+                match numbered {
+                    Some(free) => {
+                        self.detail(Msg::NumberedDestination { taken: file_name(&op.to), free: file_name(&free) });
+                        op.to = free;
+                        renumbered = true;
+                    }
+                    None => {
+                        let (name, id) = (file_name(&op.to), op.episode_id.as_str());
+                        if self.args.number_conflicts {
+                            self.info(Msg::NoFreeNumberedName { name, attempts: self.args.max_number_attempts, id });
+                        } else {
+                            self.info(Msg::DestinationExists { name, id });
+                        }
+                        self.push_outcome(&mut outcomes, OperationOutcome { op, status: OperationStatus::Skipped, error: None });
+                        continue;
+                    }
+                }
+            }
+
+            if dry_run && self.args.check_writable {
+                let dir = parent_dir(&op.to);
+                let probe = writable
+                    .entry(dir.to_path_buf())
+                    .or_insert_with(|| check_writable(dir).err().map(|e| e.to_string()));
+                if let Some(error) = probe.clone() {
+                    self.error(Msg::NotWritable { dir, error: error.clone() });
+                    self.push_outcome(&mut outcomes, OperationOutcome { op, status: OperationStatus::Failed, error: Some(error) });
+                    continue;
+                }
+            }
+
+            if dry_run {
+                let (from, to) = (self.shown_name(&op.from), self.shown_name(&op.to));
+                let (from, to) = (from.as_ref(), to.as_ref());
+                let msg = match &op.archive_entry {
+                    Some(entry) => Msg::DryRunExtract { archive: from, entry, to },
+                    None => Msg::DryRunRename { from, to },
+                };
+                // Se marcan con ! las que no son un simple renombrado en el sitio
+                let risk = if renumbered {
+                    Some(Msg::RiskRenumbered)
+                } else if op.archive_entry.is_none() && parent_dir(&op.from) != parent_dir(&op.to) {
+                    Some(Msg::RiskMovesDirectory)
+                } else {
+                    None
+                };
+                let line = match &risk {
+                    Some(risk) => format!("! {} ({})", msg.text(self.lang), risk.text(self.lang)),
+                    None => msg.text(self.lang),
+                };
+                preview.push((op.clone(), line, risk.is_some()));
+                claimed.insert(op.to.clone());
+                self.push_outcome(&mut outcomes, OperationOutcome { op, status: OperationStatus::DryRun, error: None });
+            } else {
+                let result = if self.args.mirror_tree {
+                    fs::create_dir_all(parent_dir(&op.to)).and_then(|()| self.rename_respecting_readonly(&op, case_only))
+                } else {
+                    self.rename_respecting_readonly(&op, case_only)
+                };
+                let failed = result.is_err();
+                if !failed {
+                    claimed.insert(op.to.clone());
+                }
+                self.finish_operation(&mut outcomes, op, result);
+                if failed && self.args.fail_fast {
+                    self.warn(Msg::FailFastStopped { remaining: total - outcomes.len() });
+                    break;
+                }
+            }
+        }
+
+        // El orden de --sort es solo para mostrar: las operaciones ya se evaluaron en el del plan
+        preview.sort_by(|a, b| self.display_cmp(&a.0, &b.0));
+        let shown = self.args.sample.map_or(preview.len(), |sample| sample.min(preview.len()));
+        let mut risky = 0;
+        for (_, line, is_risky) in &preview[..shown] {
+            self.say(line);
+            risky += usize::from(*is_risky);
+        }
+        let hidden = preview.len() - shown;
+        if risky > 0 {
+            self.say(&Msg::RiskySummary(risky).text(self.lang));
+        }
+        if hidden > 0 {
+            self.say(&Msg::SampleMore(hidden).text(self.lang));
+        }
+
+        outcomes
+    }
+
+    /// Orden de `--sort` para mostrar operaciones. A igual episodio (y siempre
+    /// con `path`) se respeta el del plan, que es también el de ejecución.
+    fn display_cmp(&self, a: &RenameOperation, b: &RenameOperation) -> std::cmp::Ordering {
+        match self.args.sort {
+            SortOrder::Natural => natural_cmp(&a.episode_id, &b.episode_id),
+            SortOrder::Path => std::cmp::Ordering::Equal,
+        }
+    }
+
+    /// Informa del resultado de una operación ya ejecutada y lo añade a `outcomes`.
+    fn finish_operation(&self, outcomes: &mut Vec<OperationOutcome>, op: RenameOperation, result: io::Result<()>) {
+        match result {
+            Ok(()) => {
+                let (from, to) = (self.shown_name(&op.from), self.shown_name(&op.to));
+                let (from, to) = (from.as_ref(), to.as_ref());
+                self.info(match &op.archive_entry {
+                    Some(entry) => Msg::Extracted { archive: from, entry, to },
+                    None => Msg::Renamed { from, to },
+                });
+                self.push_outcome(outcomes, OperationOutcome { op, status: OperationStatus::Renamed, error: None });
+            }
+            Err(e) => {
+                self.error(Msg::RenameError { name: file_name(&op.from), error: e.to_string() });
+                self.push_outcome(outcomes, OperationOutcome {
+                    op,
+                    status: OperationStatus::Failed,
+                    error: Some(e.to_string()),
+                });
+            }
+        }
+    }
+
+    fn rename_respecting_readonly(&self, op: &RenameOperation, case_only: bool) -> io::Result<()> {
+        rename_respecting_readonly(op, case_only, self.args.handle_readonly, self.lang)
+    }
+
+    /// Indica si las operaciones son independientes entre sí y se pueden
+    /// ejecutar en cualquier orden: destinos distintos que no existen ni son el
+    /// origen de otra, solo archivos y sin cambios únicamente de mayúsculas.
+    fn is_parallel_safe(&self, operations: &[RenameOperation]) -> bool {
+        if self.args.fail_fast || self.args.assume_targets_exist || self.args.handle_readonly || self.args.mirror_tree {
+            return false;
+        }
+        let sources: HashSet<&Path> = operations.iter().map(|op| op.from.as_path()).collect();
+        let mut targets = HashSet::new();
+        operations.iter().all(|op| {
+            targets.insert(op.to.as_path())
+                && !sources.contains(op.to.as_path())
+                && !op.to.exists()
+                && !op.from.is_dir()
+                && !differs_only_by_case(&op.from, &op.to)
+        })
+    }
+
+    /// `--parallel-execute`: reparte las operaciones entre hilos que solo tocan
+    /// el sistema de archivos; los mensajes, eventos e historial se generan
+    /// después en este hilo y en el orden del plan.
+    fn execute_parallel(&self, operations: Vec<RenameOperation>) -> Vec<OperationOutcome> {
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get()).min(operations.len());
+        let chunk_size = operations.len().div_ceil(threads);
+        // Los errores se explican igual que al renombrar en orden
+        let (handle_readonly, lang) = (self.args.handle_readonly, self.lang);
+        let rename = move |op: &RenameOperation| rename_respecting_readonly(op, false, handle_readonly, lang);
+        let results: Vec<io::Result<()>> = std::thread::scope(|scope| {
+            let handles: Vec<_> = operations
+                .chunks(chunk_size)
+                .map(|chunk| scope.spawn(move || chunk.iter().map(rename).collect::<Vec<_>>()))
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic)))
+                .collect()
+        });
+
+        let mut outcomes = Vec::with_capacity(operations.len());
+        for (op, result) in operations.into_iter().zip(results) {
+            self.finish_operation(&mut outcomes, op, result);
+        }
+        outcomes
+    }
+
+    fn print_summary(&self, summary: &RunSummary) {
+        if self.args.quiet {
+            return;
+        }
+
+        self.info(Msg::SummaryHeader);
+        self.info(Msg::SummarySucceeded(summary.succeeded));
+        if summary.errors > 0 {
+            self.info(Msg::SummaryErrors(summary.errors));
+        }
+        if self.args.dry_run {
+            self.info(Msg::SummaryDryRun);
+        }
+        self.say("");
+        self.say(summary.to_table(self.lang).trim_end());
+    }
+
+    fn build_report(&self, outcomes: &[OperationOutcome], unmatched: &[PathBuf], summary: &RunSummary) -> Json {
+        let mut sorted: Vec<&OperationOutcome> = outcomes.iter().collect();
+        sorted.sort_by(|a, b| self.display_cmp(&a.op, &b.op));
+        let operations = sorted
+            .into_iter()
+            .map(|o| {
+                Json::object([
+                    ("from", Json::from(o.op.from.display().to_string())),
+                    ("to", Json::from(o.op.to.display().to_string())),
+                    ("episode_id", Json::from(o.op.episode_id.as_str())),
+                    ("status", Json::from(o.status.as_str())),
+                    ("error", Json::from(o.error.clone())),
+                ])
+            })
+            .collect();
+        let unmatched = unmatched
+            .iter()
+            .map(|p| Json::from(p.display().to_string()))
+            .collect();
+
+        Json::object([
+            ("directory", Json::from(self.args.directory.display().to_string())),
+            ("dry_run", Json::from(self.args.dry_run)),
+            ("operations", Json::Array(operations)),
+            ("unmatched", Json::Array(unmatched)),
+            ("summary", summary.to_json()),
+            ("by_extension", summary.extensions_to_json()),
+        ])
+    }
+
+    fn write_report<W: io::Write>(&self, mut out: W, report: &Json) -> Result<()> {
+        if self.args.json_compact {
+            json::to_writer(&mut out, report)?;
+        } else {
+            json::to_writer_pretty(&mut out, report)?;
+        }
+        writeln!(out)?;
+        Ok(())
+    }
+
+    /// Nombre de `path` para mostrar, recortado por el medio con `--preview-width`.
+    fn shown_name<'a>(&self, path: &'a Path) -> Cow<'a, OsStr> {
+        let name = file_name(path);
+        match (self.name_width, name.to_str()) {
+            (Some(width), Some(text)) => match truncate_middle(text, width) {
+                Cow::Borrowed(_) => Cow::Borrowed(name),
+                Cow::Owned(short) => Cow::Owned(short.into()),
+            },
+            _ => Cow::Borrowed(name),
+        }
+    }
+
+    /// Ruta relativa al directorio de trabajo, para que el plan sea portable.
+    fn relative_to_root<'a>(&self, path: &'a Path) -> &'a Path {
+        path.strip_prefix(self.virtual_root().unwrap_or(&self.args.directory)).unwrap_or(path)
+    }
+
+    fn write_plan(&self, operations: &[RenameOperation], unmatched: &[PathBuf], path: &Path) -> Result<()> {
+        let operations = operations
+            .iter()
+            .map(|op| {
+                let size = fs::metadata(&op.from).ok().filter(|m| m.is_file()).map(|m| m.len());
+                Json::object([
+                    ("from", Json::from(self.relative_to_root(&op.from).display().to_string())),
+                    ("to", Json::from(self.relative_to_root(&op.to).display().to_string())),
+                    ("episode_id", Json::from(op.episode_id.as_str())),
+                    ("archive_entry", Json::from(op.archive_entry.clone())),
+                    ("size", Json::from(size)),
+                ])
+            })
+            .collect();
+        let plan = Json::object([
+            ("version", Json::from(PLAN_VERSION)),
+            ("directory", Json::from(self.args.directory.display().to_string())),
+            ("operations", Json::Array(operations)),
+            ("unmatched", Json::Array(unmatched.iter().map(|path| Json::from(self.relative_to_root(path).display().to_string())).collect())),
+        ]);
+
+        let file = fs::File::create(path)
+            .with_context(|| Msg::CreatePlan(path).text(self.lang))?;
+        let mut writer = io::BufWriter::new(file);
+        json::to_writer_pretty(&mut writer, &plan)?;
+        io::Write::flush(&mut writer)?;
+        Ok(())
+    }
+
+    /// `--emit-format`: escribe el plan en la salida estándar. Con `rename` cada
+    /// operación queda como `rename --no-overwrite -- 'DESDE' 'HASTA' 'DESDE'`:
+    /// util-linux reemplaza la primera aparición de DESDE en la ruta, que es la
+    /// ruta entera. El script comprueba que `rename` sea el de util-linux (el de
+    /// Perl leería DESDE como una expresión) y, como al ejecutar, se omiten los
+    /// destinos que ya existen o se repiten y las rutas que no son UTF-8.
+    fn emit_plan(&self, format: EmitFormat, operations: &[RenameOperation]) -> Result<()> {
+        let mut lines = Vec::new();
+        let mut claimed: HashSet<&Path> = HashSet::new();
+        for op in operations.iter().filter(|op| op.archive_entry.is_none()) {
+            let (Some(from), Some(to)) = (op.from.to_str(), op.to.to_str()) else {
+                self.warn(Msg::EmitSkippedNonUtf8(if op.from.to_str().is_none() { &op.from } else { &op.to }));
+                continue;
+            };
+            let names = (file_name(&op.from), file_name(&op.to));
+            if self.path_exists(&op.to) && !is_same_file(&op.from, &op.to) {
+                self.warn(Msg::EmitSkippedExisting { from: names.0, to: names.1 });
+                continue;
+            }
+            if !claimed.insert(&op.to) {
+                self.warn(Msg::EmitSkippedRepeated { from: names.0, to: names.1 });
+                continue;
+            }
+            lines.push(match format {
+                EmitFormat::Rename => {
+                    let (from, to) = (shell_quote(from), shell_quote(to));
+                    format!("rename --no-overwrite -- {} {} {}", from, to, from)
+                }
+            });
+        }
+
+        let mut out = self.stdout.borrow_mut();
+        writeln!(out, "#!/bin/sh")?;
+        match format {
+            EmitFormat::Rename => writeln!(
+                out,
+                "rename --version 2>/dev/null | grep -q util-linux || {{ echo {} >&2; exit 1; }}",
+                shell_quote(&Msg::EmitNeedsUtilLinux.text(self.lang))
+            )?,
+        }
+        for line in &lines {
+            writeln!(out, "{}", line)?;
+        }
+        drop(out);
+
+        let archives = operations.iter().filter(|op| op.archive_entry.is_some()).count();
+        if archives > 0 {
+            self.warn(Msg::EmitSkippedArchives(archives));
+        }
+        Ok(())
+    }
+
+    /// `--diff-last`: compara `plan` con el guardado en la ejecución anterior y
+    /// lo reemplaza por el actual.
+    fn diff_last_plan(&self, plan: &RenamePlan, store: Option<&Path>) -> Result<()> {
+        let path = match store {
+            Some(path) => path.to_path_buf(),
+            None => {
+                let directory = std::path::absolute(&self.args.directory).unwrap_or_else(|_| self.args.directory.clone());
+                history::last_plan_path(&directory).with_context(|| Msg::NoPlanStoreLocation.text(self.lang))?
+            }
+        };
+
+        let current = PlanSnapshot {
+            operations: plan.operations
+                .iter()
+                .map(|op| (self.relative_to_root(&op.from).display().to_string(), self.relative_to_root(&op.to).display().to_string()))
+                .collect(),
+            unmatched: plan.unmatched.iter().map(|path| self.relative_to_root(path).display().to_string()).collect(),
+        };
+        match fs::read_to_string(&path) {
+            Ok(content) => {
+                let previous = json::from_str(&content)
+                    .ok()
+                    .and_then(|json| PlanSnapshot::from_json(&json))
+                    .with_context(|| Msg::InvalidPlan(&path).text(self.lang))?;
+                self.print_plan_diff(&previous, &current, &path);
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => self.info(Msg::DiffNoPrevious(&path)),
+            Err(e) => return Err(e).with_context(|| Msg::ReadPlan(&path).text(self.lang)),
+        }
+
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent).with_context(|| Msg::CreatePlan(&path).text(self.lang))?;
+        }
+        self.write_plan(&plan.operations, &plan.unmatched, &path)
+    }
+
+    fn print_plan_diff(&self, previous: &PlanSnapshot, current: &PlanSnapshot, path: &Path) {
+        let previous_conflicts = previous.conflicts();
+        let new_conflicts: Vec<&str> = current.conflicts().into_iter().filter(|to| !previous_conflicts.contains(to)).collect();
+        let added: Vec<_> = current.operations.difference(&previous.operations).collect();
+        let dropped: Vec<_> = previous.operations.difference(&current.operations).collect();
+        let orphans: Vec<_> = current.unmatched.difference(&previous.unmatched).collect();
+        let resolved: Vec<_> = previous.unmatched.difference(&current.unmatched).collect();
+
+        if [added.len(), dropped.len(), orphans.len(), resolved.len(), new_conflicts.len()].iter().all(|&n| n == 0) {
+            self.info(Msg::DiffNoChanges);
+            return;
+        }
+        self.info(Msg::DiffHeader(path));
+        for (from, to) in added {
+            self.info(Msg::DiffNewMatch { from, to });
+        }
+        for (from, to) in dropped {
+            self.info(Msg::DiffDroppedMatch { from, to });
+        }
+        for path in orphans {
+            self.info(Msg::DiffNewOrphan(path));
+        }
+        for path in resolved {
+            self.info(Msg::DiffResolvedOrphan(path));
+        }
+        for to in new_conflicts {
+            self.info(Msg::DiffNewConflict(to));
+        }
+    }
+
+    /// Carga un plan guardado, descartando las operaciones cuyo origen ya no
+    /// existe o cambió de tamaño desde que se planificó.
+    fn load_plan(&self, path: &Path) -> Result<Vec<RenameOperation>> {
+        let content = fs::read_to_string(path)
+            .with_context(|| Msg::ReadPlan(path).text(self.lang))?;
+        let plan = json::from_str(&content)
+            .with_context(|| Msg::InvalidPlan(path).text(self.lang))?;
+
+        let version = plan.get("version").and_then(Json::as_f64);
+        if version != Some(PLAN_VERSION as f64) {
+            anyhow::bail!(Msg::UnsupportedPlanVersion { path, version }.text(self.lang));
+        }
+
+        let entries = plan.get("operations")
+            .and_then(Json::as_array)
+            .with_context(|| Msg::PlanWithoutOperations(path).text(self.lang))?;
+
+        let mut operations = Vec::with_capacity(entries.len());
+        for entry in entries {
+            let field = |key: &str| {
+                entry.get(key)
+                    .and_then(Json::as_str)
+                    .with_context(|| Msg::PlanMissingField { key, path }.text(self.lang))
+            };
+            let from = self.args.directory.join(field("from")?);
+            let to = self.args.directory.join(field("to")?);
+            let episode_id = field("episode_id")?.to_string();
+            let archive_entry = entry.get("archive_entry").and_then(Json::as_str).map(str::to_string);
+
+            let current_size = fs::metadata(&from).ok().map(|m| m.len());
+            let expected_size = entry.get("size").and_then(Json::as_f64).map(|n| n as u64);
+            match current_size {
+                None => self.info(Msg::PlanSourceMissing(&from)),
+                Some(size) if expected_size.is_some_and(|expected| expected != size) => {
+                    self.info(Msg::PlanSourceChanged(&from));
+                }
+                Some(_) => operations.push(RenameOperation { from, to, episode_id, archive_entry }),
+            }
+        }
+
+        Ok(operations)
+    }
+
+    /// Revisión interactiva de `operations` en páginas de `--page-size`.
+    /// Devuelve solo las aprobadas; terminar (o fin de la entrada) descarta
+    /// las que quedan sin revisar.
+    fn review_operations<R: io::BufRead, W: io::Write>(
+        &self,
+        operations: Vec<RenameOperation>,
+        mut input: R,
+        mut output: W,
+    ) -> Result<Vec<RenameOperation>> {
+        let page_size = self.args.page_size as usize;
+        let pages = operations.len().div_ceil(page_size);
+        let mut remaining = operations.into_iter();
+        let mut approved = Vec::new();
+
+        for page in 1..=pages {
+            let chunk: Vec<RenameOperation> = remaining.by_ref().take(page_size).collect();
+            let first_index = (page - 1) * page_size + 1;
+
+            writeln!(output, "{}", Msg::ReviewPageHeader { page, pages }.text(self.lang))?;
+            for (index, op) in (first_index..).zip(&chunk) {
+                let (from, to) = (file_name(&op.from), file_name(&op.to));
+                writeln!(output, "{}", Msg::ReviewOperation { index, from, to }.text(self.lang))?;
+            }
+
+            match self.ask(&mut input, &mut output, Msg::ReviewPagePrompt)? {
+                ReviewAnswer::Yes => approved.extend(chunk),
+                ReviewAnswer::No => {}
+                ReviewAnswer::All => {
+                    approved.extend(chunk);
+                    approved.extend(remaining);
+                    break;
+                }
+                ReviewAnswer::OneByOne => {
+                    let mut items = (first_index..).zip(chunk);
+                    while let Some((index, op)) = items.next() {
+                        let (from, to) = (file_name(&op.from), file_name(&op.to));
+                        match self.ask(&mut input, &mut output, Msg::ReviewItemPrompt { index, from, to })? {
+                            ReviewAnswer::Yes | ReviewAnswer::OneByOne => approved.push(op),
+                            ReviewAnswer::No => {}
+                            ReviewAnswer::All => {
+                                approved.push(op);
+                                approved.extend(items.map(|(_, op)| op));
+                                break;
+                            }
+                            ReviewAnswer::Quit => return Ok(approved),
+                        }
+                    }
+                }
+                ReviewAnswer::Quit => return Ok(approved),
+            }
+        }
+
+        Ok(approved)
+    }
+
+    /// `--dry-run-interactive`: pregunta por cada operación si aceptarla,
+    /// omitirla o cambiar el nombre de su destino (en el mismo directorio).
+    /// Terminar o el fin de la entrada descartan las que faltan.
+    fn edit_operations<R: io::BufRead, W: io::Write>(
+        &self,
+        operations: Vec<RenameOperation>,
+        mut input: R,
+        mut output: W,
+    ) -> Result<Vec<RenameOperation>> {
+        let mut read_line = |output: &mut W, prompt: Msg| -> Result<Option<String>> {
+            write!(output, "{}", prompt.text(self.lang))?;
+            output.flush()?;
+            let mut line = String::new();
+            if input.read_line(&mut line)? == 0 {
+                writeln!(output)?;
+                return Ok(None);
+            }
+            Ok(Some(line.trim().to_string()))
+        };
+
+        let mut approved = Vec::new();
+        for (index, mut op) in (1..).zip(operations) {
+            loop {
+                let (from, to) = (file_name(&op.from), file_name(&op.to));
+                let Some(answer) = read_line(&mut output, Msg::EditItemPrompt { index, from, to })? else {
+                    return Ok(approved);
+                };
+                match (self.lang, answer.chars().next().map(|c| c.to_ascii_lowercase())) {
+                    (Lang::Es, Some('s')) | (Lang::En, Some('y')) => approved.push(op),
+                    (_, Some('n')) => {}
+                    (_, Some('q')) => return Ok(approved),
+                    (_, Some('e')) => {
+                        let Some(name) = read_line(&mut output, Msg::EditNamePrompt)? else {
+                            return Ok(approved);
+                        };
+                        if name.is_empty() || name == "." || name == ".." || name.chars().any(is_invalid_name_char) {
+                            if !name.is_empty() {
+                                writeln!(output, "{}", Msg::InvalidEditedName(&name).text(self.lang))?;
+                            }
+                        } else {
+                            op.to = parent_dir(&op.to).join(name);
+                        }
+                        continue;
+                    }
+                    _ => continue,
+                }
+                break;
+            }
+        }
+        Ok(approved)
+    }
+
+    /// Muestra `prompt` hasta obtener una respuesta válida. El fin de la
+    /// entrada equivale a terminar.
+    fn ask<R: io::BufRead, W: io::Write>(&self, input: &mut R, output: &mut W, prompt: Msg) -> Result<ReviewAnswer> {
+        loop {
+            write!(output, "{}", prompt.text(self.lang))?;
+            output.flush()?;
+
+            let mut line = String::new();
+            if input.read_line(&mut line)? == 0 {
+                writeln!(output)?;
+                return Ok(ReviewAnswer::Quit);
+            }
+            if let Some(answer) = ReviewAnswer::parse(&line, self.lang) {
+                return Ok(answer);
+            }
+        }
+    }
+
+    /// `--prune-empty-dirs`: elimina los directorios de origen que quedaron
+    /// vacíos al mover sus archivos a otro directorio, y los ancestros que a su
+    /// vez queden vacíos, sin salir de `--directory`. En dry-run solo los lista.
+    fn prune_emptied_dirs(&self, outcomes: &[OperationOutcome]) {
+        let moved = |o: &&OperationOutcome| {
+            matches!(o.status, OperationStatus::Renamed | OperationStatus::DryRun)
+                && o.op.archive_entry.is_none()
+                && parent_dir(&o.op.from) != parent_dir(&o.op.to)
+        };
+        // Rutas que ya no estarán: orígenes movidos y directorios eliminados
+        let mut gone: HashSet<PathBuf> = outcomes.iter().filter(moved).map(|o| o.op.from.clone()).collect();
+        let mut candidates: Vec<PathBuf> = outcomes.iter().filter(moved).map(|o| parent_dir(&o.op.from).to_path_buf()).collect();
+        // Los más profundos primero, para que los padres vean a sus hijos ya eliminados
+        candidates.sort_by_key(|dir| std::cmp::Reverse(dir.components().count()));
+        candidates.dedup();
+
+        let root = &self.args.directory;
+        for mut dir in candidates {
+            while dir != *root && dir.starts_with(root) && !gone.contains(&dir) {
+                let empty = fs::read_dir(&dir)
+                    .map(|mut entries| entries.all(|e| e.is_ok_and(|e| gone.contains(&e.path()))))
+                    .unwrap_or(false);
+                if !empty {
+                    break;
+                }
+
+                if self.args.dry_run {
+                    self.info(Msg::DryRunRemoveDirectory(&dir));
+                } else if let Err(e) = fs::remove_dir(&dir) {
+                    self.error(Msg::RemoveDirectoryError { path: &dir, error: e.to_string() });
+                    break;
+                } else {
+                    self.info(Msg::RemovedEmptyDirectory(&dir));
+                }
+                gone.insert(dir.clone());
+                match dir.parent() {
+                    Some(parent) => dir = parent.to_path_buf(),
+                    None => break,
+                }
+            }
+        }
+    }
+
+    /// `--verify-after`: marca como fallidos los renombrados cuyo destino no
+    /// existe en disco y devuelve cuántos son.
+    fn verify_outcomes(&self, outcomes: &mut [OperationOutcome]) -> usize {
+        let mut missing = 0;
+        for outcome in outcomes.iter_mut().filter(|o| o.status == OperationStatus::Renamed) {
+            if fs::symlink_metadata(&outcome.op.to).is_err() {
+                self.error(Msg::VerifyMissing(&outcome.op.to));
+                outcome.status = OperationStatus::Failed;
+                outcome.error = Some(Msg::VerifyMissing(&outcome.op.to).text(self.lang));
+                missing += 1;
+            }
+        }
+        missing
+    }
+
+    /// Añade al historial una entrada con los renombrados realizados, con rutas
+    /// absolutas para poder deshacerlos desde cualquier directorio.
+    fn record_history(&self, outcomes: &[OperationOutcome], path: &Path) -> Result<()> {
+        let absolute = |path: &Path| std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+        let operations: Vec<Json> = outcomes
+            .iter()
+            .filter(|outcome| outcome.status == OperationStatus::Renamed)
+            .map(|outcome| {
+                Json::object([
+                    ("from", Json::from(absolute(&outcome.op.from).to_string_lossy().into_owned())),
+                    ("to", Json::from(absolute(&outcome.op.to).to_string_lossy().into_owned())),
+                    ("episode_id", Json::from(outcome.op.episode_id.as_str())),
+                    ("archive_entry", Json::from(outcome.op.archive_entry.clone())),
+                ])
+            })
+            .collect();
+        if operations.is_empty() {
+            return Ok(());
+        }
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+        let entry = Json::object([
+            ("timestamp", Json::from(timestamp)),
+            ("directory", Json::from(absolute(&self.args.directory).to_string_lossy().into_owned())),
+            ("operations", Json::Array(operations)),
+        ]);
+        history::record(path, &entry, self.args.history_max as usize)
+            .with_context(|| Msg::HistoryNotWritable(path).text(self.lang))
+    }
+
+    /// Escribe las métricas vía un archivo temporal, para que un recolector
+    /// nunca lea un archivo a medio escribir.
+    fn write_metrics(&self, summary: &RunSummary, path: &Path) -> Result<()> {
+        let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+        tmp_name.push(".tmp");
+        let tmp = path.with_file_name(tmp_name);
+
+        fs::write(&tmp, summary.to_prometheus(self.args.dry_run))
+            .with_context(|| Msg::WriteMetrics(&tmp).text(self.lang))?;
+        fs::rename(&tmp, path)
+            .with_context(|| Msg::WriteMetrics(path).text(self.lang))?;
+        Ok(())
+    }
+
+    /// `--summary-json`: una sola línea con la fecha, el directorio y los
+    /// contadores de `summary`, por stderr o añadida al final de `path`.
+    fn write_summary_line(&self, summary: &RunSummary, path: Option<&Path>) -> Result<()> {
+        use io::Write;
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+        let directory = std::path::absolute(&self.args.directory).unwrap_or_else(|_| self.args.directory.clone());
+        let mut fields = vec![
+            ("timestamp".to_string(), Json::from(timestamp)),
+            ("directory".to_string(), Json::from(directory.to_string_lossy().into_owned())),
+            ("dry_run".to_string(), Json::Bool(self.args.dry_run)),
+        ];
+        if let Json::Object(counts) = summary.to_json() {
+            fields.extend(counts);
+        }
+        let mut line = Vec::new();
+        json::to_writer(&mut line, &Json::Object(fields))?;
+        line.push(b'\n');
+
+        match path {
+            Some(path) => fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .and_then(|mut file| file.write_all(&line))
+                .with_context(|| Msg::WriteSummaryJson(path).text(self.lang)),
+            None => Ok(io::stderr().write_all(&line)?),
+        }
+    }
+
+    /// Con `--rename-subtitles-in-place-only`, falla si alguna operación cambia
+    /// el directorio del archivo.
+    fn check_in_place(&self, operations: &[RenameOperation]) -> Result<()> {
+        if !self.args.in_place_only {
+            return Ok(());
+        }
+        if let Some(op) = operations.iter().find(|op| parent_dir(&op.from) != parent_dir(&op.to)) {
+            anyhow::bail!(Msg::OutsideOriginalDirectory { from: &op.from, to: &op.to }.text(self.lang));
+        }
+        Ok(())
+    }
+
+    /// `--max-ops`: un regex o directorio equivocado no debe renombrar miles de
+    /// archivos. En dry-run no se aplica, para poder revisar el plan.
+    fn check_max_ops(&self, operations: &[RenameOperation]) -> Result<()> {
+        if let Some(max) = self.args.max_ops
+            && operations.len() > max
+            && !self.args.dry_run
+            && !self.args.force
+        {
+            anyhow::bail!(Msg::TooManyOperations { count: operations.len(), max }.text(self.lang));
+        }
+        Ok(())
+    }
+
+    /// `--fail-on-duplicate-targets`: con destinos repetidos, el último en
+    /// renombrarse ganaría y los demás subtítulos se perderían.
+    fn check_duplicate_targets(&self, operations: &[RenameOperation]) -> Result<()> {
+        if !self.args.fail_on_duplicate_targets {
+            return Ok(());
+        }
+        let mut by_target: BTreeMap<&Path, Vec<&Path>> = BTreeMap::new();
+        for op in operations {
+            by_target.entry(&op.to).or_default().push(&op.from);
+        }
+        by_target.retain(|_, sources| sources.len() > 1);
+        if by_target.is_empty() {
+            return Ok(());
+        }
+
+        for (to, sources) in &by_target {
+            let sources = sources.iter().map(|path| format!("{:?}", file_name(path))).collect::<Vec<_>>().join(", ");
+            self.warn(Msg::DuplicateTarget { to, sources });
+        }
+        anyhow::bail!(Msg::DuplicateTargets(by_target.len()).text(self.lang));
+    }
+
+    /// Aborta si la proporción de subtítulos emparejados queda bajo `--min-match-rate`.
+    fn check_match_rate(&self, plan: &RenamePlan) -> Result<()> {
+        let Some(min_rate) = self.args.min_match_rate else {
+            return Ok(());
+        };
+        if plan.subtitles == 0 {
+            return Ok(());
+        }
+
+        let matched = plan.subtitles - plan.unmatched.len();
+        let rate = matched as f64 / plan.subtitles as f64;
+        if rate < min_rate {
+            anyhow::bail!(Msg::LowMatchRate { matched, total: plan.subtitles, rate, min_rate }.text(self.lang));
+        }
+        Ok(())
+    }
+
+    fn run(&self) -> Result<RunStatus> {
+        let started = Instant::now();
+        let plan = match (&self.args.apply_plan, self.args.rename_one.as_deref()) {
+            (Some(plan_path), _) => RenamePlan {
+                operations: self.load_plan(plan_path)?,
+                ..Default::default()
+            },
+            (None, Some([subtitle, video])) => self.plan_single(subtitle, video)?,
+            (None, _) if self.args.normalize_all_extensions => self.plan_lowercase_extensions()?,
+            (None, _) => {
+                self.emit(Event::ScanStart { directory: &self.args.directory });
+                if let Some(offset) = self.args.episode_offset.filter(|&offset| offset != 0) {
+                    self.warn(Msg::EpisodeOffsetWarning(offset));
+                }
+                let (subtitles, videos) = self.categorize_files()?;
+                if self.args.report_duplicates {
+                    self.report_duplicates(&subtitles, &videos);
+                    return Ok(RunStatus::Done);
+                }
+                if self.args.tally {
+                    self.tally(&subtitles, &videos);
+                    return Ok(RunStatus::Done);
+                }
+                for (files, kind) in [(&subtitles, "subtitle"), (&videos, "video")] {
+                    for file in files {
+                        self.emit(Event::FileMatched { path: &file.path, kind, episode_id: &file.episode_id });
+                    }
+                }
+                let paths = |files: &[FileInfo]| files.iter().map(|file| file.path.clone()).collect::<Vec<_>>();
+                let dot_files = self.args.dot.is_some().then(|| (paths(&subtitles), paths(&videos)));
+                let plan = self.plan_renames(subtitles, videos);
+                if let (Some(dot_path), Some((subtitles, videos))) = (&self.args.dot, dot_files) {
+                    let graph = render_dot(&subtitles, &videos, &plan.matches, |path| self.relative_to_root(path), self.lang);
+                    fs::write(dot_path, graph).with_context(|| Msg::CreateDot(dot_path).text(self.lang))?;
+                    self.info(Msg::DotSaved { path: dot_path, matches: plan.matches.len() });
+                    return Ok(RunStatus::Done);
+                }
+                plan
+            }
+        };
+        for op in &plan.operations {
+            self.emit(Event::OpPlanned { from: &op.from, to: &op.to, episode_id: &op.episode_id });
+        }
+
+        if let Some(name) = &self.args.explain {
+            self.explain(name, &plan)?;
+            return Ok(RunStatus::Done);
+        }
+
+        self.check_match_rate(&plan)?;
+
+        if let Some(store) = &self.args.diff_last {
+            self.diff_last_plan(&plan, store.as_deref())?;
+        }
+
+        if let Some(format) = self.args.emit_format {
+            self.emit_plan(format, &plan.operations)?;
+            return Ok(RunStatus::Done);
+        }
+
+        if let Some(plan_path) = &self.args.plan_out {
+            self.write_plan(&plan.operations, &plan.unmatched, plan_path)?;
+            self.info(Msg::PlanSaved { count: plan.operations.len(), path: plan_path });
+            return Ok(RunStatus::Done);
+        }
+
+        self.check_in_place(&plan.operations)?;
+        self.check_duplicate_targets(&plan.operations)?;
+        self.check_max_ops(&plan.operations)?;
+
+        let operations = if self.args.dry_run_interactive {
+            self.edit_operations(plan.operations, io::stdin().lock(), io::stderr())?
+        } else if self.args.interactive {
+            self.review_operations(plan.operations, io::stdin().lock(), io::stderr())?
+        } else {
+            plan.operations
+        };
+        let status = if operations.is_empty() { RunStatus::NothingToDo } else { RunStatus::Done };
+
+        // Emparejar por fecha es demasiado arriesgado para aplicarlo sin confirmar
+        let mut outcomes = if self.args.review || self.args.match_by_mtime || self.args.dry_run_interactive {
+            self.review_then_execute(operations, io::stdin().lock(), io::stderr())?
+        } else {
+            self.execute_renames(operations)
+        };
+        let unverified = if self.args.verify_after { self.verify_outcomes(&mut outcomes) } else { 0 };
+        if self.args.prune_empty_dirs {
+            self.prune_emptied_dirs(&outcomes);
+        }
+        let mut summary = RunSummary::from_outcomes(&outcomes, plan.unmatched.len());
+        summary.duration = started.elapsed();
+        self.emit(Event::Summary(summary.to_json()));
+
+        if let Some(history_path) = &self.history_path {
+            self.record_history(&outcomes, history_path)?;
+        }
+
+        if let Some(metrics_path) = &self.args.metrics_out {
+            self.write_metrics(&summary, metrics_path)?;
+        }
+
+        if let Some(target) = &self.args.summary_json {
+            self.write_summary_line(&summary, target.as_deref())?;
+        }
+
+        match self.args.format {
+            OutputFormat::Text => {
+                if !outcomes.is_empty() {
+                    self.print_summary(&summary);
+                }
+            }
+            OutputFormat::Json => {
+                let report = self.build_report(&outcomes, &plan.unmatched, &summary);
+                self.write_report(&mut *self.stdout.borrow_mut(), &report)?;
+            }
+        }
+
+        if unverified > 0 {
+            anyhow::bail!(Msg::VerificationFailed(unverified).text(self.lang));
+        }
+        Ok(status)
+    }
+}
+
+/// Alinea `rows` en columnas: la primera fila es la cabecera, la primera columna
+/// se alinea a la izquierda y el resto (números) a la derecha.
+fn format_table(rows: &[Vec<String>]) -> String {
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    let widths: Vec<usize> = (0..columns)
+        .map(|col| rows.iter().filter_map(|row| row.get(col)).map(|cell| cell.chars().count()).max().unwrap_or(0))
+        .collect();
+
+    let mut out = String::new();
+    for (i, row) in rows.iter().enumerate() {
+        let cells: Vec<String> = row.iter().zip(&widths).enumerate()
+            .map(|(col, (cell, &width))| {
+                if col == 0 { format!("{:<width$}", cell) } else { format!("{:>width$}", cell) }
+            })
+            .collect();
+        out.push_str("  ");
+        out.push_str(cells.join("  ").trim_end());
+        out.push('\n');
+        if i == 0 {
+            let total_width = widths.iter().sum::<usize>() + 2 * widths.len().saturating_sub(1);
+            out.push_str("  ");
+            out.push_str(&"─".repeat(total_width));
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// Caracteres que el sistema de archivos de destino no admite en un nombre.
+fn is_invalid_name_char(c: char) -> bool {
+    if cfg!(target_os = "windows") {
+        c.is_control() || matches!(c, '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*')
+    } else {
+        matches!(c, '/' | '\0')
+    }
+}
+
+/// Reemplaza los caracteres no válidos por `replacement` y quita los puntos y
+/// espacios finales, que Windows descarta en silencio.
+fn sanitize_name(name: &str, replacement: char) -> String {
+    let sanitized: String = name
+        .chars()
+        .map(|c| if is_invalid_name_char(c) { replacement } else { c })
+        .collect();
+    sanitized.trim_end_matches(['.', ' ']).to_string()
+}
+
+/// Marcadores disponibles en `--output-template` y `--template-file`.
+const TEMPLATE_TOKENS: &[&str] = &["video_stem", "subtitle_stem", "id", "offset", "lang", "ext"];
+
+/// Códigos ISO 639-1 y sus equivalentes ISO 639-2 (T y B) que acepta
+/// `--lang-from-dir`, para que carpetas como `ER`, `old` o `tmp` no pasen por idiomas.
+const LANGUAGE_CODES: &[&str] = &[
+    "aa", "aar", "ab", "abk", "ae", "ave", "af", "afr", "ak", "aka", "am", "amh", "an", "arg", "ar", "ara",
+    "as", "asm", "av", "ava", "ay", "aym", "az", "aze", "ba", "bak", "be", "bel", "bg", "bul", "bi", "bis",
+    "bm", "bam", "bn", "ben", "bo", "bod", "tib", "br", "bre", "bs", "bos", "ca", "cat", "ce", "che",
+    "ch", "cha", "co", "cos", "cr", "cre", "cs", "ces", "cze", "cu", "chu", "cv", "chv", "cy", "cym", "wel",
+    "da", "dan", "de", "deu", "ger", "dv", "div", "dz", "dzo", "ee", "ewe", "el", "ell", "gre", "en", "eng",
+    "eo", "epo", "es", "spa", "et", "est", "eu", "eus", "baq", "fa", "fas", "per", "ff", "ful", "fi", "fin",
+    "fj", "fij", "fo", "fao", "fr", "fra", "fre", "fy", "fry", "ga", "gle", "gd", "gla", "gl", "glg",
+    "gn", "grn", "gu", "guj", "gv", "glv", "ha", "hau", "he", "heb", "hi", "hin", "ho", "hmo", "hr", "hrv",
+    "ht", "hat", "hu", "hun", "hy", "hye", "arm", "hz", "her", "ia", "ina", "id", "ind", "ie", "ile",
+    "ig", "ibo", "ii", "iii", "ik", "ipk", "io", "ido", "is", "isl", "ice", "it", "ita", "iu", "iku",
+    "ja", "jpn", "jv", "jav", "ka", "kat", "geo", "kg", "kon", "ki", "kik", "kj", "kua", "kk", "kaz",
+    "kl", "kal", "km", "khm", "kn", "kan", "ko", "kor", "kr", "kau", "ks", "kas", "ku", "kur", "kv", "kom",
+    "kw", "cor", "ky", "kir", "la", "lat", "lb", "ltz", "lg", "lug", "li", "lim", "ln", "lin", "lo", "lao",
+    "lt", "lit", "lu", "lub", "lv", "lav", "mg", "mlg", "mh", "mah", "mi", "mri", "mao", "mk", "mkd", "mac",
+    "ml", "mal", "mn", "mon", "mr", "mar", "ms", "msa", "may", "mt", "mlt", "my", "mya", "bur", "na", "nau",
+    "nb", "nob", "nd", "nde", "ne", "nep", "ng", "ndo", "nl", "nld", "dut", "nn", "nno", "no", "nor",
+    "nr", "nbl", "nv", "nav", "ny", "nya", "oc", "oci", "oj", "oji", "om", "orm", "or", "ori", "os", "oss",
+    "pa", "pan", "pi", "pli", "pl", "pol", "ps", "pus", "pt", "por", "qu", "que", "rm", "roh", "rn", "run",
+    "ro", "ron", "rum", "ru", "rus", "rw", "kin", "sa", "san", "sc", "srd", "sd", "snd", "se", "sme",
+    "sg", "sag", "si", "sin", "sk", "slk", "slo", "sl", "slv", "sm", "smo", "sn", "sna", "so", "som",
+    "sq", "sqi", "alb", "sr", "srp", "ss", "ssw", "st", "sot", "su", "sun", "sv", "swe", "sw", "swa",
+    "ta", "tam", "te", "tel", "tg", "tgk", "th", "tha", "ti", "tir", "tk", "tuk", "tl", "tgl", "tn", "tsn",
+    "to", "ton", "tr", "tur", "ts", "tso", "tt", "tat", "tw", "twi", "ty", "tah", "ug", "uig", "uk", "ukr",
+    "ur", "urd", "uz", "uzb", "ve", "ven", "vi", "vie", "vo", "vol", "wa", "wln", "wo", "wol", "xh", "xho",
+    "yi", "yid", "yo", "yor", "za", "zha", "zh", "zho", "chi", "zu", "zul",
+];
+
+/// Similitud mínima entre nombres para no avisar con `--sanity-check`.
+const MIN_NAME_SIMILARITY: f64 = 0.2;
+
+/// Palabras en minúsculas de un nombre, separadas por cualquier carácter no alfanumérico.
+fn name_tokens(name: &str) -> HashSet<String> {
+    name.split(|c: char| !c.is_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Palabras de `name` que no contienen el ID de episodio.
+fn significant_tokens(name: &str, episode_id: &str) -> HashSet<String> {
+    let id_tokens = name_tokens(episode_id);
+    name_tokens(name)
+        .into_iter()
+        .filter(|token| !id_tokens.iter().any(|id| token.contains(id.as_str())))
+        .collect()
+}
+
+/// Indica si un subtítulo con nombre base `base` ya acompaña al video
+/// `video_stem`: todas sus palabras (salvo el ID) están en el nombre del video.
+fn is_sidecar_of(base: &str, video_stem: &str, episode_id: &str) -> bool {
+    let video = significant_tokens(video_stem, episode_id);
+    let base = significant_tokens(base, episode_id);
+    !base.is_empty() && base.is_subset(&video)
+}
+
+/// Proporción de palabras compartidas entre dos nombres (sobre el más corto),
+/// sin contar las que contienen el ID de episodio. `None` si a alguno no le
+/// quedan palabras con las que comparar.
+fn name_similarity(a: &str, b: &str, episode_id: &str) -> Option<f64> {
+    let (a, b) = (significant_tokens(a, episode_id), significant_tokens(b, episode_id));
+    let smaller = a.len().min(b.len());
+    if smaller == 0 {
+        return None;
+    }
+    Some(a.intersection(&b).count() as f64 / smaller as f64)
+}
+
+/// Separa la etiqueta de idioma final de un nombre base (`Show.es` ->
+/// `("Show", "es")`): 2 o 3 letras minúsculas, con región opcional (`pt-BR`).
+fn split_language_tag(stem: &str) -> Option<(&str, &str)> {
+    let (base, tag) = stem.rsplit_once('.')?;
+    let (language, region) = match tag.split_once('-') {
+        Some((language, region)) => (language, Some(region)),
+        None => (tag, None),
+    };
+    let valid = (2..=3).contains(&language.len())
+        && language.chars().all(|c| c.is_ascii_lowercase())
+        && region.is_none_or(|r| r.len() == 2 && r.chars().all(|c| c.is_ascii_uppercase()));
+    (valid && !base.is_empty()).then_some((base, tag))
+}
+
+/// Etiqueta de idioma en el nombre de una carpeta (`eng`, `ES`, `pt-BR`),
+/// devuelta con el idioma en minúsculas. Solo vale un código de `LANGUAGE_CODES`.
+fn dir_language_tag(name: &str) -> Option<String> {
+    let (language, region) = match name.split_once(['-', '_']) {
+        Some((language, region)) => (language, Some(region)),
+        None => (name, None),
+    };
+    let language = language.to_lowercase();
+    let valid = LANGUAGE_CODES.contains(&language.as_str())
+        && region.is_none_or(|r| r.len() == 2 && r.chars().all(|c| c.is_ascii_alphabetic()));
+    valid.then(|| match region {
+        Some(region) => format!("{}-{}", language, region.to_uppercase()),
+        None => language,
+    })
+}
+
+/// Grafo bipartito en formato DOT: subtítulos a la izquierda, videos a la
+/// derecha y una arista por emparejamiento. Los archivos sin pareja van en gris
+/// punteado y los videos con varios subtítulos, en rojo con sus aristas.
+fn render_dot<'a>(
+    subtitles: &'a [PathBuf],
+    videos: &'a [PathBuf],
+    matches: &'a [(PathBuf, PathBuf)],
+    label: impl Fn(&'a Path) -> &'a Path,
+    lang: Lang,
+) -> String {
+    let quote = |text: &str| format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""));
+    let node = |path: &Path| quote(&path.display().to_string());
+    let mut per_video: HashMap<&Path, usize> = HashMap::new();
+    for (_, video) in matches {
+        *per_video.entry(video).or_default() += 1;
+    }
+    let matched_subtitles: HashSet<&Path> = matches.iter().map(|(subtitle, _)| subtitle.as_path()).collect();
+    let orphan = "style=dashed, color=gray, fontcolor=gray";
+    let conflict = "color=red, fontcolor=red";
+
+    let mut out = String::from("graph matches {\n    rankdir=LR;\n    node [shape=box];\n");
+    for (cluster, title, files, is_subtitle) in [("subtitles", Msg::DotSubtitles, subtitles, true), ("videos", Msg::DotVideos, videos, false)] {
+        out.push_str(&format!("    subgraph cluster_{} {{\n        label={};\n", cluster, quote(&title.text(lang))));
+        for path in files {
+            let style = if is_subtitle {
+                (!matched_subtitles.contains(path.as_path())).then_some(orphan)
+            } else {
+                match per_video.get(path.as_path()) {
+                    None => Some(orphan),
+                    Some(&count) if count > 1 => Some(conflict),
+                    Some(_) => None,
+                }
+            };
+            let style = style.map(|style| format!(", {}", style)).unwrap_or_default();
+            out.push_str(&format!("        {} [label={}{}];\n", node(path), node(label(path)), style));
+        }
+        out.push_str("    }\n");
+    }
+    for (subtitle, video) in matches {
+        let style = if per_video[video.as_path()] > 1 { format!(" [{}]", conflict) } else { String::new() };
+        out.push_str(&format!("    {} -- {}{};\n", node(subtitle), node(video), style));
+    }
+    out.push_str("}\n");
+    out
+}
+
+/// Compara como lo haría una persona: los tramos de dígitos por su valor
+/// (`E2` < `E10`) y el texto sin distinguir mayúsculas.
+fn natural_cmp(a: &str, b: &str) -> std::cmp::Ordering {
+    fn chunks(s: &str) -> impl Iterator<Item = &str> {
+        let mut rest = s;
+        std::iter::from_fn(move || {
+            let first = rest.chars().next()?;
+            let end = rest.find(|c: char| c.is_ascii_digit() != first.is_ascii_digit()).unwrap_or(rest.len());
+            let (chunk, tail) = rest.split_at(end);
+            rest = tail;
+            Some(chunk)
+        })
+    }
+
+    let mut left = chunks(a);
+    let mut right = chunks(b);
+    loop {
+        let ordering = match (left.next(), right.next()) {
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return std::cmp::Ordering::Less,
+            (Some(_), None) => return std::cmp::Ordering::Greater,
+            (Some(x), Some(y)) if x.starts_with(|c: char| c.is_ascii_digit()) && y.starts_with(|c: char| c.is_ascii_digit()) => {
+                let (x, y) = (strip_leading_zeros(x), strip_leading_zeros(y));
+                x.len().cmp(&y.len()).then_with(|| x.cmp(y))
+            }
+            (Some(x), Some(y)) => x.to_lowercase().cmp(&y.to_lowercase()),
+        };
+        if ordering.is_ne() {
+            return ordering;
+        }
+    }
+}
+
+/// `value` sin ceros a la izquierda, conservando al menos un dígito.
+fn strip_leading_zeros(value: &str) -> &str {
+    let trimmed = value.trim_start_matches('0');
+    if trimmed.is_empty() && !value.is_empty() { "0" } else { trimmed }
+}
+
+/// `value` con los ceros a la izquierda quitados de cada secuencia de dígitos
+/// (`S01E005` -> `S1E5`).
+fn strip_number_padding(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut digits = String::new();
+    for c in value.chars() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+        } else {
+            out.push_str(strip_leading_zeros(&digits));
+            digits.clear();
+            out.push(c);
+        }
+    }
+    out.push_str(strip_leading_zeros(&digits));
+    out
+}
+
+/// Indica si `pattern` empieza con `^` o termina con `$` (sin escapar).
+fn is_anchored(pattern: &str) -> bool {
+    let pattern = pattern.trim();
+    let body = pattern.strip_prefix("(?i)").unwrap_or(pattern);
+    body.starts_with('^') || (body.ends_with('$') && !body.ends_with("\\$"))
+}
+
+static RESOLUTION_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)(?:^|[^a-z0-9])(?:(\d{3,4})[pi]|([48])k)(?:[^a-z0-9]|$)").unwrap());
+
+/// Altura vertical indicada en el nombre de un video (`720p`, `1080i`, `4K`),
+/// para ordenar por resolución. Los nombres sin indicación quedan al final.
+fn resolution_rank(path: &Path) -> u32 {
+    let Some(name) = path.file_stem().and_then(OsStr::to_str) else {
+        return 0;
+    };
+    RESOLUTION_RE
+        .captures_iter(name)
+        .filter_map(|caps| match (caps.get(1), caps.get(2)) {
+            (Some(height), _) => height.as_str().parse().ok(),
+            (None, Some(k)) => Some(if k.as_str() == "8" { 4320 } else { 2160 }),
+            _ => None,
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+static OFFSET_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?:^|[.\s_])([+-]\d+(?:\.\d+)?s)$").unwrap());
+
+/// Marca de desfase al final del nombre base de un subtítulo (`Show.S01E05.+2s`).
+fn extract_offset(stem: &str) -> Option<&str> {
+    OFFSET_RE.captures(stem)?.get(1).map(|m| m.as_str())
+}
+
+/// Valor del campo `Title:` de la sección `[Script Info]` de un subtítulo ASS/SSA.
+/// Devuelve `None` si el archivo no es `.ass`/`.ssa` o no tiene título.
+fn read_ass_title(path: &Path) -> Option<String> {
+    let extension = path.extension()?.to_str()?.to_lowercase();
+    if extension != "ass" && extension != "ssa" {
+        return None;
+    }
+
+    let content = fs::read(path).ok()?;
+    let content = String::from_utf8_lossy(&content);
+    let mut in_script_info = false;
+    for line in content.lines() {
+        let line = line.trim_start_matches('\u{feff}').trim();
+        if line.starts_with('[') {
+            in_script_info = line.eq_ignore_ascii_case("[script info]");
+            continue;
+        }
+        if in_script_info
+            && let Some((key, value)) = line.split_once(':')
+            && key.trim().eq_ignore_ascii_case("title")
+        {
+            let title = value.trim();
+            return (!title.is_empty()).then(|| title.to_string());
+        }
+    }
+    None
+}
+
+/// Interpreta un manifiesto de hashes: una entrada `<crc32 hex> <id>` por línea.
+/// Las líneas vacías y las que empiezan con `#` se ignoran.
+fn parse_hash_manifest(content: &str, lang: Lang) -> Result<HashMap<String, String>> {
+    let mut hashes = HashMap::new();
+    for (number, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut fields = line.split_whitespace();
+        let entry = fields.next()
+            .zip(fields.next())
+            .and_then(|(hash, id)| Some((u32::from_str_radix(hash, 16).ok()?, id)));
+        match entry {
+            Some((crc, id)) => {
+                hashes.insert(format!("{:08x}", crc), id.to_string());
+            }
+            None => anyhow::bail!(Msg::HashManifestLine { line: number + 1, content: line }.text(lang)),
+        }
+    }
+    Ok(hashes)
+}
+
+/// Entradas de un CSV `título,ID`, con el título normalizado por `title_words`.
+/// El ID va tras la última coma, así el título puede contener comas; se
+/// ignoran las líneas vacías, los comentarios `#` y una cabecera `title,id`.
+fn parse_title_map(content: &str) -> Result<Vec<(String, String)>, (usize, String)> {
+    let mut entries = Vec::new();
+    for (number, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || (number == 0 && line.eq_ignore_ascii_case("title,id")) {
+            continue;
+        }
+        let unquote = |field: &str| field.trim().trim_matches('"').trim().to_string();
+        let entry = line.rsplit_once(',').map(|(title, id)| (title_words(&unquote(title)), unquote(id)));
+        match entry {
+            Some((title, id)) if !title.is_empty() && !id.is_empty() => entries.push((title, id)),
+            _ => return Err((number + 1, line.to_string())),
+        }
+    }
+    Ok(entries)
+}
+
+/// Palabras de `text` en minúsculas y sin diacríticos, separadas por un espacio.
+fn title_words(text: &str) -> String {
+    strip_accents(text)
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Primer código de cada bloque Unicode de dígitos decimales (0-9 consecutivos).
+const DIGIT_BLOCK_ZEROS: &[u32] = &[
+    0x0660, 0x06F0, 0x07C0, 0x0966, 0x09E6, 0x0A66, 0x0AE6, 0x0B66, 0x0BE6, 0x0C66, 0x0CE6,
+    0x0D66, 0x0E50, 0x0ED0, 0x0F20, 0x1040, 0x17E0, 0x1810, 0x2080, 0xFF10,
+];
+
+/// Letras latinas con diacríticos y su forma sin ellos.
+const ACCENT_FOLDS: &[(&str, char)] = &[
+    ("ÀÁÂÃÄÅĀĂĄ", 'A'), ("àáâãäåāăą", 'a'), ("ÇĆĈĊČ", 'C'), ("çćĉċč", 'c'), ("ĎĐ", 'D'), ("ďđ", 'd'),
+    ("ÈÉÊËĒĔĖĘĚ", 'E'), ("èéêëēĕėęě", 'e'), ("ĜĞĠĢ", 'G'), ("ĝğġģ", 'g'), ("ĤĦ", 'H'), ("ĥħ", 'h'),
+    ("ÌÍÎÏĨĪĬĮİ", 'I'), ("ìíîïĩīĭįı", 'i'), ("Ĵ", 'J'), ("ĵ", 'j'), ("Ķ", 'K'), ("ķ", 'k'),
+    ("ĹĻĽĿŁ", 'L'), ("ĺļľŀł", 'l'), ("ÑŃŅŇ", 'N'), ("ñńņň", 'n'), ("ÒÓÔÕÖØŌŎŐ", 'O'), ("òóôõöøōŏő", 'o'),
+    ("ŔŖŘ", 'R'), ("ŕŗř", 'r'), ("ŚŜŞŠ", 'S'), ("śŝşš", 's'), ("ŢŤŦ", 'T'), ("ţťŧ", 't'),
+    ("ÙÚÛÜŨŪŬŮŰŲ", 'U'), ("ùúûüũūŭůűų", 'u'), ("Ŵ", 'W'), ("ŵ", 'w'), ("ÝŸŶ", 'Y'), ("ýÿŷ", 'y'),
+    ("ŹŻŽ", 'Z'), ("źżž", 'z'),
+];
+
+/// Quita los diacríticos de `name`: las marcas combinantes (U+0300..U+036F,
+/// como en los nombres en forma NFD de macOS) y las letras latinas precompuestas.
+fn strip_accents(name: &str) -> String {
+    name.chars()
+        .filter(|c| !('\u{0300}'..='\u{036F}').contains(c))
+        .map(|c| {
+            ACCENT_FOLDS
+                .iter()
+                .find(|(accented, _)| accented.contains(c))
+                .map_or(c, |&(_, plain)| plain)
+        })
+        .collect()
+}
+
+/// Convierte formas de ancho completo y numerales Unicode estilizados a ASCII.
+fn normalize_digits(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            let code = c as u32;
+            let digit = match code {
+                // Dígitos matemáticos (negrita, doble trazo, sans-serif, monoespaciado)
+                0x1D7CE..=0x1D7FF => Some((code - 0x1D7CE) % 10),
+                // Superíndices
+                0x2070 => Some(0),
+                0x00B9 => Some(1),
+                0x00B2 => Some(2),
+                0x00B3 => Some(3),
+                0x2074..=0x2079 => Some(code - 0x2070),
+                // Dígitos en círculo ⓪ ①..⑨
+                0x24EA => Some(0),
+                0x2460..=0x2468 => Some(code - 0x2460 + 1),
+                _ => DIGIT_BLOCK_ZEROS
+                    .iter()
+                    .find(|&&zero| (zero..zero + 10).contains(&code))
+                    .map(|&zero| code - zero),
+            };
+            match (digit, code) {
+                (Some(d), _) => char::from_digit(d, 10).unwrap(),
+                // Resto de formas ASCII de ancho completo (letras, signos)
+                (None, 0xFF01..=0xFF5E) => char::from_u32(code - 0xFEE0).unwrap_or(c),
+                (None, 0x3000) => ' ',
+                (None, _) => c,
+            }
+        })
+        .collect()
+}
+
+/// Comparación estilo glob con `*` (cualquier secuencia) y `?` (un carácter).
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Última posición de `*` en el patrón y del texto cuando se encontró
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = backtrack {
+            p = star_p + 1;
+            t = star_t + 1;
+            backtrack = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Nombre de archivo de `path` para mostrar en mensajes.
+fn file_name(path: &Path) -> &OsStr {
+    path.file_name().unwrap_or_default()
+}
+
+/// Columnas de una línea de dry-run que no son ninguno de los dos nombres
+/// (emoji, `[DRY RUN]`, comillas y flecha).
+const PREVIEW_LINE_OVERHEAD: usize = 20;
+
+/// Ancho mínimo de un nombre recortado, para que siga siendo reconocible.
+const MIN_NAME_WIDTH: usize = 12;
+
+/// Ancho de la terminal según `$COLUMNS`, solo si la salida es una terminal.
+fn terminal_width() -> Option<usize> {
+    use io::IsTerminal;
+
+    if !io::stdout().is_terminal() {
+        return None;
+    }
+    std::env::var("COLUMNS").ok()?.trim().parse().ok()
+}
+
+/// Recorta `name` a `width` caracteres quitando el medio (`inicio…fin.ext`) y
+/// conservando la extensión; si no cabe ni la extensión, se recorta entero.
+fn truncate_middle(name: &str, width: usize) -> Cow<'_, str> {
+    if name.chars().count() <= width {
+        return Cow::Borrowed(name);
+    }
+    let (stem, ext) = match name.rfind('.') {
+        Some(dot) if dot > 0 && name[dot..].chars().count() + 3 <= width => name.split_at(dot),
+        _ => (name, ""),
+    };
+    let budget = width.saturating_sub(ext.chars().count() + 1);
+    let head = budget.div_ceil(2);
+    let tail = budget - head;
+    let start: String = stem.chars().take(head).collect();
+    let end: String = stem.chars().skip(stem.chars().count() - tail).collect();
+    Cow::Owned(format!("{}…{}{}", start, end, ext))
+}
+
+/// Directorio que contiene `path`, o `.` si no tiene padre.
+fn parent_dir(path: &Path) -> &Path {
+    path.parent().unwrap_or_else(|| Path::new("."))
+}
+
+/// Construye la variante numerada `n` de `path` según `format`.
+fn numbered_destination(path: &Path, n: usize, format: NumberFormat) -> PathBuf {
+    let stem = path.file_stem().and_then(OsStr::to_str).unwrap_or("unknown");
+    let name = match (format, path.extension().and_then(OsStr::to_str)) {
+        (NumberFormat::Dot, Some(ext)) => format!("{}.{}.{}", stem, n, ext),
+        (NumberFormat::Dot, None) => format!("{}.{}", stem, n),
+        (NumberFormat::Paren, Some(ext)) => format!("{} ({}).{}", stem, n, ext),
+        (NumberFormat::Paren, None) => format!("{} ({})", stem, n),
+    };
+    path.with_file_name(name)
+}
+
+/// Busca el primer destino numerado libre (empezando en 2) en hasta `max_attempts` intentos.
+fn resolve_numbered_destination(
+    path: &Path,
+    format: NumberFormat,
+    max_attempts: usize,
+    is_taken: impl Fn(&Path) -> bool,
+) -> Option<PathBuf> {
+    (2..2 + max_attempts)
+        .map(|n| numbered_destination(path, n, format))
+        .find(|candidate| !is_taken(candidate))
+}
+
+/// `text` entre comillas simples de sh; cada `'` se escribe como `'\\''`.
+fn shell_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "'\\''"))
+}
+
+/// Renombra `op` teniendo en cuenta un origen de solo lectura: con
+/// `handle_readonly` (`--handle-readonly`) quita el atributo y lo restaura en el
+/// destino (o en el origen si falla); sin la opción, un error lo explica en vez
+/// del mensaje del sistema. Libre de `self` para poder usarse desde los hilos
+/// de `--parallel-execute`.
+fn rename_respecting_readonly(op: &RenameOperation, case_only: bool, handle_readonly: bool, lang: Lang) -> io::Result<()> {
+    let rename = || if case_only { rename_case_only(&op.from, &op.to) } else { apply_operation(op) };
+    let original = match fs::metadata(&op.from) {
+        Ok(metadata) if op.archive_entry.is_none() && metadata.permissions().readonly() => metadata.permissions(),
+        _ => return rename(),
+    };
+    if !handle_readonly {
+        return rename().map_err(|e| io::Error::new(e.kind(), Msg::ReadOnlySource(e.to_string()).text(lang)));
+    }
+
+    fs::set_permissions(&op.from, writable(original.clone()))?;
+    match rename() {
+        Ok(()) => fs::set_permissions(&op.to, original),
+        Err(e) => {
+            let _ = fs::set_permissions(&op.from, original);
+            Err(e)
+        }
+    }
+}
+
+/// Ejecuta `op`: extrae la entrada del zip o renombra el archivo.
+fn apply_operation(op: &RenameOperation) -> io::Result<()> {
+    match &op.archive_entry {
+        Some(entry) => extract_archive_entry(&op.from, entry, &op.to),
+        None => fs::rename(&op.from, &op.to),
+    }
+}
+
+/// `permissions` sin el atributo de solo lectura. En Unix solo se añade el
+/// permiso de escritura del dueño, sin abrir el archivo a otros usuarios.
+fn writable(mut permissions: fs::Permissions) -> fs::Permissions {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        permissions.set_mode(permissions.mode() | 0o200);
+    }
+    #[cfg(not(unix))]
+    #[allow(clippy::permissions_set_readonly_false)]
+    permissions.set_readonly(false);
+    permissions
+}
+
+/// Archivo de bloqueo de `--concurrency-safe` dentro de `--directory`.
+const LOCK_FILE_NAME: &str = ".sub_renamer.lock";
+
+/// Toma un bloqueo exclusivo sobre `path` sin esperar. El archivo se deja en su
+/// sitio al terminar (borrarlo abriría una carrera con quien lo esté abriendo);
+/// guarda el PID de quien lo tiene para el mensaje de la otra instancia.
+fn acquire_lock(path: &Path, lang: Lang) -> Result<fs::File> {
+    use io::Write;
+
+    let mut file = fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)
+        .with_context(|| Msg::LockFailed(path).text(lang))?;
+    match file.try_lock() {
+        Ok(()) => {}
+        Err(fs::TryLockError::WouldBlock) => {
+            let owner = fs::read_to_string(path)
+                .ok()
+                .filter(|pid| !pid.trim().is_empty())
+                .map(|pid| format!(" (PID {})", pid.trim()))
+                .unwrap_or_default();
+            anyhow::bail!(Msg::DirectoryLocked { path, owner }.text(lang));
+        }
+        Err(fs::TryLockError::Error(e)) => return Err(e).with_context(|| Msg::LockFailed(path).text(lang)),
+    }
+    file.set_len(0)?;
+    write!(file, "{}", std::process::id())?;
+    Ok(file)
+}
+
+/// Comprueba que se pueden crear archivos en `dir` creando y borrando uno temporal.
+fn check_writable(dir: &Path) -> io::Result<()> {
+    let probe = dir.join(format!(".sub-renamer-write-test-{}", std::process::id()));
+    fs::OpenOptions::new().write(true).create_new(true).open(&probe)?;
+    fs::remove_file(&probe)
+}
+
+/// Indica si dos rutas solo difieren en mayúsculas/minúsculas.
+fn differs_only_by_case(a: &Path, b: &Path) -> bool {
+    let (a, b) = (a.to_string_lossy(), b.to_string_lossy());
+    a != b && a.to_lowercase() == b.to_lowercase()
+}
+
+/// Indica si `a` y `b` son el mismo archivo en disco (misma entrada, aunque los
+/// nombres difieran en mayúsculas en un sistema de archivos que las ignora).
+#[cfg(unix)]
+fn is_same_file(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    match (fs::metadata(a), fs::metadata(b)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn is_same_file(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Extrae `entry` del zip `archive` en `to`, sin sobrescribir un archivo existente.
+fn extract_archive_entry(archive: &Path, entry: &str, to: &Path) -> io::Result<()> {
+    use io::Write;
+
+    let content = archive::read_entry(archive, entry)?;
+    fs::OpenOptions::new().write(true).create_new(true).open(to)?.write_all(&content)
+}
+
+/// Renombra en dos pasos pasando por un nombre temporal, para que los sistemas
+/// de archivos que ignoran mayúsculas apliquen el cambio en vez de ignorarlo.
+fn rename_case_only(from: &Path, to: &Path) -> io::Result<()> {
+    let mut tmp_name = from.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(".sub-renamer-tmp");
+    let tmp = from.with_file_name(tmp_name);
+
+    fs::rename(from, &tmp)?;
+    if let Err(e) = fs::rename(&tmp, to) {
+        // Intentar dejar el archivo como estaba
+        let _ = fs::rename(&tmp, from);
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// `--dump-config`: una línea `opción: valor` por cada opción, indicando si el
+/// valor es el predeterminado o se deriva de otras opciones o del entorno (el
+/// idioma, el regex que falta, las opciones implícitas).
+fn dump_config<W: io::Write>(matches: &ArgMatches, mut args: Args, mut out: W) -> io::Result<()> {
+    let lang = args.lang.unwrap_or_else(Lang::from_env);
+    args.apply_implied();
+    let (srt_regex, mkv_regex) = args.effective_regexes();
+    for arg in Args::command().get_arguments() {
+        let id = arg.get_id().as_str();
+        let Some(long) = arg.get_long() else { continue };
+        if matches!(id, "help" | "version" | "dump_config") {
+            continue;
+        }
+        let source = matches.value_source(id);
+        let given = source.is_some_and(|source| source != clap::parser::ValueSource::DefaultValue);
+        let derived = match id {
+            _ if given => None,
+            "lang" => lang.to_possible_value().map(|value| value.get_name().to_string()),
+            "srt_regex" => srt_regex.map(str::to_string),
+            "mkv_regex" => mkv_regex.map(str::to_string),
+            "dry_run" if args.dry_run => Some("true".to_string()),
+            "fix_case" if args.fix_case => Some("true".to_string()),
+            "match_video_dir" if args.match_video_dir => Some("true".to_string()),
+            _ => None,
+        };
+        let value = matches
+            .get_raw(id)
+            .map(|values| values.map(|v| v.to_string_lossy()).collect::<Vec<_>>().join(" "))
+            .unwrap_or_default();
+        match (derived, source) {
+            (Some(derived), _) => writeln!(out, "{}: {}  # {}", long, derived, Msg::ConfigDerived.text(lang))?,
+            (None, Some(clap::parser::ValueSource::DefaultValue)) => {
+                writeln!(out, "{}: {}  # {}", long, value, Msg::ConfigDefault.text(lang))?
+            }
+            (None, Some(_)) => writeln!(out, "{}: {}", long, value)?,
+            (None, None) => writeln!(out, "{}: -", long)?,
+        }
+    }
+    Ok(())
+}
+
+/// Subcomando `validate-regex`: muestra el ID que se extraería de cada ejemplo.
+fn validate_regex<W: io::Write>(args: &Args, pattern: &str, samples: &[String], mut out: W) -> Result<()> {
+    let lang = args.lang.unwrap_or_else(Lang::from_env);
+    let regex = args.episode_regex(pattern)
+        .with_context(|| Msg::InvalidRegex(pattern).text(lang))?;
+    args.check_capture_group(&regex, lang)?;
+    let strip = args.strip_regex(lang)?;
+
+    for sample in samples {
+        let name = OsStr::new(sample);
+        let text = args.prepare_match_text(strip.as_ref(), sample);
+        let line = match args.capture_episode_id(&regex, &text) {
+            Some(id) => Msg::RegexMatch { name, id: &id }.text(lang),
+            None => Msg::RegexNoMatch(name).text(lang),
+        };
+        writeln!(out, "{}", line)?;
+    }
+    Ok(())
+}
+
+/// Definición de la CLI con la tabla de códigos de salida en `lang`.
+fn cli_command(lang: Lang) -> clap::Command {
+    Args::command().after_long_help(Msg::ExitCodesHelp.text(lang))
+}
+
+/// Idioma para la ayuda: la ayuda se muestra antes de interpretar los
+/// argumentos, así que `--lang` se busca a mano y, si falta, manda el entorno.
+fn help_lang(argv: &[OsString]) -> Lang {
+    let value = argv.iter().zip(argv.iter().skip(1)).find_map(|(arg, next)| {
+        let arg = arg.to_str()?;
+        match arg.strip_prefix("--lang=") {
+            Some(value) => Some(value),
+            None => (arg == "--lang").then(|| next.to_str()).flatten(),
+        }
+    });
+    value.and_then(|value| Lang::from_str(value, true).ok()).unwrap_or_else(Lang::from_env)
+}
+
+fn main() -> Result<()> {
+    let argv: Vec<OsString> = std::env::args_os().collect();
+    let matches = cli_command(help_lang(&argv)).get_matches_from(argv);
+    let args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+
+    if args.dump_config {
+        dump_config(&matches, args, io::stdout().lock())?;
+        return Ok(());
+    }
+
+    if let Some(Command::ValidateRegex { pattern, samples }) = &args.command {
+        return validate_regex(&args, pattern, samples, io::stdout().lock());
+    }
+
+    // Mostrar ayuda si no se proporcionan regex
+    if args.requires_matcher() && !args.has_matcher() {
+        let lang = args.lang.unwrap_or_else(Lang::from_env);
+        eprintln!("{}", Msg::Usage.text(lang));
+        
+        std::process::exit(1);
+    }
+
+    let no_op_exit_code = args.no_op_exit_code;
+    let renamer = SubtitleRenamer::new(args)?;
+    if renamer.run()? == RunStatus::NothingToDo
+        && let Some(code) = no_op_exit_code
+    {
+        std::process::exit(code.into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    /// Búfer compartido para capturar la salida estándar del renombrador.
+    #[derive(Clone, Default)]
+    struct SharedBuffer(std::rc::Rc<RefCell<Vec<u8>>>);
+
+    impl io::Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl SharedBuffer {
+        fn text(&self) -> String {
+            String::from_utf8_lossy(&self.0.borrow()).into_owned()
+        }
+    }
+
+    /// Argumentos por defecto de la CLI apuntando a `dir`, sin regex.
+    fn test_args(dir: &Path) -> Args {
+        Args::parse_from(["sub-renamer", "--directory", dir.to_str().unwrap()])
+    }
+
+    #[test]
+    fn test_parse_extensions() {
+        assert_eq!(
+            SubtitleRenamer::parse_extensions("srt,ass,vtt"),
+            vec!["srt", "ass", "vtt"]
+        );
+        assert_eq!(
+            SubtitleRenamer::parse_extensions("mkv, mp4 , avi"),
+            vec!["mkv", "mp4", "avi"]
+        );
+        assert_eq!(
+            SubtitleRenamer::parse_extensions(""),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            SubtitleRenamer::parse_extensions("SRT, .Ass"),
+            vec!["srt", "ass"]
+        );
+    }
+
+    #[test]
+    fn test_extract_episode_id() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        
+        // Test con regex que captura solo temporada
+        let args1 = Args {
+            srt_regex: Some(r"S(\d{2})E\d{2}".to_string()),
+            mkv_regex: Some(r"S(\d{2})E\d{2}".to_string()),
+            ..test_args(temp_dir.path())
+        };
+
+        let renamer1 = SubtitleRenamer::new(args1)?;
+        let test_path1 = temp_dir.path().join("Show.S01E05.1080p.mkv");
+        fs::write(&test_path1, b"")?;
+
+        let episode_id1 = renamer1.extract_episode_id(&test_path1, false);
+        assert_eq!(episode_id1, Some("01".to_string()));
+
+        // Test con regex que captura temporada y episodio
+        let args2 = Args {
+            srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+            mkv_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+            ..test_args(temp_dir.path())
+        };
+
+        let renamer2 = SubtitleRenamer::new(args2)?;
+        let test_path2 = temp_dir.path().join("Show.S01E05.1080p.mkv");
+        fs::write(&test_path2, b"")?;
+
+        let episode_id2 = renamer2.extract_episode_id(&test_path2, false);
+        assert_eq!(episode_id2, Some("S01E05".to_string()));
+
+        // Test con regex que captura múltiples grupos
+        let args3 = Args {
+            srt_regex: Some(r"S(\d{2})E(\d{2})".to_string()),
+            mkv_regex: Some(r"S(\d{2})E(\d{2})".to_string()),
+            ..test_args(temp_dir.path())
+        };
+
+        let renamer3 = SubtitleRenamer::new(args3)?;
+        let test_path3 = temp_dir.path().join("Show.S01E05.1080p.mkv");
+        fs::write(&test_path3, b"")?;
+
+        let episode_id3 = renamer3.extract_episode_id(&test_path3, false);
+        // Con múltiples grupos, solo toma el primer grupo de captura
+        assert_eq!(episode_id3, Some("01".to_string()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_episode_id_various_formats() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        
+        // Test con diferentes formatos de nombres de archivo
+        let test_cases = vec![
+            // (regex, filename, expected_result)
+            (r"(S\d{2}E\d{2})", "Show.S01E05.1080p.mkv", Some("S01E05")),
+            (r"(\d{1,2}x\d{2})", "Show.1x05.1080p.mkv", Some("1x05")),
+            (r"Episode\.(\d+)", "Show.Episode.5.mkv", Some("5")),
+            (r"Ep(\d+)", "Show.Ep05.mkv", Some("05")),
+            (r"(S\d{2}E\d{2})", "Show.no.match.mkv", None),
+        ];
+
+        for (regex_str, filename, expected) in test_cases {
+            let args = Args {
+                srt_regex: Some(regex_str.to_string()),
+                mkv_regex: Some(regex_str.to_string()),
+                ..test_args(temp_dir.path())
+            };
+
+            let renamer = SubtitleRenamer::new(args)?;
+            let test_path = temp_dir.path().join(filename);
+            fs::write(&test_path, b"")?;
+
+            let episode_id = renamer.extract_episode_id(&test_path, false);
+            assert_eq!(
+                episode_id,
+                expected.map(|s| s.to_string()),
+                "Failed for regex '{}' and filename '{}'",
+                regex_str,
+                filename
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_json_report_compact_vs_pretty() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        fs::write(temp_dir.path().join("Show.S01E05.srt"), b"")?;
+        fs::write(temp_dir.path().join("Show.S01E05.1080p.mkv"), b"")?;
+        fs::write(temp_dir.path().join("Show.S01E06.srt"), b"")?;
+
+        let args = Args {
+            srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+            dry_run: true,
+            quiet: true,
+            format: OutputFormat::Json,
+            json_compact: true,
+            ..test_args(temp_dir.path())
+        };
+        let renamer = SubtitleRenamer::new(args)?;
+        let (subtitles, videos) = renamer.categorize_files()?;
+        let plan = renamer.plan_renames(subtitles, videos);
+        let outcomes = renamer.execute_renames(plan.operations);
+        let summary = RunSummary::from_outcomes(&outcomes, plan.unmatched.len());
+        assert_eq!(summary.succeeded, 1);
+        assert_eq!(summary.unmatched, 1);
+
+        let report = renamer.build_report(&outcomes, &plan.unmatched, &summary);
+        let mut compact = Vec::new();
+        renamer.write_report(&mut compact, &report)?;
+        let compact = String::from_utf8(compact)?;
+        assert_eq!(compact.lines().count(), 1);
+        assert!(compact.contains(r#""status":"dry_run""#));
+        assert!(compact.contains(r#""summary":{"succeeded":1,"skipped":0,"errors":0,"unmatched":1,"duration_seconds":0}"#));
+
+        let mut pretty = Vec::new();
+        json::to_writer_pretty(&mut pretty, &report)?;
+        assert!(String::from_utf8(pretty)?.lines().count() > 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_differs_only_by_case() {
+        assert!(differs_only_by_case(Path::new("a/Show.S01E05.SRT"), Path::new("a/Show.S01E05.srt")));
+        assert!(!differs_only_by_case(Path::new("a/Show.srt"), Path::new("a/Show.srt")));
+        assert!(!differs_only_by_case(Path::new("a/Show.S01E05.srt"), Path::new("a/Show.S01E06.srt")));
+    }
+
+    #[test]
+    fn test_case_only_rename_skipped_without_fix_case() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let from = temp_dir.path().join("show.s01e05.srt");
+        let to = temp_dir.path().join("Show.S01E05.srt");
+        fs::write(&from, b"")?;
+        let case_insensitive = to.exists();
+
+        let renamer = SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(?i)(s\d{2}e\d{2})".to_string()),
+            quiet: true,
+            ..test_args(temp_dir.path())
+        })?;
+        let outcomes = renamer.execute_renames(vec![RenameOperation {
+            from: from.clone(),
+            to: to.clone(),
+            episode_id: "s01e05".to_string(),
+            archive_entry: None,
+        }]);
+        // Solo se omite si el destino es el mismo archivo; si no, es un renombrado normal
+        if case_insensitive {
+            assert_eq!(outcomes[0].status, OperationStatus::Skipped);
+            assert!(from.exists());
+        } else {
+            assert_eq!(outcomes[0].status, OperationStatus::Renamed);
+            fs::rename(&to, &from)?;
+        }
+
+        let renamer = SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(?i)(s\d{2}e\d{2})".to_string()),
+            quiet: true,
+            fix_case: true,
+            ..test_args(temp_dir.path())
+        })?;
+        let outcomes = renamer.execute_renames(vec![RenameOperation {
+            from,
+            to,
+            episode_id: "s01e05".to_string(),
+            archive_entry: None,
+        }]);
+        assert_eq!(outcomes[0].status, OperationStatus::Renamed);
+        let names: Vec<_> = fs::read_dir(temp_dir.path())?
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(names, vec!["Show.S01E05.srt"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_case_only_rename_never_overwrites_another_file() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let from = temp_dir.path().join("show.s01e05.srt");
+        let to = temp_dir.path().join("Show.S01E05.srt");
+        fs::write(&from, b"NEW")?;
+        if to.exists() {
+            // En un sistema que ignora mayúsculas no pueden coexistir los dos archivos
+            return Ok(());
+        }
+        fs::write(&to, b"EXISTING")?;
+        fs::write(temp_dir.path().join("Show.S01E05.mkv"), b"")?;
+
+        for fix_case in [false, true] {
+            let renamer = SubtitleRenamer::new(Args {
+                srt_regex: Some(r"(?i)s\d{2}e(\d{2})".to_string()),
+                fix_case,
+                quiet: true,
+                ..test_args(temp_dir.path())
+            })?;
+            let outcomes = renamer.execute_renames(vec![RenameOperation {
+                from: from.clone(),
+                to: to.clone(),
+                episode_id: "05".to_string(),
+                archive_entry: None,
+            }]);
+            assert_eq!(outcomes[0].status, OperationStatus::Skipped);
+            assert_eq!(fs::read(&from)?, b"NEW");
+            assert_eq!(fs::read(&to)?, b"EXISTING");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_colocated_only_skips_subtitles_in_other_directories() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let subs_dir = temp_dir.path().join("subs");
+        fs::create_dir(&subs_dir)?;
+        fs::write(temp_dir.path().join("Show.S01E05.1080p.mkv"), b"")?;
+        fs::write(temp_dir.path().join("Show.S01E05.srt"), b"")?;
+        fs::write(temp_dir.path().join("Show.S01E06.1080p.mkv"), b"")?;
+        fs::write(subs_dir.join("Show.S01E06.srt"), b"")?;
+
+        let args = Args {
+            srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+            recursive: true,
+            quiet: true,
+            ..test_args(temp_dir.path())
+        };
+        let renamer = SubtitleRenamer::new(args)?;
+        let (subtitles, videos) = renamer.categorize_files()?;
+        assert_eq!(renamer.plan_renames(subtitles, videos).operations.len(), 2);
+
+        let renamer = SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+            recursive: true,
+            quiet: true,
+            colocated_only: true,
+            ..test_args(temp_dir.path())
+        })?;
+        let (subtitles, videos) = renamer.categorize_files()?;
+        let plan = renamer.plan_renames(subtitles, videos);
+        assert_eq!(plan.operations.len(), 1);
+        assert_eq!(plan.operations[0].to, temp_dir.path().join("Show.S01E05.1080p.srt"));
+        assert_eq!(plan.unmatched, vec![subs_dir.join("Show.S01E06.srt")]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_numbered_destination_formats() {
+        let path = Path::new("dir/Show.S01E05.srt");
+        assert_eq!(
+            numbered_destination(path, 2, NumberFormat::Dot),
+            Path::new("dir/Show.S01E05.2.srt")
+        );
+        assert_eq!(
+            numbered_destination(path, 3, NumberFormat::Paren),
+            Path::new("dir/Show.S01E05 (3).srt")
+        );
+    }
+
+    #[test]
+    fn test_resolve_numbered_destination() {
+        let path = Path::new("dir/Show.srt");
+        let taken: HashSet<PathBuf> = [PathBuf::from("dir/Show.2.srt"), PathBuf::from("dir/Show.3.srt")]
+            .into_iter()
+            .collect();
+
+        assert_eq!(
+            resolve_numbered_destination(path, NumberFormat::Dot, 5, |p| taken.contains(p)),
+            Some(PathBuf::from("dir/Show.4.srt"))
+        );
+        // Con solo dos intentos (2 y 3) no queda ninguno libre
+        assert_eq!(
+            resolve_numbered_destination(path, NumberFormat::Dot, 2, |p| taken.contains(p)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_number_conflicts_in_execute_renames() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let from = temp_dir.path().join("Show.S01E05.srt");
+        let to = temp_dir.path().join("Show.S01E05.1080p.srt");
+        fs::write(&from, b"nuevo")?;
+        fs::write(&to, b"existente")?;
+
+        let renamer = SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+            quiet: true,
+            number_conflicts: true,
+            number_format: NumberFormat::Paren,
+            ..test_args(temp_dir.path())
+        })?;
+        let outcomes = renamer.execute_renames(vec![RenameOperation {
+            from,
+            to: to.clone(),
+            episode_id: "S01E05".to_string(),
+            archive_entry: None,
+        }]);
+
+        let numbered = temp_dir.path().join("Show.S01E05.1080p (2).srt");
+        assert_eq!(outcomes[0].status, OperationStatus::Renamed);
+        assert_eq!(outcomes[0].op.to, numbered);
+        assert_eq!(fs::read(&numbered)?, b"nuevo");
+        assert_eq!(fs::read(&to)?, b"existente");
+        Ok(())
+    }
+
+    #[test]
+    fn test_part_regex_pairs_multi_part_movies() -> Result<()> {
+        let cases = [
+            ("Movie.CD1.srt", "Movie.CD1.1080p.mkv", "Movie.CD2.srt", "Movie.CD2.1080p.mkv"),
+            ("Movie Part 1.srt", "Movie.Part1.mkv", "Movie Part 2.srt", "Movie.Part2.mkv"),
+        ];
+
+        for (sub1, video1, sub2, video2) in cases {
+            let temp_dir = TempDir::new()?;
+            for name in [sub1, video1, sub2, video2] {
+                fs::write(temp_dir.path().join(name), b"")?;
+            }
+
+            let renamer = SubtitleRenamer::new(Args {
+                part_regex: Some(r"(?i)(?:cd|part)\s*(\d+)".to_string()),
+                quiet: true,
+                ..test_args(temp_dir.path())
+            })?;
+            let (subtitles, videos) = renamer.categorize_files()?;
+            let mut targets: Vec<_> = renamer
+                .plan_renames(subtitles, videos)
+                .operations
+                .into_iter()
+                .map(|op| op.to.file_name().unwrap().to_string_lossy().into_owned())
+                .collect();
+            targets.sort();
+
+            let expected = |video: &str| format!("{}.srt", video.trim_end_matches(".mkv"));
+            assert_eq!(targets, vec![expected(video1), expected(video2)], "casos {} / {}", sub1, sub2);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_part_regex_combines_with_episode_id() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let renamer = SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+            part_regex: Some(r"(?i)part\s*(\d+)".to_string()),
+            ..test_args(temp_dir.path())
+        })?;
+
+        assert_eq!(
+            renamer.extract_match_key(Path::new("Show.S01E05.Part02.mkv"), false),
+            Some("S01E05.part2".to_string())
+        );
+        assert_eq!(
+            renamer.extract_match_key(Path::new("Show.S01E05.mkv"), false),
+            Some("S01E05".to_string())
+        );
+        assert_eq!(renamer.extract_match_key(Path::new("Show.mkv"), false), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("extras", "extras"));
+        assert!(glob_match("sample*", "samples"));
+        assert!(glob_match("*feat*", "featurettes"));
+        assert!(glob_match("s?mple", "sample"));
+        assert!(!glob_match("extras", "extras2"));
+        assert!(!glob_match("sample*", "season 1"));
+    }
+
+    #[test]
+    fn test_prune_skips_matching_directories() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        for dir in ["Season 1", "Extras", "Season 1/Sample", "Season 1/Subs"] {
+            fs::create_dir_all(temp_dir.path().join(dir))?;
+        }
+        for file in [
+            "Season 1/Show.S01E01.mkv",
+            "Season 1/Subs/Show.S01E01.srt",
+            "Season 1/Sample/Show.S01E01.sample.mkv",
+            "Extras/Show.S01E01.featurette.mkv",
+        ] {
+            fs::write(temp_dir.path().join(file), b"")?;
+        }
+
+        let renamer = SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+            recursive: true,
+            prune: vec!["extras".to_string(), "Sampl*".to_string()],
+            ..test_args(temp_dir.path())
+        })?;
+        let mut files = renamer.get_files()?;
+        files.sort();
+
+        assert_eq!(
+            files,
+            vec![
+                temp_dir.path().join("Season 1/Show.S01E01.mkv"),
+                temp_dir.path().join("Season 1/Subs/Show.S01E01.srt"),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_plan_out_then_apply_plan() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let plan_path = temp_dir.path().join("plan.json");
+        fs::write(temp_dir.path().join("Show.S01E05.srt"), b"sub 5")?;
+        fs::write(temp_dir.path().join("Show.S01E05.1080p.mkv"), b"")?;
+        fs::write(temp_dir.path().join("Show.S01E06.srt"), b"sub 6")?;
+        fs::write(temp_dir.path().join("Show.S01E06.1080p.mkv"), b"")?;
+
+        let planner = SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+            quiet: true,
+            plan_out: Some(plan_path.clone()),
+            ..test_args(temp_dir.path())
+        })?;
+        planner.run()?;
+        // Planificar no renombra nada
+        assert!(temp_dir.path().join("Show.S01E05.srt").exists());
+
+        // Un archivo que cambia tras planificar se descarta al aplicar
+        fs::write(temp_dir.path().join("Show.S01E06.srt"), b"sub 6 editado")?;
+
+        let applier = SubtitleRenamer::new(Args {
+            quiet: true,
+            apply_plan: Some(plan_path),
+            ..test_args(temp_dir.path())
+        })?;
+        let operations = applier.load_plan(applier.args.apply_plan.as_ref().unwrap())?;
+        assert_eq!(operations.len(), 1);
+        assert_eq!(operations[0].episode_id, "S01E05");
+
+        applier.run()?;
+        assert!(temp_dir.path().join("Show.S01E05.1080p.srt").exists());
+        assert!(temp_dir.path().join("Show.S01E06.srt").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_normalize_digits() {
+        assert_eq!(normalize_digits("Ｓ０１Ｅ０５"), "S01E05");
+        assert_eq!(normalize_digits("Ep.٠٥"), "Ep.05");
+        assert_eq!(normalize_digits("Ep 𝟎𝟓 ②"), "Ep 05 2");
+        assert_eq!(normalize_digits("Show.S01E05.mkv"), "Show.S01E05.mkv");
+    }
+
+    #[test]
+    fn test_normalize_digits_recovers_full_width_ids() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().join("番組　Ｅ０５.srt");
+        let args = Args {
+            srt_regex: Some(r"E(\d{2})".to_string()),
+            ..test_args(temp_dir.path())
+        };
+
+        let renamer = SubtitleRenamer::new(args)?;
+        assert_eq!(renamer.extract_episode_id(&path, true), None);
+
+        let renamer = SubtitleRenamer::new(Args {
+            srt_regex: Some(r"E(\d{2})".to_string()),
+            normalize_digits: true,
+            ..test_args(temp_dir.path())
+        })?;
+        assert_eq!(renamer.extract_episode_id(&path, true), Some("05".to_string()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_summary_to_prometheus() {
+        let summary = RunSummary {
+            succeeded: 3,
+            skipped: 1,
+            errors: 0,
+            unmatched: 2,
+            duration: Duration::from_millis(1500),
+            ..Default::default()
+        };
+        let text = summary.to_prometheus(false);
+
+        assert!(text.contains("# HELP sub_renamer_skipped Operations skipped\n"));
+        assert!(text.contains("# TYPE sub_renamer_renamed gauge\nsub_renamer_renamed 3\n"));
+        assert!(text.contains("sub_renamer_skipped 1\n"));
+        assert!(text.contains("sub_renamer_unmatched 2\n"));
+        assert!(text.contains("sub_renamer_duration_seconds 1.5\n"));
+        assert!(text.contains("sub_renamer_dry_run 0\n"));
+        // Cada métrica lleva HELP, TYPE y valor
+        assert_eq!(text.lines().count(), 18);
+    }
+
+    #[test]
+    fn test_metrics_out_written_after_run() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let metrics_path = temp_dir.path().join("metrics").join("sub_renamer.prom");
+        fs::create_dir(temp_dir.path().join("metrics"))?;
+        fs::write(temp_dir.path().join("Show.S01E05.srt"), b"")?;
+        fs::write(temp_dir.path().join("Show.S01E05.1080p.mkv"), b"")?;
+
+        SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+            quiet: true,
+            metrics_out: Some(metrics_path.clone()),
+            ..test_args(temp_dir.path())
+        })?
+        .run()?;
+
+        let text = fs::read_to_string(&metrics_path)?;
+        assert!(text.contains("sub_renamer_renamed 1\n"));
+        assert!(!metrics_path.with_file_name("sub_renamer.prom.tmp").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_hash_manifest() -> Result<()> {
+        let hashes = parse_hash_manifest("# hashes del descargador\nCBF43926 S01E05\n\n  1a2b3c S01E06  \n", Lang::Es)?;
+        assert_eq!(hashes.get("cbf43926"), Some(&"S01E05".to_string()));
+        assert_eq!(hashes.get("001a2b3c"), Some(&"S01E06".to_string()));
+        assert!(parse_hash_manifest("zzzz S01E05", Lang::Es).is_err());
+        assert!(parse_hash_manifest("cbf43926", Lang::Es).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_video_hash_manifest_matches_cryptic_video_names() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let manifest = temp_dir.path().join("hashes.txt");
+        fs::write(temp_dir.path().join("a8f3e1.mkv"), b"123456789 contenido del video")?;
+        fs::write(temp_dir.path().join("Show.S01E05.srt"), b"")?;
+        fs::write(&manifest, "cbf43926 S01E05\n")?;
+
+        let renamer = SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+            quiet: true,
+            video_hash_manifest: Some(manifest),
+            hash_prefix_bytes: Some(9),
+            ..test_args(temp_dir.path())
+        })?;
+        let (subtitles, videos) = renamer.categorize_files()?;
+        let plan = renamer.plan_renames(subtitles, videos);
+
+        assert_eq!(plan.operations.len(), 1);
+        assert_eq!(plan.operations[0].to, temp_dir.path().join("a8f3e1.srt"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_overlapping_extensions_are_rejected() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let result = SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+            srt_ext: "srt,MP4".to_string(),
+            video_ext: "mkv,mp4".to_string(),
+            lang: Some(Lang::En),
+            ..test_args(temp_dir.path())
+        });
+
+        let error = result.err().expect("las extensiones solapadas deben fallar").to_string();
+        assert!(error.contains("mp4"), "{}", error);
+        Ok(())
+    }
+
+    #[test]
+    fn test_rename_dir_only_for_single_episode_directories() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let episode5 = temp_dir.path().join("ep5");
+        let episode6 = temp_dir.path().join("ep6");
+        fs::create_dir(&episode5)?;
+        fs::create_dir(&episode6)?;
+        fs::write(episode5.join("Show.S01E05.srt"), b"")?;
+        fs::write(episode5.join("Show.S01E05.1080p.mkv"), b"")?;
+        fs::write(episode6.join("Show.S01E06.srt"), b"")?;
+        fs::write(episode6.join("Show.S01E06.1080p.mkv"), b"")?;
+        fs::write(episode6.join("notas.txt"), b"")?;
+
+        let renamer = SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+            recursive: true,
+            quiet: true,
+            rename_dir: true,
+            ..test_args(temp_dir.path())
+        })?;
+        let (subtitles, videos) = renamer.categorize_files()?;
+        let plan = renamer.plan_renames(subtitles, videos);
+        assert_eq!(plan.operations.len(), 3);
+        // El directorio se renombra después de sus archivos
+        let last = plan.operations.last().unwrap();
+        assert_eq!(last.from, episode5);
+        assert_eq!(last.to, temp_dir.path().join("Show.S01E05.1080p"));
+
+        renamer.execute_renames(plan.operations);
+        let renamed_dir = temp_dir.path().join("Show.S01E05.1080p");
+        assert!(renamed_dir.join("Show.S01E05.1080p.srt").exists());
+        assert!(renamed_dir.join("Show.S01E05.1080p.mkv").exists());
+        assert!(episode6.join("Show.S01E06.1080p.srt").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_ass_title_match() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let with_title = temp_dir.path().join("track3.ass");
+        let without_title = temp_dir.path().join("Show.S01E06.ass");
+        fs::write(
+            &with_title,
+            "\u{feff}[Script Info]\n; comentario\nTitle: Show - S01E05 - Pilot\nScriptType: v4.00+\n\n[Events]\nTitle: S09E09\n",
+        )?;
+        fs::write(&without_title, "[Script Info]\nScriptType: v4.00+\n")?;
+
+        let renamer = SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+            ass_title_match: true,
+            ..test_args(temp_dir.path())
+        })?;
+        assert_eq!(renamer.extract_episode_id(&with_title, true), Some("S01E05".to_string()));
+        // Sin Title se usa el nombre de archivo
+        assert_eq!(renamer.extract_episode_id(&without_title, true), Some("S01E06".to_string()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_rate() {
+        assert_eq!(parse_rate("0.8"), Ok(0.8));
+        assert_eq!(parse_rate("1"), Ok(1.0));
+        assert!(parse_rate("1.5").is_err());
+        assert!(parse_rate("-0.1").is_err());
+        assert!(parse_rate("mucho").is_err());
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("1048576"), Ok(1 << 20));
+        assert_eq!(parse_size("500K"), Ok(500 << 10));
+        assert_eq!(parse_size("5mb"), Ok(5 << 20));
+        assert_eq!(parse_size("1GiB"), Ok(1 << 30));
+        assert!(parse_size("cinco").is_err());
+        assert!(parse_size("-1M").is_err());
+    }
+
+    #[test]
+    fn test_size_limits_skip_misidentified_files() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        fs::write(temp_dir.path().join("Show.S01E01.mkv"), vec![0; 2048])?;
+        fs::write(temp_dir.path().join("Show.S01E02.mkv"), b"muestra")?;
+        fs::write(temp_dir.path().join("sub.S01E01.srt"), vec![0; 2048])?;
+        fs::write(temp_dir.path().join("sub.S01E02.srt"), b"1")?;
+        fs::write(temp_dir.path().join("Show.S01E03.mkv"), vec![0; 2048])?;
+        fs::write(temp_dir.path().join("sub.S01E03.srt"), b"1")?;
+
+        SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+            max_subtitle_size: Some(1024),
+            min_video_size: Some(1024),
+            quiet: true,
+            ..test_args(temp_dir.path())
+        })?
+        .run()?;
+
+        assert!(temp_dir.path().join("sub.S01E01.srt").exists());
+        assert!(temp_dir.path().join("sub.S01E02.srt").exists());
+        assert!(temp_dir.path().join("Show.S01E03.srt").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_min_match_rate_aborts_low_match_runs() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        fs::write(temp_dir.path().join("Show.S01E05.srt"), b"")?;
+        fs::write(temp_dir.path().join("Show.S01E05.1080p.mkv"), b"")?;
+        fs::write(temp_dir.path().join("Show.S01E06.srt"), b"")?;
+
+        let run_with = |rate: f64| -> Result<RunStatus> {
+            SubtitleRenamer::new(Args {
+                srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+                dry_run: true,
+                quiet: true,
+                min_match_rate: Some(rate),
+                ..test_args(temp_dir.path())
+            })?
+            .run()
+        };
+
+        // 1 de 2 subtítulos emparejados: 50%
+        assert!(run_with(0.8).is_err());
+        assert!(run_with(0.5).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_offset() {
+        assert_eq!(extract_offset("Show.S01E05.+2s"), Some("+2s"));
+        assert_eq!(extract_offset("Show.S01E05 -1.5s"), Some("-1.5s"));
+        assert_eq!(extract_offset("Show.S01E05"), None);
+        assert_eq!(extract_offset("Show.S01E05.2s"), None);
+        assert_eq!(extract_offset("Show.S01E05+2s"), None);
+    }
+
+    #[test]
+    fn test_preserve_offset_in_new_name() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        fs::write(temp_dir.path().join("Show.S01E05.+2s.srt"), b"")?;
+        fs::write(temp_dir.path().join("Show.S01E06.srt"), b"")?;
+        fs::write(temp_dir.path().join("Show.S01E05.1080p.mkv"), b"")?;
+        fs::write(temp_dir.path().join("Show.S01E06.1080p.mkv"), b"")?;
+
+        let renamer = SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+            quiet: true,
+            preserve_offset: true,
+            ..test_args(temp_dir.path())
+        })?;
+        let (subtitles, videos) = renamer.categorize_files()?;
+        let mut targets: Vec<_> = renamer
+            .plan_renames(subtitles, videos)
+            .operations
+            .into_iter()
+            .map(|op| op.to)
+            .collect();
+        targets.sort();
+
+        assert_eq!(
+            targets,
+            vec![
+                temp_dir.path().join("Show.S01E05.1080p.+2s.srt"),
+                temp_dir.path().join("Show.S01E06.1080p.srt"),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_rename_one_without_regex() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let subtitle = temp_dir.path().join("subs_final_v2.srt");
+        let video = temp_dir.path().join("Movie.2019.1080p.mkv");
+        fs::write(&subtitle, b"sub")?;
+        fs::write(&video, b"")?;
+
+        let args = Args::parse_from([
+            OsStr::new("sub-renamer"),
+            OsStr::new("--directory"),
+            temp_dir.path().as_os_str(),
+            OsStr::new("--quiet"),
+            OsStr::new("--rename-one"),
+            subtitle.as_os_str(),
+            video.as_os_str(),
+        ]);
+        assert!(!args.requires_matcher());
+        SubtitleRenamer::new(args)?.run()?;
+
+        assert!(!subtitle.exists());
+        assert!(temp_dir.path().join("Movie.2019.1080p.srt").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_review_answer_parse() {
+        assert_eq!(ReviewAnswer::parse("s\n", Lang::Es), Some(ReviewAnswer::Yes));
+        assert_eq!(ReviewAnswer::parse("Yes", Lang::En), Some(ReviewAnswer::Yes));
+        assert_eq!(ReviewAnswer::parse(" t", Lang::Es), Some(ReviewAnswer::All));
+        assert_eq!(ReviewAnswer::parse("o", Lang::En), Some(ReviewAnswer::OneByOne));
+        assert_eq!(ReviewAnswer::parse("\n", Lang::Es), None);
+        assert_eq!(ReviewAnswer::parse("x", Lang::Es), None);
+        // Solo valen las letras del prompt en el idioma activo
+        assert_eq!(ReviewAnswer::parse("s", Lang::En), None);
+        assert_eq!(ReviewAnswer::parse("y", Lang::Es), None);
+    }
+
+    #[test]
+    fn test_review_operations_pages() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let renamer = SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(\d+)".to_string()),
+            interactive: true,
+            page_size: 2,
+            lang: Some(Lang::Es),
+            ..test_args(temp_dir.path())
+        })?;
+        let operations = || {
+            (1..=7)
+                .map(|n| RenameOperation {
+                    from: PathBuf::from(format!("{}.srt", n)),
+                    to: PathBuf::from(format!("video{}.srt", n)),
+                    episode_id: n.to_string(),
+                    archive_entry: None,
+                })
+                .collect::<Vec<_>>()
+        };
+        let approved_ids = |input: &str| -> Result<Vec<String>> {
+            let approved = renamer.review_operations(operations(), input.as_bytes(), io::sink())?;
+            Ok(approved.into_iter().map(|op| op.episode_id).collect())
+        };
+
+        // Página 1 aprobada, página 2 rechazada, página 3 una a una, resto aprobado
+        assert_eq!(approved_ids("s\nn\nu\nn\n?\ns\nt\n")?, ["1", "2", "6", "7"]);
+        // Terminar descarta lo no revisado; el fin de la entrada también
+        assert_eq!(approved_ids("s\nq\n")?, ["1", "2"]);
+        assert_eq!(approved_ids("")?, Vec::<String>::new());
+        Ok(())
+    }
+
+    #[test]
+    fn test_name_similarity() {
+        assert_eq!(name_similarity("Breaking.Bad.S01E05", "The.Wire.S01E05.720p", "S01E05"), Some(0.0));
+        assert_eq!(name_similarity("breaking_bad_s01e05", "Breaking.Bad.S01E05.720p", "S01E05"), Some(1.0));
+        // El ID capturado puede ser solo una parte de la palabra
+        assert_eq!(name_similarity("Show.S01E05", "Other.S01E05", "05"), Some(0.0));
+        assert_eq!(name_similarity("S01E05", "Show.S01E05", "S01E05"), None);
+    }
+
+    #[test]
+    fn test_history_file_records_renames() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let history_dir = TempDir::new()?;
+        let history_file = history_dir.path().join("history.jsonl");
+        fs::write(temp_dir.path().join("Show.S01E01.srt"), b"")?;
+        fs::write(temp_dir.path().join("Show.S01E01.1080p.mkv"), b"")?;
+
+        let run = |dry_run| -> Result<RunStatus> {
+            SubtitleRenamer::new(Args {
+                srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+                quiet: true,
+                dry_run,
+                history_file: Some(history_file.clone()),
+                ..test_args(temp_dir.path())
+            })?
+            .run()
+        };
+        // En dry-run no se registra nada, pero el archivo ya se valida y crea
+        run(true)?;
+        assert_eq!(fs::read_to_string(&history_file)?, "");
+        run(false)?;
+
+        let content = fs::read_to_string(&history_file)?;
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines.len(), 1);
+        let entry = json::from_str(lines[0])?;
+        let operations = entry.get("operations").and_then(Json::as_array).unwrap();
+        assert_eq!(operations.len(), 1);
+        let to = operations[0].get("to").and_then(Json::as_str).unwrap();
+        assert!(Path::new(to).is_absolute());
+        assert!(to.ends_with("Show.S01E01.1080p.srt"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_unwritable_history_file_is_rejected_at_startup() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let blocker = temp_dir.path().join("not-a-dir");
+        fs::write(&blocker, b"")?;
+
+        let result = SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(\d+)".to_string()),
+            history_file: Some(blocker.join("history.jsonl")),
+            ..test_args(temp_dir.path())
+        });
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_show_key_regex_prevents_cross_show_matches() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        for name in [
+            "Breaking Bad - E01.srt",
+            "The Wire - E01.srt",
+            "Breaking.Bad.E01.720p.mkv",
+            "Better.Call.Saul.E01.720p.mkv",
+        ] {
+            fs::write(temp_dir.path().join(name), b"")?;
+        }
+
+        let renamer = SubtitleRenamer::new(Args {
+            srt_regex: Some(r"E(\d{2})".to_string()),
+            show_key_regex: Some(r"^(.+?)[ .-]+E\d{2}".to_string()),
+            quiet: true,
+            ..test_args(temp_dir.path())
+        })?;
+        let (subtitles, videos) = renamer.categorize_files()?;
+        let plan = renamer.plan_renames(subtitles, videos);
+
+        assert_eq!(plan.operations.len(), 1);
+        assert_eq!(plan.operations[0].episode_id, "breaking bad:01");
+        assert_eq!(plan.operations[0].to, temp_dir.path().join("Breaking.Bad.E01.720p.srt"));
+        assert_eq!(plan.unmatched, vec![temp_dir.path().join("The Wire - E01.srt")]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_regex_subcommand() -> Result<()> {
+        let args = Args::parse_from([
+            "sub-renamer",
+            "--lang",
+            "en",
+            "validate-regex",
+            r"s(\d{2})e(\d{2})",
+            "Show.S01E02.mkv",
+            "Show.1x02.mkv",
+            "--ignore-case",
+            "--group-separator",
+            "x",
+        ]);
+        let Some(Command::ValidateRegex { pattern, samples }) = &args.command else {
+            panic!("se esperaba el subcomando validate-regex");
+        };
+
+        let mut out = Vec::new();
+        validate_regex(&args, pattern, samples, &mut out)?;
+        assert_eq!(
+            String::from_utf8(out)?,
+            "✅ \"Show.S01E02.mkv\" -> 01x02\n❌ \"Show.1x02.mkv\": no match\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_regex_strips_like_a_real_run() -> Result<()> {
+        let argv = ["sub-renamer", "--lang", "en", "--strip-before-match", r"\d{3,4}p", "validate-regex", r"(\d+)", "Show 1080p - 05.mkv"];
+        let args = Args::parse_from(argv);
+        let Some(Command::ValidateRegex { pattern, samples }) = &args.command else {
+            panic!("se esperaba el subcomando validate-regex");
+        };
+
+        let mut out = Vec::new();
+        validate_regex(&args, pattern, samples, &mut out)?;
+        assert_eq!(String::from_utf8(out)?, "✅ \"Show 1080p - 05.mkv\" -> 05\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_capture_group_selects_episode_id() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let renamer = SubtitleRenamer::new(Args {
+            srt_regex: Some(r"S(\d{2})E(\d{2})".to_string()),
+            capture_group: 2,
+            ..test_args(temp_dir.path())
+        })?;
+        assert_eq!(renamer.extract_episode_id(Path::new("Show.S01E07.srt"), true), Some("07".to_string()));
+
+        let out_of_range = SubtitleRenamer::new(Args {
+            srt_regex: Some(r"S(\d{2})E(\d{2})".to_string()),
+            capture_group: 3,
+            ..test_args(temp_dir.path())
+        });
+        assert!(out_of_range.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_date_format_normalize() {
+        assert_eq!(DateFormat::Ymd.normalize(["2024", "3", "15"]), Some("2024-03-15".to_string()));
+        assert_eq!(DateFormat::Dmy.normalize(["15", "03", "2024"]), Some("2024-03-15".to_string()));
+        assert_eq!(DateFormat::Mdy.normalize(["03", "15", "2024"]), Some("2024-03-15".to_string()));
+        assert_eq!(DateFormat::Mdy.normalize(["15", "03", "2024"]), None);
+    }
+
+    #[test]
+    fn test_air_date_matching_ignores_separators() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        fs::write(temp_dir.path().join("Late Show 2024-03-15.srt"), b"")?;
+        fs::write(temp_dir.path().join("Late.Show.2024.03.15.720p.mkv"), b"")?;
+        fs::write(temp_dir.path().join("Late.Show.2024.03.16.720p.mkv"), b"")?;
+
+        let renamer = SubtitleRenamer::new(Args {
+            date_format: Some(DateFormat::Ymd),
+            quiet: true,
+            ..test_args(temp_dir.path())
+        })?;
+        let (subtitles, videos) = renamer.categorize_files()?;
+        let plan = renamer.plan_renames(subtitles, videos);
+
+        assert_eq!(plan.operations.len(), 1);
+        assert_eq!(plan.operations[0].episode_id, "2024-03-15");
+        assert_eq!(plan.operations[0].to, temp_dir.path().join("Late.Show.2024.03.15.720p.srt"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_show_key_and_air_date_compose() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        for name in [
+            "The Daily Show 2024-03-15.srt",
+            "Late Night 2024-03-15.srt",
+            "The.Daily.Show.2024.03.15.720p.mkv",
+            "Late.Night.2024.03.16.720p.mkv",
+        ] {
+            fs::write(temp_dir.path().join(name), b"")?;
+        }
+
+        let renamer = SubtitleRenamer::new(Args {
+            date_format: Some(DateFormat::Ymd),
+            show_key_regex: Some("auto".to_string()),
+            quiet: true,
+            ..test_args(temp_dir.path())
+        })?;
+        let (subtitles, videos) = renamer.categorize_files()?;
+        let plan = renamer.plan_renames(subtitles, videos);
+
+        assert_eq!(plan.operations.len(), 1);
+        assert_eq!(plan.operations[0].episode_id, "the daily show:2024-03-15");
+        assert_eq!(plan.operations[0].to, temp_dir.path().join("The.Daily.Show.2024.03.15.720p.srt"));
+        assert_eq!(plan.unmatched, vec![temp_dir.path().join("Late Night 2024-03-15.srt")]);
+
+        let without_date = SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(\d{4})".to_string()),
+            show_key_regex: Some("auto".to_string()),
+            lang: Some(Lang::En),
+            ..test_args(temp_dir.path())
+        });
+        assert!(without_date.err().unwrap().to_string().contains("requires --date-format"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_fail_fast_stops_at_first_error() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let existing = temp_dir.path().join("b.srt");
+        fs::write(&existing, b"")?;
+        let operations = || {
+            vec![
+                RenameOperation {
+                    from: temp_dir.path().join("missing.srt"),
+                    to: temp_dir.path().join("A.srt"),
+                    episode_id: "01".to_string(),
+                    archive_entry: None,
+                },
+                RenameOperation {
+                    from: existing.clone(),
+                    to: temp_dir.path().join("c.srt"),
+                    episode_id: "02".to_string(),
+                    archive_entry: None,
+                },
+            ]
+        };
+        let renamer = |fail_fast| {
+            SubtitleRenamer::new(Args {
+                srt_regex: Some(r"(\d+)".to_string()),
+                quiet: true,
+                fail_fast,
+                ..test_args(temp_dir.path())
+            })
+        };
+
+        let outcomes = renamer(true)?.execute_renames(operations());
+        assert_eq!(outcomes.len(), 1);
+        assert_eq!(outcomes[0].status, OperationStatus::Failed);
+        assert!(existing.exists());
+
+        let outcomes = renamer(false)?.execute_renames(operations());
+        assert_eq!(outcomes.len(), 2);
+        assert_eq!(outcomes[1].status, OperationStatus::Renamed);
+        Ok(())
+    }
+
+    #[test]
+    fn test_sanitize_name() {
+        assert_eq!(sanitize_name("Show. ", '_'), "Show");
+        assert_eq!(sanitize_name("AC/DC Live", '-'), "AC-DC Live");
+        if cfg!(target_os = "windows") {
+            assert_eq!(sanitize_name("Show: Part 1?", '_'), "Show_ Part 1_");
+        } else {
+            assert_eq!(sanitize_name("Show: Part 1?", '_'), "Show: Part 1?");
+        }
+    }
+
+    #[test]
+    fn test_multiple_subtitle_extensions_for_one_video() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        for name in ["Show.S01E05.srt", "Show.S01E05.ass", "Show.S01E05.vtt", "Show.S01E05.1080p.mkv"] {
+            fs::write(temp_dir.path().join(name), b"")?;
+        }
+
+        SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+            srt_ext: "srt,ass,vtt".to_string(),
+            quiet: true,
+            ..test_args(temp_dir.path())
+        })?
+        .run()?;
+
+        for ext in ["srt", "ass", "vtt"] {
+            assert!(temp_dir.path().join(format!("Show.S01E05.1080p.{}", ext)).exists());
+            assert!(!temp_dir.path().join(format!("Show.S01E05.{}", ext)).exists());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_edit_operations_accepts_skips_and_renames_targets() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let renamer = SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(\d+)".to_string()),
+            dry_run_interactive: true,
+            lang: Some(Lang::Es),
+            ..test_args(temp_dir.path())
+        })?;
+        let operations = (1..=4)
+            .map(|n| RenameOperation {
+                from: PathBuf::from(format!("subs/{}.srt", n)),
+                to: PathBuf::from(format!("subs/video{}.srt", n)),
+                episode_id: n.to_string(),
+                archive_entry: None,
+            })
+            .collect::<Vec<_>>();
+
+        // 1 aceptada, 2 omitida, 3 editada (tras un nombre inválido y una `y`,
+        // que en español no es una respuesta), 4 descartada al terminar
+        let input = "s\nn\ne\notro/x.srt\ne\nFinal.srt\n?\ny\ns\nq\n";
+        let approved = renamer.edit_operations(operations, input.as_bytes(), io::sink())?;
+
+        let targets: Vec<&Path> = approved.iter().map(|op| op.to.as_path()).collect();
+        assert_eq!(targets, [Path::new("subs/video1.srt"), Path::new("subs/Final.srt")]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_review_applies_only_after_confirmation() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let from = temp_dir.path().join("Show.S01E01.srt");
+        let to = temp_dir.path().join("Show.S01E01.1080p.srt");
+        fs::write(&from, b"")?;
+        let operations = vec![RenameOperation {
+            from: from.clone(),
+            to: to.clone(),
+            episode_id: "S01E01".to_string(),
+            archive_entry: None,
+        }];
+        let renamer = SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+            quiet: true,
+            review: true,
+            lang: Some(Lang::Es),
+            ..test_args(temp_dir.path())
+        })?;
+
+        let outcomes = renamer.review_then_execute(operations.clone(), "n\n".as_bytes(), io::sink())?;
+        assert_eq!(outcomes[0].status, OperationStatus::DryRun);
+        assert!(from.exists());
+
+        let outcomes = renamer.review_then_execute(operations, "s\n".as_bytes(), io::sink())?;
+        assert_eq!(outcomes[0].status, OperationStatus::Renamed);
+        assert!(to.exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_meta_json_id_for_cryptic_videos() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        fs::write(temp_dir.path().join("Show.S01E05.srt"), b"")?;
+        fs::write(temp_dir.path().join("Show.S01E06.srt"), b"")?;
+        fs::write(temp_dir.path().join("a1b2c3.mkv"), b"")?;
+        fs::write(temp_dir.path().join("a1b2c3.json"), br#"{"title": "Show", "season": 1, "episode": "5"}"#)?;
+        fs::write(temp_dir.path().join("Show.S01E06.720p.mkv"), b"")?;
+
+        let renamer = SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+            meta_json: true,
+            quiet: true,
+            ..test_args(temp_dir.path())
+        })?;
+        let (subtitles, videos) = renamer.categorize_files()?;
+        let mut targets: Vec<_> = renamer.plan_renames(subtitles, videos)
+            .operations
+            .into_iter()
+            .map(|op| op.to)
+            .collect();
+        targets.sort();
+
+        // Sin JSON se vuelve al regex
         assert_eq!(
-            SubtitleRenamer::parse_extensions("mkv, mp4 , avi"),
-            vec!["mkv", "mp4", "avi"]
+            targets,
+            vec![temp_dir.path().join("Show.S01E06.720p.srt"), temp_dir.path().join("a1b2c3.srt")]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_in_place_only_rejects_moves_to_other_directories() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let renamer = SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(\d+)".to_string()),
+            in_place_only: true,
+            ..test_args(temp_dir.path())
+        })?;
+        let op = |to: &str| RenameOperation {
+            from: temp_dir.path().join("sub.srt"),
+            to: temp_dir.path().join(to),
+            episode_id: "1".to_string(),
+            archive_entry: None,
+        };
+
+        assert!(renamer.check_in_place(&[op("video.srt")]).is_ok());
+        assert!(renamer.check_in_place(&[op("video.srt"), op("other/video.srt")]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_summary_counts_renamed_directories_apart() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let to = temp_dir.path().join("Show.S01E01.1080p");
+        fs::create_dir(&to)?;
+        let outcomes = [OperationOutcome {
+            op: RenameOperation {
+                from: temp_dir.path().join("Ep.01"),
+                to,
+                episode_id: "S01E01".to_string(),
+                archive_entry: None,
+            },
+            status: OperationStatus::Renamed,
+            error: None,
+        }];
+        let summary = RunSummary::from_outcomes(&outcomes, 0);
+
+        assert_eq!(summary.by_extension[""], StatusCounts { succeeded: 1, skipped: 0, errors: 0 });
+        Ok(())
+    }
+
+    #[test]
+    fn test_summary_counts_by_extension_table() {
+        let outcome = |from: &str, status| OperationOutcome {
+            op: RenameOperation {
+                from: PathBuf::from(from),
+                to: PathBuf::from("x"),
+                episode_id: String::new(),
+                archive_entry: None,
+            },
+            status,
+            error: None,
+        };
+        let outcomes = [
+            outcome("a.srt", OperationStatus::Renamed),
+            outcome("b.SRT", OperationStatus::Renamed),
+            outcome("c.ass", OperationStatus::Skipped),
+            outcome("d.ass", OperationStatus::Failed),
+        ];
+        let summary = RunSummary::from_outcomes(&outcomes, 0);
+
+        assert_eq!(summary.by_extension["srt"], StatusCounts { succeeded: 2, skipped: 0, errors: 0 });
+        assert_eq!(summary.by_extension["ass"], StatusCounts { succeeded: 0, skipped: 1, errors: 1 });
+        assert_eq!(
+            summary.to_table(Lang::En),
+            concat!(
+                "  Extension  Succeeded  Skipped  Errors\n",
+                "  ─────────────────────────────────────\n",
+                "  .ass               0        1       1\n",
+                "  .srt               2        0       0\n",
+                "  Total              2        1       1\n",
+            )
+        );
+    }
+
+    #[test]
+    fn test_json_mode_stdout_is_only_the_json_document() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        fs::write(temp_dir.path().join("Show.S01E05.srt"), b"")?;
+        fs::write(temp_dir.path().join("Show.S01E05.1080p.mkv"), b"")?;
+        fs::write(temp_dir.path().join("Show.S01E06.srt"), b"")?;
+
+        let stdout = SharedBuffer::default();
+        let mut renamer = SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+            dry_run: true,
+            verbose: true,
+            format: OutputFormat::Json,
+            ..test_args(temp_dir.path())
+        })?;
+        renamer.stdout = RefCell::new(Box::new(stdout.clone()));
+        renamer.run()?;
+
+        let report = json::from_str(&stdout.text())?;
+        assert_eq!(report.get("operations").and_then(Json::as_array).map(<[Json]>::len), Some(1));
+        assert_eq!(report.get("unmatched").and_then(Json::as_array).map(<[Json]>::len), Some(1));
+        Ok(())
+    }
+
+    #[test]
+    fn test_keep_ext_case_preserves_uppercase_extension() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        fs::write(temp_dir.path().join("Show.S01E05.SRT"), b"")?;
+        fs::write(temp_dir.path().join("Show.S01E05.1080p.mkv"), b"")?;
+
+        let target = |keep_ext_case| -> Result<PathBuf> {
+            let renamer = SubtitleRenamer::new(Args {
+                srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+                keep_ext_case,
+                quiet: true,
+                ..test_args(temp_dir.path())
+            })?;
+            let (subtitles, videos) = renamer.categorize_files()?;
+            assert_eq!(subtitles[0].extension, "SRT");
+            assert_eq!(subtitles[0].normalized_extension, "srt");
+            Ok(renamer.plan_renames(subtitles, videos).operations.remove(0).to)
+        };
+
+        assert_eq!(target(false)?, temp_dir.path().join("Show.S01E05.1080p.srt"));
+        assert_eq!(target(true)?, temp_dir.path().join("Show.S01E05.1080p.SRT"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_sample_limits_dry_run_listing() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        for n in 1..=5 {
+            fs::write(temp_dir.path().join(format!("Show.E0{}.srt", n)), b"")?;
+            fs::write(temp_dir.path().join(format!("Show.E0{}.720p.mkv", n)), b"")?;
+        }
+
+        let stdout = SharedBuffer::default();
+        let mut renamer = SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(E\d{2})".to_string()),
+            dry_run: true,
+            sample: Some(2),
+            lang: Some(Lang::En),
+            ..test_args(temp_dir.path())
+        })?;
+        renamer.stdout = RefCell::new(Box::new(stdout.clone()));
+        renamer.run()?;
+
+        let output = stdout.text();
+        assert_eq!(output.matches("[DRY RUN]").count(), 2);
+        assert!(output.contains("... and 3 more"));
+        assert!(output.contains("Succeeded: 5"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_zip_subs_extracts_subtitle_with_video_name() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let zip = temp_dir.path().join("Show.S01E05.zip");
+        fs::write(&zip, archive::build_stored_zip(&[("Show.S01E05.srt", b"subtitulo"), ("info.nfo", b"x")]))?;
+        fs::write(temp_dir.path().join("Show.S01E05.1080p.mkv"), b"")?;
+
+        SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+            zip_subs: true,
+            quiet: true,
+            ..test_args(temp_dir.path())
+        })?
+        .run()?;
+
+        assert_eq!(fs::read(temp_dir.path().join("Show.S01E05.1080p.srt"))?, b"subtitulo");
+        assert!(zip.exists());
+        assert!(!temp_dir.path().join("Show.S01E05.1080p.nfo").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_zip_subs_follows_dir_config_extensions() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let show = temp_dir.path().join("Show");
+        fs::create_dir(&show)?;
+        fs::write(show.join(dirconfig::FILE_NAME), "srt_ext = ['ass']\n")?;
+        let zip = show.join("Show.S01E05.zip");
+        fs::write(&zip, archive::build_stored_zip(&[("Show.S01E05.ass", b"ass"), ("Show.S01E05.srt", b"srt")]))?;
+        fs::write(show.join("Show.S01E05.1080p.mkv"), b"")?;
+
+        SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+            zip_subs: true,
+            recursive: true,
+            quiet: true,
+            ..test_args(temp_dir.path())
+        })?
+        .run()?;
+
+        assert_eq!(fs::read(show.join("Show.S01E05.1080p.ass"))?, b"ass");
+        assert!(!show.join("Show.S01E05.1080p.srt").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_zip_subs_extracts_deflated_entry() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let content: &[u8] = b"1\n00:00:01,000 --> 00:00:02,000\nHola hola hola hola\n";
+        // `content` comprimido con zlib (DEFLATE sin envoltorio)
+        let deflated: &[u8] = &[
+            0x33, 0xe4, 0x32, 0x30, 0xb0, 0x02, 0x21, 0x43, 0x1d, 0x03, 0x03, 0x03, 0x05, 0x5d, 0x5d, 0x3b, 0x05,
+            0xa8, 0x80, 0x11, 0x48, 0x80, 0xcb, 0x23, 0x3f, 0x27, 0x51, 0x21, 0x03, 0x85, 0xe0, 0x02, 0x00,
+        ];
+        fs::write(
+            temp_dir.path().join("Show.S01E05.zip"),
+            archive::build_deflated_zip(&[("Show.S01E05.srt", deflated, content)]),
+        )?;
+        fs::write(temp_dir.path().join("Show.S01E05.1080p.mkv"), b"")?;
+
+        SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+            zip_subs: true,
+            quiet: true,
+            ..test_args(temp_dir.path())
+        })?
+        .run()?;
+
+        assert_eq!(fs::read(temp_dir.path().join("Show.S01E05.1080p.srt"))?, content);
+        Ok(())
+    }
+
+    #[test]
+    fn test_assume_targets_exist_exercises_conflict_handling() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let from = temp_dir.path().join("Show.S01E01.srt");
+        fs::write(&from, b"")?;
+        let operations = || {
+            vec![RenameOperation {
+                from: from.clone(),
+                to: temp_dir.path().join("Show.S01E01.1080p.srt"),
+                episode_id: "S01E01".to_string(),
+                archive_entry: None,
+            }]
+        };
+        let renamer = |number_conflicts| {
+            SubtitleRenamer::new(Args {
+                srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+                quiet: true,
+                dry_run: true,
+                assume_targets_exist: true,
+                number_conflicts,
+                ..test_args(temp_dir.path())
+            })
+        };
+
+        let outcomes = renamer(false)?.execute_renames(operations());
+        assert_eq!(outcomes[0].status, OperationStatus::Skipped);
+
+        let outcomes = renamer(true)?.execute_renames(operations());
+        assert_eq!(outcomes[0].status, OperationStatus::DryRun);
+        assert_eq!(outcomes[0].op.to, temp_dir.path().join("Show.S01E01.1080p.2.srt"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_numeric_base_normalizes_hex_ids() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let renamer = SubtitleRenamer::new(Args {
+            srt_regex: Some(r"\[([0-9A-Fa-fx]+)\]".to_string()),
+            numeric_base: Some(16),
+            ..test_args(temp_dir.path())
+        })?;
+
+        let id = |name: &str| renamer.extract_episode_id(Path::new(name), true);
+        assert_eq!(id("Show [0A].srt"), Some("10".to_string()));
+        assert_eq!(id("Show [0x0a].srt"), Some("10".to_string()));
+        assert_eq!(id("Show [10].srt"), Some("16".to_string()));
+        // Lo que no es un número en la base se compara tal cual
+        assert_eq!(id("Show [xx].srt"), Some("xx".to_string()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_source_subs_renames_videos() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        for name in ["Show - 01 [Fansub].srt", "Show - 01 [Fansub].ass", "Show - 01 [Ripper].srt", "Show.E01.720p.MKV"] {
+            fs::write(temp_dir.path().join(name), b"")?;
+        }
+
+        let renamer = SubtitleRenamer::new(Args {
+            srt_regex: Some(r" - (\d{2})".to_string()),
+            mkv_regex: Some(r"E(\d{2})".to_string()),
+            srt_ext: "srt,ass".to_string(),
+            source: Source::Subs,
+            quiet: true,
+            ..test_args(temp_dir.path())
+        })?;
+        let (subtitles, videos) = renamer.categorize_files()?;
+        let plan = renamer.plan_renames(subtitles, videos);
+
+        assert_eq!(plan.operations.len(), 1);
+        assert_eq!(plan.operations[0].from, temp_dir.path().join("Show.E01.720p.MKV"));
+        assert_eq!(plan.operations[0].to, temp_dir.path().join("Show - 01 [Fansub].MKV"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_after_flags_missing_targets() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let present = temp_dir.path().join("present.srt");
+        fs::write(&present, b"")?;
+        let outcome = |to: PathBuf| OperationOutcome {
+            op: RenameOperation { from: PathBuf::from("x.srt"), to, episode_id: String::new(), archive_entry: None },
+            status: OperationStatus::Renamed,
+            error: None,
+        };
+        let mut outcomes = [outcome(present), outcome(temp_dir.path().join("gone.srt"))];
+
+        let renamer = SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(\d+)".to_string()),
+            quiet: true,
+            verify_after: true,
+            ..test_args(temp_dir.path())
+        })?;
+        assert_eq!(renamer.verify_outcomes(&mut outcomes), 1);
+        assert_eq!(outcomes[0].status, OperationStatus::Renamed);
+        assert_eq!(outcomes[1].status, OperationStatus::Failed);
+        assert!(outcomes[1].error.is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn test_template_file_uses_first_applicable_template() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let rules = TempDir::new()?;
+        let template_file = rules.path().join("plantillas.txt");
+        fs::write(&template_file, "# con desfase si lo hay\n{video_stem}.sync{offset}.{ext}\n{video_stem}.{id}.{ext}\n")?;
+        fs::write(temp_dir.path().join("Show.S01E05.+2s.srt"), b"")?;
+        fs::write(temp_dir.path().join("Show.S01E06.srt"), b"")?;
+        fs::write(temp_dir.path().join("Show.S01E05.1080p.mkv"), b"")?;
+        fs::write(temp_dir.path().join("Show.S01E06.1080p.mkv"), b"")?;
+
+        let renamer = SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+            template_file: Some(template_file),
+            quiet: true,
+            ..test_args(temp_dir.path())
+        })?;
+        let (subtitles, videos) = renamer.categorize_files()?;
+        let mut targets: Vec<_> = renamer.plan_renames(subtitles, videos)
+            .operations
+            .into_iter()
+            .map(|op| op.to)
+            .collect();
+        targets.sort();
+
+        assert_eq!(
+            targets,
+            vec![
+                temp_dir.path().join("Show.S01E05.1080p.sync+2s.srt"),
+                temp_dir.path().join("Show.S01E06.1080p.S01E06.srt"),
+            ]
         );
+        Ok(())
+    }
+
+    #[test]
+    fn test_unknown_template_token_is_rejected() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let result = SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(\d+)".to_string()),
+            output_template: Some("{video_stem}.{idioma}.{ext}".to_string()),
+            lang: Some(Lang::En),
+            ..test_args(temp_dir.path())
+        });
+        let error = result.err().expect("el marcador desconocido debe fallar").to_string();
+        assert!(error.contains("{idioma}"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_prune_empty_dirs_removes_only_emptied_directories() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let nested = temp_dir.path().join("season").join("subs");
+        fs::create_dir_all(&nested)?;
+        fs::create_dir(temp_dir.path().join("already-empty"))?;
+        let from = nested.join("Show.S01E01.srt");
+        fs::write(&from, b"")?;
+        let operations = || {
+            vec![RenameOperation {
+                from: from.clone(),
+                to: temp_dir.path().join("Show.S01E01.1080p.srt"),
+                episode_id: "S01E01".to_string(),
+                archive_entry: None,
+            }]
+        };
+        let renamer = |dry_run| {
+            SubtitleRenamer::new(Args {
+                srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+                quiet: true,
+                dry_run,
+                prune_empty_dirs: true,
+                ..test_args(temp_dir.path())
+            })
+        };
+
+        let dry = renamer(true)?;
+        dry.prune_emptied_dirs(&dry.execute_renames(operations()));
+        assert!(nested.exists());
+
+        let real = renamer(false)?;
+        real.prune_emptied_dirs(&real.execute_renames(operations()));
+        assert!(!temp_dir.path().join("season").exists());
+        assert!(temp_dir.path().join("already-empty").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_uppercase_user_extensions_match_any_case() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        for name in ["Show.S01E01.srt", "Show.S01E02.ASS", "Show.S01E01.1080p.mkv", "Show.S01E02.1080p.MKV"] {
+            fs::write(temp_dir.path().join(name), b"")?;
+        }
+
+        SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+            srt_ext: "SRT,ASS".to_string(),
+            video_ext: "MKV".to_string(),
+            quiet: true,
+            ..test_args(temp_dir.path())
+        })?
+        .run()?;
+
+        assert!(temp_dir.path().join("Show.S01E01.1080p.srt").exists());
+        assert!(temp_dir.path().join("Show.S01E02.1080p.ass").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolution_rank() {
+        assert_eq!(resolution_rank(Path::new("Show.S01E01.720p.WEB.mkv")), 720);
+        assert_eq!(resolution_rank(Path::new("Show.S01E01.2160p.mkv")), 2160);
+        assert_eq!(resolution_rank(Path::new("Show S01E01 [4K].mkv")), 2160);
+        assert_eq!(resolution_rank(Path::new("Show.S01E01.x264.mkv")), 0);
+    }
+
+    #[test]
+    fn test_prefer_resolution_picks_highest_quality_video() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        for name in ["Show.S01E01.srt", "Show.S01E01.720p.mkv", "Show.S01E01.2160p.mkv", "Show.S01E01.1080p.mkv"] {
+            fs::write(temp_dir.path().join(name), b"")?;
+        }
+
+        SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+            prefer_resolution: true,
+            quiet: true,
+            ..test_args(temp_dir.path())
+        })?
+        .run()?;
+
+        assert!(temp_dir.path().join("Show.S01E01.2160p.srt").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_run_reports_nothing_to_do() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        fs::write(temp_dir.path().join("Show.S01E01.srt"), b"")?;
+        fs::write(temp_dir.path().join("Show.S01E01.1080p.mkv"), b"")?;
+        let renamer = || {
+            SubtitleRenamer::new(Args {
+                srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+                quiet: true,
+                ..test_args(temp_dir.path())
+            })
+        };
+
+        assert_eq!(renamer()?.run()?, RunStatus::Done);
+        assert_eq!(renamer()?.run()?, RunStatus::NothingToDo);
+        Ok(())
+    }
+
+    #[test]
+    fn test_match_by_mtime_pairs_files_by_age() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let base = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        for (i, name) in ["zz.srt", "aa.srt", "video-b.mkv", "video-a.mkv"].iter().enumerate() {
+            let file = fs::File::create(temp_dir.path().join(name))?;
+            // Subtítulos y videos en el mismo orden de antigüedad: zz y video-b primero
+            file.set_modified(base + Duration::from_secs((i % 2) as u64 * 60))?;
+        }
+        let renamer = SubtitleRenamer::new(Args {
+            match_by_mtime: true,
+            quiet: true,
+            ..test_args(temp_dir.path())
+        })?;
+
+        let (subtitles, videos) = renamer.categorize_files()?;
+        let plan = renamer.plan_renames(subtitles, videos);
+        let mut pairs: Vec<(&OsStr, &OsStr)> = plan.operations.iter().map(|op| (file_name(&op.from), file_name(&op.to))).collect();
+        pairs.sort();
+        assert_eq!(pairs, [(OsStr::new("aa.srt"), OsStr::new("video-a.srt")), (OsStr::new("zz.srt"), OsStr::new("video-b.srt"))]);
+
+        fs::write(temp_dir.path().join("extra.srt"), b"")?;
+        assert!(renamer.categorize_files().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_compound_extension_is_kept_as_a_unit() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        for name in ["Show.S01E01.EN.srt", "Show.S01E01.es.sub", "Show.S01E01.1080p.mkv"] {
+            fs::write(temp_dir.path().join(name), b"")?;
+        }
+
+        SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+            compound_ext: Some("en.srt,es.sub".to_string()),
+            quiet: true,
+            ..test_args(temp_dir.path())
+        })?
+        .run()?;
+
+        assert!(temp_dir.path().join("Show.S01E01.1080p.en.srt").exists());
+        assert!(temp_dir.path().join("Show.S01E01.1080p.es.sub").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_explain_traces_one_file_without_renaming() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        for name in ["Show.S01E01.srt", "Show.S01E02.srt", "Show.S01E01.1080p.mkv"] {
+            fs::write(temp_dir.path().join(name), b"")?;
+        }
+        let explain = |name: &str| -> Result<String> {
+            let stdout = SharedBuffer::default();
+            let mut renamer = SubtitleRenamer::new(Args {
+                srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+                explain: Some(name.to_string()),
+                lang: Some(Lang::En),
+                quiet: true,
+                ..test_args(temp_dir.path())
+            })?;
+            renamer.stdout = RefCell::new(Box::new(stdout.clone()));
+            renamer.run()?;
+            Ok(stdout.text())
+        };
+
+        let trace = explain("Show.S01E01.srt")?;
+        assert!(trace.contains("matches --srt-ext"), "{}", trace);
+        assert!(trace.contains("captured key \"S01E01\""), "{}", trace);
+        assert!(trace.contains("rename to"), "{}", trace);
+        assert!(temp_dir.path().join("Show.S01E01.srt").exists());
+
+        assert!(explain("Show.S01E02.srt")?.contains("nothing to pair it with"));
+        assert!(explain("missing.srt").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_strip_before_match_removes_noise_first() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let renamer = |strip: Option<&str>| {
+            SubtitleRenamer::new(Args {
+                srt_regex: Some(r"(\d{4})".to_string()),
+                strip_before_match: strip.map(str::to_string),
+                quiet: true,
+                ..test_args(temp_dir.path())
+            })
+        };
+        let path = Path::new("Show.1080p.2023.mkv");
+
+        assert_eq!(renamer(None)?.extract_episode_id(path, false).as_deref(), Some("1080"));
+        assert_eq!(renamer(Some(r"\d{3,4}p"))?.extract_episode_id(path, false).as_deref(), Some("2023"));
+        assert!(renamer(Some("(")).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_dump_config_lists_effective_values() -> Result<()> {
+        let argv = ["sub-renamer", "--srt-regex", "E(\\d+)", "--recursive", "--scan-archive", "lib.tar", "--lang", "en"];
+        let matches = Args::command().try_get_matches_from(argv)?;
+        let mut out = Vec::new();
+        dump_config(&matches, Args::from_arg_matches(&matches)?, &mut out)?;
+        let out = String::from_utf8(out)?;
+
+        assert!(out.lines().any(|line| line == "srt-regex: E(\\d+)"), "{}", out);
+        assert!(out.lines().any(|line| line == "recursive: true"), "{}", out);
+        assert!(out.lines().any(|line| line == "mkv-regex: E(\\d+)  # derived"), "{}", out);
+        assert!(out.lines().any(|line| line == "dry-run: true  # derived"), "{}", out);
+        assert!(out.lines().any(|line| line == "subs-dir: -"), "{}", out);
+        Ok(())
+    }
+
+    #[test]
+    fn test_long_help_lists_exit_codes_in_the_requested_language() {
+        let argv: Vec<OsString> = ["sub-renamer", "--lang", "en", "--help"].into_iter().map(OsString::from).collect();
+        assert_eq!(help_lang(&argv), Lang::En);
+        let help = cli_command(Lang::En).render_long_help().to_string();
+        assert!(help.contains("Exit codes:\n  0  successful run"), "{}", help);
+        assert!(cli_command(Lang::Es).render_long_help().to_string().contains("Códigos de salida:"));
+    }
+
+    #[test]
+    fn test_dump_config_localizes_annotations() -> Result<()> {
+        let matches = Args::command().try_get_matches_from(["sub-renamer", "--lang", "es"])?;
+        let mut out = Vec::new();
+        dump_config(&matches, Args::from_arg_matches(&matches)?, &mut out)?;
+        let out = String::from_utf8(out)?;
+
+        assert!(out.lines().any(|line| line == "lang: es"), "{}", out);
+        assert!(out.lines().any(|line| line == "srt-ext: srt  # predeterminado"), "{}", out);
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_dirs_move_subtitles_next_to_videos() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let (videos, subs) = (temp_dir.path().join("Video"), temp_dir.path().join("Subs"));
+        fs::create_dir(&videos)?;
+        fs::create_dir(&subs)?;
+        fs::write(videos.join("Show.S01E05.1080p.mkv"), b"")?;
+        fs::write(subs.join("S01E05.srt"), b"")?;
+        // Un video junto a los subtítulos no cuenta: los videos se buscan en --videos-dir
+        fs::write(subs.join("S01E05.mkv"), b"")?;
+
+        SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+            subs_dir: Some(subs.clone()),
+            videos_dir: Some(videos.clone()),
+            quiet: true,
+            ..test_args(temp_dir.path())
+        })?
+        .run()?;
+
+        assert!(videos.join("Show.S01E05.1080p.srt").exists());
+        assert!(!subs.join("S01E05.srt").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_dirs_recursive_cross_tree() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let (videos, subs) = (temp_dir.path().join("Video"), temp_dir.path().join("Subs"));
+        fs::create_dir_all(videos.join("Show/Season 1"))?;
+        fs::create_dir_all(subs.join("descargas/show"))?;
+        fs::write(videos.join("Show/Season 1/Show.S01E05.mkv"), b"")?;
+        fs::write(videos.join("Show/Season 1/Show.S01E06.mkv"), b"")?;
+        fs::write(subs.join("descargas/show/S01E05.srt"), b"")?;
+        fs::write(subs.join("S01E06.srt"), b"")?;
+
+        let base = || Args {
+            srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+            subs_dir: Some(subs.clone()),
+            videos_dir: Some(videos.clone()),
+            recursive: true,
+            quiet: true,
+            ..test_args(temp_dir.path())
+        };
+
+        // Por defecto cada subtítulo termina junto a su video, a cualquier profundidad
+        SubtitleRenamer::new(Args { mirror_tree: false, ..base() })?.run()?;
+        assert!(videos.join("Show/Season 1/Show.S01E05.srt").exists());
+        assert!(videos.join("Show/Season 1/Show.S01E06.srt").exists());
+
+        // Con --mirror-tree quedan bajo --subs-dir, en la ruta relativa del video
+        fs::rename(videos.join("Show/Season 1/Show.S01E05.srt"), subs.join("S01E05.srt"))?;
+        SubtitleRenamer::new(Args { mirror_tree: true, ..base() })?.run()?;
+        assert!(subs.join("Show/Season 1/Show.S01E05.srt").exists());
+        assert!(videos.join("Show/Season 1/Show.S01E06.srt").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_anchored() {
+        assert!(is_anchored(r"^S(\d{2})E(\d{2})$"));
+        assert!(is_anchored(r"(?i)^s(\d{2})"));
+        assert!(is_anchored(r"E(\d+)$"));
+        assert!(!is_anchored(r"S(\d{2})E(\d{2})"));
+        assert!(!is_anchored(r"costs(\d+)\$"));
+    }
+
+    #[test]
+    fn test_events_file_streams_run_events() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let media = temp_dir.path().join("media");
+        fs::create_dir(&media)?;
+        fs::write(media.join("Show.S01E01.srt"), b"")?;
+        fs::write(media.join("Show.S01E01.1080p.mkv"), b"")?;
+        let events_path = temp_dir.path().join("events.jsonl");
+
+        SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+            events_file: Some(events_path.clone()),
+            quiet: true,
+            ..test_args(&media)
+        })?
+        .run()?;
+
+        let events: Vec<String> = fs::read_to_string(&events_path)?
+            .lines()
+            .map(|line| json::from_str(line).unwrap().get("event").and_then(Json::as_str).unwrap().to_string())
+            .collect();
+        assert_eq!(events, ["scan-start", "file-matched", "file-matched", "op-planned", "op-done", "summary"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_ignore_already_subtitled_marks_redundant_operations() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        for name in ["Show.S01E01.srt", "Show.S01E01.1080p.srt", "Show.S01E01.1080p.mkv"] {
+            fs::write(temp_dir.path().join(name), b"")?;
+        }
+        let operations = vec![RenameOperation {
+            from: temp_dir.path().join("Show.S01E01.srt"),
+            to: temp_dir.path().join("Show.S01E01.1080p.srt"),
+            episode_id: "S01E01".to_string(),
+            archive_entry: None,
+        }];
+        let renamer = |ignore_already_subtitled| {
+            SubtitleRenamer::new(Args {
+                srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+                ignore_already_subtitled,
+                quiet: true,
+                ..test_args(temp_dir.path())
+            })
+        };
+
+        assert_eq!(renamer(false)?.execute_renames(operations.clone())[0].status, OperationStatus::Skipped);
+        assert_eq!(renamer(true)?.execute_renames(operations)[0].status, OperationStatus::AlreadyDone);
+        assert!(temp_dir.path().join("Show.S01E01.srt").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_writable_flags_unwritable_destinations() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let from = temp_dir.path().join("Show.S01E01.srt");
+        fs::write(&from, b"")?;
+        let op = |to: PathBuf| RenameOperation {
+            from: from.clone(),
+            to,
+            episode_id: "S01E01".to_string(),
+            archive_entry: None,
+        };
+        let renamer = SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+            dry_run: true,
+            check_writable: true,
+            quiet: true,
+            ..test_args(temp_dir.path())
+        })?;
+
+        let outcomes = renamer.execute_renames(vec![
+            op(temp_dir.path().join("Show.S01E01.1080p.srt")),
+            op(temp_dir.path().join("missing").join("Show.S01E01.1080p.srt")),
+        ]);
+        assert_eq!(outcomes[0].status, OperationStatus::DryRun);
+        assert_eq!(outcomes[1].status, OperationStatus::Failed);
+        assert_eq!(fs::read_dir(temp_dir.path())?.count(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_normalize_groups_only_affects_named_groups() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let renamer = |groups: &[&str]| {
+            SubtitleRenamer::new(Args {
+                srt_regex: Some(r"S(?<season>\d+)E(?<ep>\d+)".to_string()),
+                group_separator: Some("x".to_string()),
+                normalize_groups: groups.iter().map(|g| g.to_string()).collect(),
+                quiet: true,
+                ..test_args(temp_dir.path())
+            })
+        };
+        let path = Path::new("Show.S01E005.mkv");
+
+        assert_eq!(renamer(&[])?.extract_episode_id(path, false).as_deref(), Some("01x005"));
+        assert_eq!(renamer(&["ep"])?.extract_episode_id(path, false).as_deref(), Some("01x5"));
+        assert!(renamer(&["episode"]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_use_full_match_without_capture_groups() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let renamer = |pattern: &str, use_full_match| {
+            SubtitleRenamer::new(Args {
+                srt_regex: Some(pattern.to_string()),
+                use_full_match,
+                quiet: true,
+                ..test_args(temp_dir.path())
+            })
+        };
+        let path = Path::new("Show.S01E05.mkv");
+
+        assert!(renamer(r"S\d{2}E\d{2}", false).is_err());
+        assert_eq!(renamer(r"S\d{2}E\d{2}", true)?.extract_episode_id(path, false).as_deref(), Some("S01E05"));
+        // Con el grupo presente, manda --capture-group
+        assert_eq!(renamer(r"S\d{2}E(\d{2})", true)?.extract_episode_id(path, false).as_deref(), Some("05"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parallel_execute_only_when_plan_is_conflict_free() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let path = |name: &str| temp_dir.path().join(name);
+        let op = |from: &str, to: &str| RenameOperation {
+            from: path(from),
+            to: path(to),
+            episode_id: from.to_string(),
+            archive_entry: None,
+        };
+        for i in 1..=8 {
+            fs::write(path(&format!("{}.srt", i)), b"")?;
+        }
+        let renamer = SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(\d+)".to_string()),
+            parallel_execute: true,
+            quiet: true,
+            ..test_args(temp_dir.path())
+        })?;
+
+        assert!(!renamer.is_parallel_safe(&[op("1.srt", "a.srt"), op("2.srt", "1.srt")]));
+        assert!(!renamer.is_parallel_safe(&[op("1.srt", "a.srt"), op("2.srt", "a.srt")]));
+        assert!(!renamer.is_parallel_safe(&[op("1.srt", "2.srt")]));
+
+        let operations: Vec<_> = (1..=8).map(|i| op(&format!("{}.srt", i), &format!("ep{}.srt", i))).collect();
+        assert!(renamer.is_parallel_safe(&operations));
+        let outcomes = renamer.execute_renames(operations);
+        assert!(outcomes.iter().all(|o| o.status == OperationStatus::Renamed));
+        assert_eq!(outcomes[7].op.to, path("ep8.srt"));
+        assert!((1..=8).all(|i| path(&format!("ep{}.srt", i)).exists()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_bare_episode_number_with_assume_season() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        for name in ["05.srt", "12.srt", "Show.S01E05.mkv", "Show.S02E05.mkv", "Show.S01E12.mkv"] {
+            fs::write(temp_dir.path().join(name), b"")?;
+        }
+
+        SubtitleRenamer::new(Args {
+            srt_regex: Some(r"^(\d+)\.".to_string()),
+            mkv_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+            assume_season: Some(1),
+            quiet: true,
+            ..test_args(temp_dir.path())
+        })?
+        .run()?;
+
+        assert!(temp_dir.path().join("Show.S01E05.srt").exists());
+        assert!(temp_dir.path().join("Show.S01E12.srt").exists());
+        assert!(!temp_dir.path().join("Show.S02E05.srt").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_assume_season_follows_the_video_group_separator() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        for name in ["5.srt", "Show.1x05.mkv", "Show.2x05.mkv"] {
+            fs::write(temp_dir.path().join(name), b"")?;
+        }
+
+        SubtitleRenamer::new(Args {
+            srt_regex: Some(r"^(\d+)\.".to_string()),
+            mkv_regex: Some(r"(\d+)x(\d{2})".to_string()),
+            group_separator: Some("x".to_string()),
+            normalize_numbers: true,
+            assume_season: Some(1),
+            quiet: true,
+            ..test_args(temp_dir.path())
+        })?
+        .run()?;
+
+        assert!(temp_dir.path().join("Show.1x05.srt").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_alt_regex_matches_on_any_shared_id() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        for name in ["Show - 13.srt", "Show.S01E02.srt", "Show.S02E01 (13).mkv", "Show.S01E02 (02).mkv"] {
+            fs::write(temp_dir.path().join(name), b"")?;
+        }
+
+        SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+            alt_regex: vec![r"[-(] ?(\d{2,3})\b".to_string()],
+            quiet: true,
+            ..test_args(temp_dir.path())
+        })?
+        .run()?;
+
+        assert!(temp_dir.path().join("Show.S02E01 (13).srt").exists());
+        assert!(temp_dir.path().join("Show.S01E02 (02).srt").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_emit_format_rename_prints_quoted_commands() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let media = temp_dir.path().join("media");
+        fs::create_dir(&media)?;
+        fs::write(media.join("Show's.S01E01.srt"), b"")?;
+        fs::write(media.join("Show's.S01E01.1080p.mkv"), b"")?;
+        let stdout = SharedBuffer::default();
+        let mut renamer = SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+            emit_format: Some(EmitFormat::Rename),
+            quiet: true,
+            ..test_args(&media)
+        })?;
+        renamer.stdout = RefCell::new(Box::new(stdout.clone()));
+        renamer.run()?;
+
+        let dir = media.display();
+        let script = stdout.text();
+        let mut lines = script.lines();
+        assert_eq!(lines.next(), Some("#!/bin/sh"));
+        assert!(lines.next().is_some_and(|guard| guard.starts_with("rename --version 2>/dev/null | grep -q util-linux")));
         assert_eq!(
-            SubtitleRenamer::parse_extensions(""),
-            Vec::<String>::new()
+            lines.next(),
+            Some(format!(
+                "rename --no-overwrite -- '{dir}/Show'\\''s.S01E01.srt' '{dir}/Show'\\''s.S01E01.1080p.srt' '{dir}/Show'\\''s.S01E01.srt'"
+            ).as_str())
         );
+        assert_eq!(lines.next(), None);
+        assert!(media.join("Show's.S01E01.srt").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_emit_format_leaves_out_existing_and_repeated_targets() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let op = |from: &str, to: &str| RenameOperation {
+            from: temp_dir.path().join(from),
+            to: temp_dir.path().join(to),
+            episode_id: String::new(),
+            archive_entry: None,
+        };
+        for name in ["a.srt", "b.srt", "c.srt", "taken.srt"] {
+            fs::write(temp_dir.path().join(name), b"")?;
+        }
+        let stdout = SharedBuffer::default();
+        let mut renamer = SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(\d+)".to_string()),
+            quiet: true,
+            ..test_args(temp_dir.path())
+        })?;
+        renamer.stdout = RefCell::new(Box::new(stdout.clone()));
+        renamer.emit_plan(EmitFormat::Rename, &[op("a.srt", "taken.srt"), op("b.srt", "new.srt"), op("c.srt", "new.srt")])?;
+
+        let script = stdout.text();
+        let commands: Vec<&str> = script.lines().filter(|line| line.starts_with("rename --no-overwrite")).collect();
+        assert_eq!(commands.len(), 1, "{}", script);
+        assert!(commands[0].contains("b.srt"), "{}", script);
+
+        // Un nombre que no es UTF-8 no se escribe con caracteres de reemplazo
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStrExt;
+            let stdout = SharedBuffer::default();
+            renamer.stdout = RefCell::new(Box::new(stdout.clone()));
+            let mut invalid = op("a.srt", "x.srt");
+            invalid.from = temp_dir.path().join(OsStr::from_bytes(b"caf\xe9.srt"));
+            renamer.emit_plan(EmitFormat::Rename, &[invalid])?;
+            assert!(!stdout.text().contains("rename --no-overwrite"), "{}", stdout.text());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_nearest_dir_config_wins() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let show = temp_dir.path().join("Show A");
+        let season = show.join("Season 2");
+        fs::create_dir_all(&season)?;
+        fs::write(show.join(dirconfig::FILE_NAME), "srt_regex = 'Ep(\\d+)'\n")?;
+        fs::write(season.join(dirconfig::FILE_NAME), "srt_regex = 'Cap(\\d+)'\nsrt_ext = ['ass']\n")?;
+        let files = [
+            temp_dir.path().join("Show.S01E01.srt"),
+            temp_dir.path().join("Show.S01E01.1080p.mkv"),
+            show.join("Ep01.srt"),
+            show.join("Show A Ep01 720p.mkv"),
+            season.join("Cap03.ass"),
+            season.join("Show A Cap03.mkv"),
+            // srt no está en las extensiones de Season 2
+            season.join("Cap04.srt"),
+            season.join("Show A Cap04.mkv"),
+        ];
+        for file in &files {
+            fs::write(file, b"")?;
+        }
+
+        SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+            recursive: true,
+            colocated_only: true,
+            quiet: true,
+            ..test_args(temp_dir.path())
+        })?
+        .run()?;
+
+        assert!(temp_dir.path().join("Show.S01E01.1080p.srt").exists());
+        assert!(show.join("Show A Ep01 720p.srt").exists());
+        assert!(season.join("Show A Cap03.ass").exists());
+        assert!(season.join("Cap04.srt").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_strip_accents_matches_accented_show_keys() -> Result<()> {
+        assert_eq!(strip_accents("Pokémon Señor Ça"), "Pokemon Senor Ca");
+        assert_eq!(strip_accents("Poke\u{301}mon"), "Pokemon");
+
+        let temp_dir = TempDir::new()?;
+        for name in ["Pokémon.S01E01.srt", "Pokemon.S01E01.1080p.mkv"] {
+            fs::write(temp_dir.path().join(name), b"")?;
+        }
+        let run = |strip_accents| {
+            SubtitleRenamer::new(Args {
+                srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+                show_key_regex: Some(r"^(.+?)\.S\d".to_string()),
+                strip_accents,
+                quiet: true,
+                ..test_args(temp_dir.path())
+            })?
+            .run()
+        };
+
+        run(false)?;
+        assert!(!temp_dir.path().join("Pokemon.S01E01.1080p.srt").exists());
+        run(true)?;
+        assert!(temp_dir.path().join("Pokemon.S01E01.1080p.srt").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_max_ops_requires_force_above_the_limit() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        for i in 1..=3 {
+            fs::write(temp_dir.path().join(format!("Show.S01E0{}.srt", i)), b"")?;
+            fs::write(temp_dir.path().join(format!("Show.S01E0{}.1080p.mkv", i)), b"")?;
+        }
+        let run = |dry_run, force| {
+            SubtitleRenamer::new(Args {
+                srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+                max_ops: Some(2),
+                dry_run,
+                force,
+                quiet: true,
+                ..test_args(temp_dir.path())
+            })?
+            .run()
+        };
+
+        assert!(run(false, false).is_err());
+        assert!(temp_dir.path().join("Show.S01E01.srt").exists());
+        run(true, false)?;
+        run(false, true)?;
+        assert!(temp_dir.path().join("Show.S01E03.1080p.srt").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_language_tag() {
+        assert_eq!(split_language_tag("Show S01E05.es"), Some(("Show S01E05", "es")));
+        assert_eq!(split_language_tag("Show.S01E05.pt-BR"), Some(("Show.S01E05", "pt-BR")));
+        assert_eq!(split_language_tag("Show.S01E05.WEB"), None);
+        assert_eq!(split_language_tag("Show.S01E05"), None);
+    }
+
+    #[test]
+    fn test_sidecar_stem_reuses_existing_subtitle_name() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        for name in ["Show.S01E05.720p-GRP.mkv", "Show S01E05.es.srt", "random.S01E05.en.srt"] {
+            fs::write(temp_dir.path().join(name), b"")?;
+        }
+
+        SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+            sidecar_stem: true,
+            quiet: true,
+            ..test_args(temp_dir.path())
+        })?
+        .run()?;
+
+        assert!(temp_dir.path().join("Show S01E05.es.srt").exists());
+        assert!(temp_dir.path().join("Show S01E05.en.srt").exists());
+        assert!(!temp_dir.path().join("random.S01E05.en.srt").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_sidecar_stem_ignores_newcomer_sorting_first() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        for name in ["Show.S01E05.720p-GRP.mkv", "Show S01E05.es.srt", "Addic7ed.S01E05.en.srt"] {
+            fs::write(temp_dir.path().join(name), b"")?;
+        }
+
+        let renamer = SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+            sidecar_stem: true,
+            quiet: true,
+            ..test_args(temp_dir.path())
+        })?;
+        let (subtitles, videos) = renamer.categorize_files()?;
+        let plan = renamer.plan_renames(subtitles, videos);
+
+        assert_eq!(plan.operations.len(), 1);
+        assert_eq!(plan.operations[0].from, temp_dir.path().join("Addic7ed.S01E05.en.srt"));
+        assert_eq!(plan.operations[0].to, temp_dir.path().join("Show S01E05.en.srt"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_report_duplicates_lists_groups_without_renaming() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        for name in ["Show.S01E01.srt", "Show.S01E01.720p.mkv", "Show.S01E01.1080p.mkv", "Show.S01E02.srt", "Show.S01E02.mkv"] {
+            fs::write(temp_dir.path().join(name), b"")?;
+        }
+        let stdout = SharedBuffer::default();
+        let mut renamer = SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+            report_duplicates: true,
+            lang: Some(Lang::En),
+            quiet: true,
+            ..test_args(temp_dir.path())
+        })?;
+        renamer.stdout = RefCell::new(Box::new(stdout.clone()));
+        renamer.run()?;
+
+        assert_eq!(stdout.text(), "🎬 S01E01: 2 videos\n   Show.S01E01.1080p.mkv\n   Show.S01E01.720p.mkv\n");
+        assert!(temp_dir.path().join("Show.S01E02.srt").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_match_last_uses_the_final_occurrence() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let renamer = |match_last| {
+            SubtitleRenamer::new(Args {
+                srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+                match_last,
+                quiet: true,
+                ..test_args(temp_dir.path())
+            })
+        };
+        let path = Path::new("Collection.S01E01-E10.Show.S01E05.mkv");
+
+        assert_eq!(renamer(false)?.extract_episode_id(path, false).as_deref(), Some("S01E01"));
+        assert_eq!(renamer(true)?.extract_episode_id(path, false).as_deref(), Some("S01E05"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_dry_run_marks_risky_operations() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let sub_dir = temp_dir.path().join("subs");
+        fs::create_dir(&sub_dir)?;
+        for path in [sub_dir.join("Show.S01E01.srt"), temp_dir.path().join("Show.S01E02.srt")] {
+            fs::write(path, b"")?;
+        }
+        let op = |from: PathBuf, to: &str| RenameOperation {
+            from,
+            to: temp_dir.path().join(to),
+            episode_id: "S01E01".to_string(),
+            archive_entry: None,
+        };
+        let stdout = SharedBuffer::default();
+        let mut renamer = SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+            dry_run: true,
+            lang: Some(Lang::En),
+            ..test_args(temp_dir.path())
+        })?;
+        renamer.stdout = RefCell::new(Box::new(stdout.clone()));
+        renamer.execute_renames(vec![
+            op(sub_dir.join("Show.S01E01.srt"), "Show.S01E01.1080p.srt"),
+            op(temp_dir.path().join("Show.S01E02.srt"), "Show.S01E02.1080p.srt"),
+        ]);
+
+        let text = stdout.text();
+        let lines: Vec<&str> = text.lines().collect();
+        assert!(lines[0].starts_with("! ") && lines[0].ends_with("(moves to another directory)"), "{}", text);
+        assert!(!lines[1].starts_with('!'), "{}", text);
+        assert!(text.contains("1 operation(s) marked with !"), "{}", text);
+        Ok(())
+    }
+
+    #[test]
+    fn test_only_missing_skips_videos_with_subtitles() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        for name in [
+            "Show.S01E01.1080p.mkv",
+            "Show.S01E01.1080p.es.srt",
+            "Show.S01E01.srt",
+            "Show.S01E02.1080p.mkv",
+            "Show.S01E02.srt",
+        ] {
+            fs::write(temp_dir.path().join(name), b"")?;
+        }
+
+        SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+            only_missing: true,
+            quiet: true,
+            ..test_args(temp_dir.path())
+        })?
+        .run()?;
+
+        assert!(temp_dir.path().join("Show.S01E01.srt").exists());
+        assert!(!temp_dir.path().join("Show.S01E01.1080p.srt").exists());
+        assert!(temp_dir.path().join("Show.S01E02.1080p.srt").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_archive_plans_without_touching_disk() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let tar = temp_dir.path().join("biblioteca.tar");
+        fs::write(&tar, archive::build_tar(&[
+            ("Show/Show.S01E01.1080p.mkv", b""),
+            ("Show/subs.S01E01.srt", b""),
+            ("Show/subs.S01E02.srt", b""),
+        ]))?;
+
+        let stdout = SharedBuffer::default();
+        let mut renamer = SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+            scan_archive: Some(tar.clone()),
+            lang: Some(Lang::En),
+            ..test_args(temp_dir.path())
+        })?;
+        renamer.stdout = RefCell::new(Box::new(stdout.clone()));
+        renamer.run()?;
+
+        let output = stdout.text();
+        assert!(output.contains(r#"[DRY RUN] "subs.S01E01.srt" -> "Show.S01E01.1080p.srt""#), "{}", output);
+        assert_eq!(fs::read_dir(temp_dir.path())?.count(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_archive_ignores_real_files_with_the_same_path() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        fs::create_dir(temp_dir.path().join("Show"))?;
+        fs::write(temp_dir.path().join("Show/Show.S01E01.1080p.srt"), b"")?;
+        let tar = temp_dir.path().join("biblioteca.tar");
+        fs::write(&tar, archive::build_tar(&[
+            ("Show/Show.S01E01.1080p.mkv", b""),
+            ("Show/subs.S01E01.srt", b""),
+        ]))?;
+
+        let stdout = SharedBuffer::default();
+        let mut renamer = SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+            scan_archive: Some(tar),
+            lang: Some(Lang::En),
+            ..test_args(temp_dir.path())
+        })?;
+        renamer.stdout = RefCell::new(Box::new(stdout.clone()));
+        renamer.run()?;
+
+        let output = stdout.text();
+        assert!(output.contains(r#"[DRY RUN] "subs.S01E01.srt" -> "Show.S01E01.1080p.srt""#), "{}", output);
+        assert!(!output.contains("already exists"), "{}", output);
+        Ok(())
+    }
+
+    #[test]
+    fn test_episode_offset_shifts_subtitle_numbers() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        for name in ["Show.S01E05.mkv", "subs.S01E06.srt", "subs.S01E00.srt"] {
+            fs::write(temp_dir.path().join(name), b"")?;
+        }
+
+        SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+            episode_offset: Some(-1),
+            quiet: true,
+            ..test_args(temp_dir.path())
+        })?
+        .run()?;
+
+        assert!(temp_dir.path().join("Show.S01E05.srt").exists());
+        assert!(temp_dir.path().join("subs.S01E00.srt").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_fail_on_duplicate_targets_aborts_before_renaming() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        for name in ["Show.S01E01.mkv", "a.S01E01.srt", "b.S01E01.srt"] {
+            fs::write(temp_dir.path().join(name), b"")?;
+        }
+
+        let result = SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+            fail_on_duplicate_targets: true,
+            lang: Some(Lang::En),
+            quiet: true,
+            ..test_args(temp_dir.path())
+        })?
+        .run();
+
+        assert!(result.unwrap_err().to_string().contains("1 repeated targets"));
+        assert!(temp_dir.path().join("a.S01E01.srt").exists());
+        assert!(temp_dir.path().join("b.S01E01.srt").exists());
+        Ok(())
     }
 
     #[test]
-    fn test_extract_episode_id() -> Result<()> {
+    fn test_from_manifest_plans_virtual_files() -> Result<()> {
         let temp_dir = TempDir::new()?;
-        
-        // Test con regex que captura solo temporada
-        let args1 = Args {
-            srt_regex: Some(r"S(\d{2})E\d{2}".to_string()),
-            mkv_regex: Some(r"S(\d{2})E\d{2}".to_string()),
-            srt_ext: "srt".to_string(),
-            video_ext: "mkv".to_string(),
-            directory: temp_dir.path().to_path_buf(),
-            recursive: false,
-            dry_run: false,
-            quiet: false,
-            verbose: false,
+        let manifest = temp_dir.path().join("listado.json");
+        fs::write(&manifest, r#"[
+            {"path": "Show/Show.S01E01.1080p", "extension": "mkv"},
+            {"path": "Show/subs.S01E01.srt", "extension": ".srt"}
+        ]"#)?;
+
+        let stdout = SharedBuffer::default();
+        let mut renamer = SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+            from_manifest: Some(manifest),
+            lang: Some(Lang::En),
+            ..test_args(temp_dir.path())
+        })?;
+        renamer.stdout = RefCell::new(Box::new(stdout.clone()));
+        renamer.run()?;
+
+        let output = stdout.text();
+        assert!(output.contains(r#"[DRY RUN] "subs.S01E01.srt" -> "Show.S01E01.1080p.srt""#), "{}", output);
+        assert_eq!(fs::read_dir(temp_dir.path())?.count(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_natural_cmp_orders_numbers_by_value() {
+        let mut ids = vec!["E10", "e2", "E1", "E02b", "S2E1", "S10E1"];
+        ids.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(ids, ["E1", "e2", "E02b", "E10", "S2E1", "S10E1"]);
+    }
+
+    #[test]
+    fn test_dry_run_lists_operations_in_natural_order() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        for n in [1, 2, 10] {
+            fs::write(temp_dir.path().join(format!("sub.E{}.srt", n)), b"")?;
+            fs::write(temp_dir.path().join(format!("Show.E{}.mkv", n)), b"")?;
+        }
+
+        let stdout = SharedBuffer::default();
+        let mut renamer = SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(E\d+)".to_string()),
+            dry_run: true,
+            ..test_args(temp_dir.path())
+        })?;
+        renamer.stdout = RefCell::new(Box::new(stdout.clone()));
+        renamer.run()?;
+
+        let output = stdout.text();
+        let positions: Vec<usize> = ["sub.E1.srt", "sub.E2.srt", "sub.E10.srt"]
+            .iter()
+            .map(|name| output.find(&format!("{:?}", name)).unwrap())
+            .collect();
+        assert!(positions.is_sorted(), "{}", output);
+        Ok(())
+    }
+
+    #[test]
+    fn test_mark_unmatched_prefixes_orphan_subtitles() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        for name in ["Show.S01E01.mkv", "a.S01E01.srt", "a.S01E02.srt", "UNMATCHED.a.S01E03.srt"] {
+            fs::write(temp_dir.path().join(name), b"")?;
+        }
+
+        SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+            mark_unmatched: Some("UNMATCHED.".to_string()),
+            quiet: true,
+            ..test_args(temp_dir.path())
+        })?
+        .run()?;
+
+        assert!(temp_dir.path().join("Show.S01E01.srt").exists());
+        assert!(temp_dir.path().join("UNMATCHED.a.S01E02.srt").exists());
+        assert!(temp_dir.path().join("UNMATCHED.a.S01E03.srt").exists());
+        assert!(!temp_dir.path().join("UNMATCHED.UNMATCHED.a.S01E03.srt").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_match_crc_uses_bracketed_checksum() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        for name in [
+            "[Grupo] Serie - 05 [1080p][A1B2C3D4].mkv",
+            "[Grupo] Serie - 05v2 [a1b2c3d4].ass",
+            "[Grupo] Serie - 05 [DEADBEEF].ass",
+        ] {
+            fs::write(temp_dir.path().join(name), b"")?;
+        }
+
+        SubtitleRenamer::new(Args {
+            match_crc: true,
+            srt_ext: "ass".to_string(),
+            quiet: true,
+            ..test_args(temp_dir.path())
+        })?
+        .run()?;
+
+        assert!(temp_dir.path().join("[Grupo] Serie - 05 [1080p][A1B2C3D4].ass").exists());
+        assert!(temp_dir.path().join("[Grupo] Serie - 05 [DEADBEEF].ass").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_normalize_all_extensions_lowercases_subtitles_only() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        for name in ["Huerfano.SRT", "Otro.Ass", "ya.srt", "Video.MKV"] {
+            fs::write(temp_dir.path().join(name), b"")?;
+        }
+
+        SubtitleRenamer::new(Args {
+            normalize_all_extensions: true,
+            srt_ext: "srt,ass".to_string(),
+            quiet: true,
+            ..test_args(temp_dir.path())
+        })?
+        .run()?;
+
+        let mut names: Vec<String> = fs::read_dir(temp_dir.path())?
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        assert_eq!(names, ["Huerfano.srt", "Otro.ass", "Video.MKV", "ya.srt"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_normalize_all_extensions_keeps_existing_lowercase_twin() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let (upper, lower) = (temp_dir.path().join("Show.SRT"), temp_dir.path().join("Show.srt"));
+        fs::write(&upper, b"UPPER")?;
+        if lower.exists() {
+            // En un sistema que ignora mayúsculas no pueden coexistir los dos archivos
+            return Ok(());
+        }
+        fs::write(&lower, b"LOWER")?;
+
+        SubtitleRenamer::new(Args {
+            normalize_all_extensions: true,
+            quiet: true,
+            ..test_args(temp_dir.path())
+        })?
+        .run()?;
+
+        assert_eq!(fs::read(&upper)?, b"UPPER");
+        assert_eq!(fs::read(&lower)?, b"LOWER");
+        Ok(())
+    }
+
+    #[test]
+    fn test_recursive_cross_depth_match_keeps_subtitle_folder() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let deep = temp_dir.path().join("Show/subs/es/S01");
+        fs::create_dir_all(&deep)?;
+        fs::write(temp_dir.path().join("Show/Show.S01E01.mkv"), b"")?;
+        fs::write(temp_dir.path().join("Show/Show.S01E02.mkv"), b"")?;
+        fs::write(deep.join("sub.S01E01.srt"), b"")?;
+        // También a menos profundidad que su video
+        fs::write(temp_dir.path().join("aparte.S01E02.srt"), b"")?;
+
+        let run = |overrides: Args| SubtitleRenamer::new(overrides)?.run();
+        let base = || Args {
+            srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+            recursive: true,
+            quiet: true,
+            ..test_args(temp_dir.path())
         };
 
-        let renamer1 = SubtitleRenamer::new(args1)?;
-        let test_path1 = temp_dir.path().join("Show.S01E05.1080p.mkv");
-        fs::write(&test_path1, b"")?;
+        run(base())?;
+        assert!(deep.join("Show.S01E01.srt").exists());
+        assert!(temp_dir.path().join("Show.S01E02.srt").exists());
 
-        let episode_id1 = renamer1.extract_episode_id(&test_path1, false);
-        assert_eq!(episode_id1, Some("01".to_string()));
+        run(Args { match_video_dir: true, ..base() })?;
+        assert!(temp_dir.path().join("Show/Show.S01E01.srt").exists());
+        assert!(temp_dir.path().join("Show/Show.S01E02.srt").exists());
+        assert!(!deep.join("Show.S01E01.srt").exists());
+        Ok(())
+    }
 
-        // Test con regex que captura temporada y episodio
-        let args2 = Args {
+    #[test]
+    fn test_diff_last_reports_changes_between_runs() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let library = temp_dir.path().join("series");
+        fs::create_dir(&library)?;
+        let store = temp_dir.path().join("ultimo-plan.json");
+        fs::write(library.join("Show.S01E01.mkv"), b"")?;
+        fs::write(library.join("sub.S01E01.srt"), b"")?;
+        fs::write(library.join("sub.S01E02.srt"), b"")?;
+
+        let run = || -> Result<String> {
+            let stdout = SharedBuffer::default();
+            let mut renamer = SubtitleRenamer::new(Args {
+                srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+                dry_run: true,
+                diff_last: Some(Some(store.clone())),
+                lang: Some(Lang::En),
+                ..test_args(&library)
+            })?;
+            renamer.stdout = RefCell::new(Box::new(stdout.clone()));
+            renamer.run()?;
+            Ok(stdout.text())
+        };
+
+        assert!(run()?.contains("No previous plan"));
+        assert!(run()?.contains("has not changed"));
+
+        fs::write(library.join("Show.S01E02.mkv"), b"")?;
+        fs::write(library.join("sub.S01E03.srt"), b"")?;
+        let output = run()?;
+        assert!(output.contains("➕ sub.S01E02.srt -> Show.S01E02.srt"), "{}", output);
+        assert!(output.contains("No longer unmatched: sub.S01E02.srt"), "{}", output);
+        assert!(output.contains("Newly unmatched: sub.S01E03.srt"), "{}", output);
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_dot_marks_orphans_and_conflicts() {
+        let path = |name: &str| PathBuf::from(format!("lib/{}", name));
+        let subtitles = [path("a.E01.srt"), path("b.E01.srt"), path("c.E02.srt")];
+        let videos = [path("Show.E01.mkv"), path("Show.E03.mkv")];
+        let matches = [(subtitles[0].clone(), videos[0].clone()), (subtitles[1].clone(), videos[0].clone())];
+
+        let dot = render_dot(&subtitles, &videos, &matches, |p| p.strip_prefix("lib").unwrap(), Lang::En);
+
+        assert!(dot.starts_with("graph matches {"));
+        assert!(dot.contains(r#"label="subtitles";"#), "{}", dot);
+        assert!(dot.contains(r#""lib/a.E01.srt" -- "lib/Show.E01.mkv" [color=red, fontcolor=red];"#), "{}", dot);
+        assert!(dot.contains(r#""lib/c.E02.srt" [label="c.E02.srt", style=dashed"#), "{}", dot);
+        assert!(dot.contains(r#""lib/Show.E03.mkv" [label="Show.E03.mkv", style=dashed"#), "{}", dot);
+        assert!(dot.contains(r#""lib/Show.E01.mkv" [label="Show.E01.mkv", color=red"#), "{}", dot);
+    }
+
+    #[test]
+    fn test_parse_title_map() {
+        let entries = parse_title_map("title,id\n# temporada 1\nThe Reckoning,S01E05\n\"Hello, Goodbye\",S01E06\n").unwrap();
+        assert_eq!(entries, [
+            ("the reckoning".to_string(), "S01E05".to_string()),
+            ("hello goodbye".to_string(), "S01E06".to_string()),
+        ]);
+        assert_eq!(parse_title_map("ok,S01E01\nsin id"), Err((2, "sin id".to_string())));
+    }
+
+    #[test]
+    fn test_title_map_matches_title_named_subtitles() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let map = temp_dir.path().join("titulos.csv");
+        fs::write(&map, "The Reckoning,S01E05\nReckoning,S01E09\n")?;
+        fs::write(temp_dir.path().join("Show.S01E05.1080p.mkv"), b"")?;
+        fs::write(temp_dir.path().join("Show - the reckoning.srt"), b"")?;
+
+        SubtitleRenamer::new(Args {
             srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
-            mkv_regex: Some(r"(S\d{2}E\d{2})".to_string()),
-            srt_ext: "srt".to_string(),
-            video_ext: "mkv".to_string(),
-            directory: temp_dir.path().to_path_buf(),
-            recursive: false,
-            dry_run: false,
-            quiet: false,
-            verbose: false,
+            title_map: Some(map),
+            quiet: true,
+            ..test_args(temp_dir.path())
+        })?
+        .run()?;
+
+        assert!(temp_dir.path().join("Show.S01E05.1080p.srt").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_handle_readonly_restores_attribute_on_target() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let subtitle = temp_dir.path().join("sub.S01E01.srt");
+        fs::write(temp_dir.path().join("Show.S01E01.mkv"), b"")?;
+        fs::write(&subtitle, b"")?;
+        let mut permissions = fs::metadata(&subtitle)?.permissions();
+        permissions.set_readonly(true);
+        fs::set_permissions(&subtitle, permissions)?;
+
+        SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+            handle_readonly: true,
+            quiet: true,
+            ..test_args(temp_dir.path())
+        })?
+        .run()?;
+
+        let target = temp_dir.path().join("Show.S01E01.srt");
+        assert!(fs::metadata(&target)?.permissions().readonly());
+        assert!(!subtitle.exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_tally_counts_values_per_group() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        for name in ["Show.S01E01.mkv", "Show.S01E02.mkv", "Show.S02E10.mkv", "a.S01E01.srt", "a.S02E10.srt", "Show.S01E09.srt"] {
+            fs::write(temp_dir.path().join(name), b"")?;
+        }
+
+        let stdout = SharedBuffer::default();
+        let mut renamer = SubtitleRenamer::new(Args {
+            srt_regex: Some(r"S(?<season>\d{2})E(\d{2})".to_string()),
+            group_separator: Some("x".to_string()),
+            tally: true,
+            lang: Some(Lang::En),
+            ..test_args(temp_dir.path())
+        })?;
+        renamer.stdout = RefCell::new(Box::new(stdout.clone()));
+        renamer.run()?;
+
+        let output = stdout.text();
+        let lines: Vec<Vec<&str>> = output.lines().map(|line| line.split_whitespace().collect()).collect();
+        assert!(output.contains("📊 Group season:") && output.contains("📊 Group 2:"), "{}", output);
+        assert!(lines.contains(&vec!["01", "2", "2"]), "{}", output);
+        assert!(lines.contains(&vec!["09", "1", "0"]), "{}", output);
+        assert!(lines.contains(&vec!["10", "1", "1"]), "{}", output);
+        assert!(temp_dir.path().join("a.S01E01.srt").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_subtitle_dirs_renames_vobsub_folder() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let vobsub = temp_dir.path().join("Show.S01E05.subs");
+        let extras = temp_dir.path().join("Extras.S01E05");
+        fs::create_dir(&vobsub)?;
+        fs::create_dir(&extras)?;
+        for name in ["disco.idx", "disco.sub", "leeme.txt"] {
+            fs::write(vobsub.join(name), b"")?;
+        }
+        fs::write(extras.join("making-of.txt"), b"")?;
+        fs::write(temp_dir.path().join("Show.S01E05.1080p.mkv"), b"")?;
+
+        SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+            subtitle_dirs: true,
+            quiet: true,
+            ..test_args(temp_dir.path())
+        })?
+        .run()?;
+
+        let renamed = temp_dir.path().join("Show.S01E05.1080p");
+        assert!(renamed.join("disco.idx").exists());
+        assert!(renamed.join("leeme.txt").exists());
+        assert!(!vobsub.exists());
+        assert!(extras.exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_concurrency_safe_refuses_second_instance() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let args = || Args {
+            srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+            concurrency_safe: true,
+            lang: Some(Lang::En),
+            ..test_args(temp_dir.path())
         };
 
-        let renamer2 = SubtitleRenamer::new(args2)?;
-        let test_path2 = temp_dir.path().join("Show.S01E05.1080p.mkv");
-        fs::write(&test_path2, b"")?;
+        let first = SubtitleRenamer::new(args())?;
+        let error = SubtitleRenamer::new(args()).err().unwrap().to_string();
+        assert!(error.contains("Another instance"), "{}", error);
+        assert!(error.contains(&format!("PID {}", std::process::id())), "{}", error);
 
-        let episode_id2 = renamer2.extract_episode_id(&test_path2, false);
-        assert_eq!(episode_id2, Some("S01E05".to_string()));
+        drop(first);
+        SubtitleRenamer::new(args())?;
+        Ok(())
+    }
 
-        // Test con regex que captura múltiples grupos
-        let args3 = Args {
-            srt_regex: Some(r"S(\d{2})E(\d{2})".to_string()),
+    #[test]
+    fn test_truncate_middle_keeps_extension() {
+        assert_eq!(truncate_middle("Show.S01E01.srt", 20), "Show.S01E01.srt");
+        assert_eq!(truncate_middle("Show.S01E01.1080p.WEB-DL.x264-GROUP.mkv", 20), "Show.S01…4-GROUP.mkv");
+        assert_eq!(truncate_middle("Show.S01E01.1080p.WEB-DL.x264-GROUP.mkv", 20).chars().count(), 20);
+        assert_eq!(truncate_middle("Sin_extension_muy_larga", 9), "Sin_…arga");
+        assert_eq!(truncate_middle("a.extension-larguisima", 8), "a.ex…ima");
+    }
+
+    #[test]
+    fn test_preview_width_only_changes_displayed_names() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let video = "Show.S01E01.1080p.WEB-DL.DDP5.1.H.264-GROUP.mkv";
+        fs::write(temp_dir.path().join("Show - 01x01.srt"), b"")?;
+        fs::write(temp_dir.path().join(video), b"")?;
+
+        let buf = SharedBuffer::default();
+        let mut renamer = SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(\d{2})x(\d{2})".to_string()),
             mkv_regex: Some(r"S(\d{2})E(\d{2})".to_string()),
-            srt_ext: "srt".to_string(),
-            video_ext: "mkv".to_string(),
-            directory: temp_dir.path().to_path_buf(),
-            recursive: false,
-            dry_run: false,
-            quiet: false,
-            verbose: false,
-        };
+            group_separator: Some("x".to_string()),
+            preview_width: Some(60),
+            lang: Some(Lang::En),
+            ..test_args(temp_dir.path())
+        })?;
+        renamer.stdout = RefCell::new(Box::new(buf.clone()));
+        renamer.run()?;
 
-        let renamer3 = SubtitleRenamer::new(args3)?;
-        let test_path3 = temp_dir.path().join("Show.S01E05.1080p.mkv");
-        fs::write(&test_path3, b"")?;
+        assert!(fs::exists(temp_dir.path().join("Show.S01E01.1080p.WEB-DL.DDP5.1.H.264-GROUP.srt"))?);
+        let text = buf.text();
+        assert!(text.contains("-> \"Show.S01…4-GROUP.srt\""), "{}", text);
+        assert!(!text.contains("WEB-DL"), "{}", text);
+        Ok(())
+    }
 
-        let episode_id3 = renamer3.extract_episode_id(&test_path3, false);
-        // Con múltiples grupos, solo toma el primer grupo de captura
-        assert_eq!(episode_id3, Some("01".to_string()));
+    #[test]
+    fn test_lang_from_dir_places_subtitles_next_to_video() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let show = temp_dir.path().join("Show");
+        for lang in ["eng", "spa"] {
+            fs::create_dir_all(show.join("subs").join(lang))?;
+            fs::write(show.join("subs").join(lang).join("S01E05.srt"), b"")?;
+        }
+        fs::write(show.join("Show.S01E05.1080p.mkv"), b"")?;
+
+        let renamer = SubtitleRenamer::new(Args {
+            srt_regex: Some(r"S(\d{2})E(\d{2})".to_string()),
+            recursive: true,
+            lang_from_dir: true,
+            quiet: true,
+            ..test_args(temp_dir.path())
+        })?;
+        renamer.run()?;
 
+        assert!(fs::exists(show.join("Show.S01E05.1080p.eng.srt"))?);
+        assert!(fs::exists(show.join("Show.S01E05.1080p.spa.srt"))?);
+        assert!(!fs::exists(show.join("subs/eng/S01E05.srt"))?);
+
+        assert_eq!(dir_language_tag("pt_br").as_deref(), Some("pt-BR"));
+        assert_eq!(dir_language_tag("Season 1"), None);
+        for name in ["ER", "Sub", "old", "tmp"] {
+            assert_eq!(dir_language_tag(name), None, "{}", name);
+        }
         Ok(())
     }
 
     #[test]
-    fn test_extract_episode_id_various_formats() -> Result<()> {
+    fn test_lang_from_dir_only_reads_the_subtitle_folder() -> Result<()> {
         let temp_dir = TempDir::new()?;
-        
-        // Test con diferentes formatos de nombres de archivo
-        let test_cases = vec![
-            // (regex, filename, expected_result)
-            (r"(S\d{2}E\d{2})", "Show.S01E05.1080p.mkv", Some("S01E05")),
-            (r"(\d{1,2}x\d{2})", "Show.1x05.1080p.mkv", Some("1x05")),
-            (r"Episode\.(\d+)", "Show.Episode.5.mkv", Some("5")),
-            (r"Ep(\d+)", "Show.Ep05.mkv", Some("05")),
-            (r"(S\d{2}E\d{2})", "Show.no.match.mkv", None),
-        ];
+        let season = temp_dir.path().join("Es").join("Season1");
+        fs::create_dir_all(&season)?;
+        fs::write(season.join("sub.S01E01.srt"), b"")?;
+        fs::write(season.join("Es.S01E01.mkv"), b"")?;
 
-        for (regex_str, filename, expected) in test_cases {
-            let args = Args {
-                srt_regex: Some(regex_str.to_string()),
-                mkv_regex: Some(regex_str.to_string()),
-                srt_ext: "srt".to_string(),
-                video_ext: "mkv".to_string(),
-                directory: temp_dir.path().to_path_buf(),
-                recursive: false,
-                dry_run: false,
-                quiet: false,
-                verbose: false,
-            };
+        let renamer = SubtitleRenamer::new(Args {
+            srt_regex: Some(r"S(\d{2})E(\d{2})".to_string()),
+            recursive: true,
+            lang_from_dir: true,
+            quiet: true,
+            ..test_args(temp_dir.path())
+        })?;
+        renamer.run()?;
 
-            let renamer = SubtitleRenamer::new(args)?;
-            let test_path = temp_dir.path().join(filename);
-            fs::write(&test_path, b"")?;
+        assert!(fs::exists(season.join("Es.S01E01.srt"))?);
+        Ok(())
+    }
 
-            let episode_id = renamer.extract_episode_id(&test_path, false);
-            assert_eq!(
-                episode_id,
-                expected.map(|s| s.to_string()),
-                "Failed for regex '{}' and filename '{}'",
-                regex_str,
-                filename
-            );
+    #[test]
+    fn test_abort_if_video_missing_skips_whole_directory() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        for (dir, names) in [
+            ("S01", ["Show 1x01.srt", "Show 1x02.srt", "Show.S01E01.mkv"]),
+            ("S02", ["Show 2x01.srt", "Show 2x02.srt", "Show.S02E01.mkv"]),
+        ] {
+            fs::create_dir(temp_dir.path().join(dir))?;
+            for name in names {
+                fs::write(temp_dir.path().join(dir).join(name), b"")?;
+            }
+        }
+        fs::write(temp_dir.path().join("S02").join("Show.S02E02.mkv"), b"")?;
+
+        let renamer = SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(\d)x(\d{2})".to_string()),
+            mkv_regex: Some(r"S0(\d)E(\d{2})".to_string()),
+            group_separator: Some("x".to_string()),
+            recursive: true,
+            colocated_only: true,
+            abort_if_video_missing_for_any_subtitle_in_dir: true,
+            quiet: true,
+            ..test_args(temp_dir.path())
+        })?;
+        let (subtitles, videos) = renamer.categorize_files()?;
+        let plan = renamer.plan_renames(subtitles, videos);
+
+        let mut targets: Vec<_> = plan.operations.iter().map(|op| op.to.clone()).collect();
+        targets.sort();
+        assert_eq!(
+            targets,
+            [temp_dir.path().join("S02/Show.S02E01.srt"), temp_dir.path().join("S02/Show.S02E02.srt")]
+        );
+        assert_eq!(plan.unmatched, vec![temp_dir.path().join("S01/Show 1x02.srt")]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_verbose_reports_matched_group_indices() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        fs::write(temp_dir.path().join("Show 1x05.srt"), b"")?;
+        fs::write(temp_dir.path().join("Show.S01E05.mkv"), b"")?;
+
+        let buf = SharedBuffer::default();
+        let mut renamer = SubtitleRenamer::new(Args {
+            srt_regex: Some(r"S(\d{2})E(?<ep>\d{2})|(\d{1,2})x(\d{2})".to_string()),
+            group_separator: Some("x".to_string()),
+            verbose: true,
+            lang: Some(Lang::En),
+            ..test_args(temp_dir.path())
+        })?;
+        renamer.stdout = RefCell::new(Box::new(buf.clone()));
+        renamer.categorize_files()?;
+
+        let text = buf.text();
+        assert!(text.contains("\"Show 1x05.srt\": matched groups 3=\"1\", 4=\"05\""), "{}", text);
+        assert!(text.contains("\"Show.S01E05.mkv\": matched groups 1=\"01\", 2 (ep)=\"05\""), "{}", text);
+        Ok(())
+    }
+
+    #[test]
+    fn test_first_number_captures_first_integer() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let renamer = SubtitleRenamer::new(Args {
+            first_number: true,
+            ..test_args(temp_dir.path())
+        })?;
+        let id = |name: &str| renamer.extract_episode_id(Path::new(name), true);
+
+        assert_eq!(id("Episodio 05.srt").as_deref(), Some("5"));
+        assert_eq!(id("12 - El regreso.srt").as_deref(), Some("12"));
+        assert_eq!(id("Show_007_x264.mkv").as_deref(), Some("7"));
+        assert_eq!(id("Cap.000.srt").as_deref(), Some("0"));
+        assert_eq!(id("Trailer.mp4"), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_first_number_matches_without_regex() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        fs::write(temp_dir.path().join("Episodio 5.srt"), b"")?;
+        fs::write(temp_dir.path().join("Mi Serie - 05 [1080p].mkv"), b"")?;
+
+        let renamer = SubtitleRenamer::new(Args {
+            first_number: true,
+            quiet: true,
+            ..test_args(temp_dir.path())
+        })?;
+        let (subtitles, videos) = renamer.categorize_files()?;
+        let plan = renamer.plan_renames(subtitles, videos);
+
+        assert_eq!(plan.operations.len(), 1);
+        assert_eq!(plan.operations[0].to, temp_dir.path().join("Mi Serie - 05 [1080p].srt"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_normalize_numbers_matches_three_and_two_digit_padding() -> Result<()> {
+        assert_eq!(strip_number_padding("S01E005"), "S1E5");
+        assert_eq!(strip_number_padding("E000"), "E0");
+        assert_eq!(strip_number_padding("Parte.B"), "Parte.B");
+
+        let temp_dir = TempDir::new()?;
+        fs::write(temp_dir.path().join("Show.S01E005.srt"), b"")?;
+        fs::write(temp_dir.path().join("Show.S01E05.720p.mkv"), b"")?;
+        fs::write(temp_dir.path().join("Show.S01E050.srt"), b"")?;
+
+        let renamer = SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(S\d+E\d+)".to_string()),
+            normalize_numbers: true,
+            quiet: true,
+            ..test_args(temp_dir.path())
+        })?;
+        let (subtitles, videos) = renamer.categorize_files()?;
+        let plan = renamer.plan_renames(subtitles, videos);
+
+        assert_eq!(plan.operations.len(), 1);
+        assert_eq!(plan.operations[0].episode_id, "S1E5");
+        assert_eq!(plan.operations[0].to, temp_dir.path().join("Show.S01E05.720p.srt"));
+        assert_eq!(plan.unmatched, vec![temp_dir.path().join("Show.S01E050.srt")]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_summary_json_appends_one_line_per_run() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let log = temp_dir.path().join("runs.log");
+        fs::write(temp_dir.path().join("Show.S01E05.srt"), b"")?;
+        fs::write(temp_dir.path().join("Show.S01E05.1080p.mkv"), b"")?;
+        fs::write(temp_dir.path().join("Show.S01E06.srt"), b"")?;
+
+        for _ in 0..2 {
+            SubtitleRenamer::new(Args {
+                srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+                quiet: true,
+                summary_json: Some(Some(log.clone())),
+                ..test_args(temp_dir.path())
+            })?
+            .run()?;
+        }
+
+        let content = fs::read_to_string(&log)?;
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines.len(), 2);
+        let first = json::from_str(lines[0])?;
+        assert!(first.get("timestamp").and_then(Json::as_f64).is_some());
+        assert_eq!(first.get("succeeded").and_then(Json::as_f64), Some(1.0));
+        assert_eq!(first.get("unmatched").and_then(Json::as_f64), Some(1.0));
+        assert!(first.get("duration_seconds").is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn test_natural_sort_does_not_reorder_execution() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let dir = temp_dir.path().join("Ep01dir");
+        fs::create_dir(&dir)?;
+        fs::write(dir.join("sub.S01E01.srt"), b"")?;
+        fs::write(dir.join("Show.S01E01.1080p.mkv"), b"")?;
+
+        let renamer = SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+            recursive: true,
+            rename_dir: true,
+            quiet: true,
+            ..test_args(temp_dir.path())
+        })?;
+        assert_eq!(renamer.args.sort, SortOrder::Natural);
+        renamer.run()?;
+
+        assert!(fs::exists(temp_dir.path().join("Show.S01E01.1080p/Show.S01E01.1080p.srt"))?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_rename_dir_waits_for_the_files_inside() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let dir = temp_dir.path().join("Ep01dir");
+        fs::create_dir(&dir)?;
+        for name in ["sub.S01E01.srt", "Show.S01E01.1080p.mkv", "Show.S01E01.1080p.srt"] {
+            fs::write(dir.join(name), b"")?;
         }
 
+        let renamer = SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(S\d{2}E\d{2})".to_string()),
+            recursive: true,
+            rename_dir: true,
+            quiet: true,
+            ..test_args(temp_dir.path())
+        })?;
+        renamer.run()?;
+
+        // El subtítulo no se pudo renombrar, así que la carpeta tampoco
+        assert!(fs::exists(dir.join("sub.S01E01.srt"))?);
+        assert!(!fs::exists(temp_dir.path().join("Show.S01E01.1080p"))?);
+        Ok(())
+    }
+
+    #[cfg(any(target_os = "macos", target_os = "windows"))]
+    #[test]
+    fn test_fix_case_on_case_insensitive_filesystem() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let from = temp_dir.path().join("show.s01e05.srt");
+        let to = temp_dir.path().join("Show.S01E05.srt");
+        fs::write(&from, b"")?;
+
+        // El destino "existe" porque es el mismo archivo; no debe tratarse como conflicto
+        assert!(to.exists());
+
+        let renamer = SubtitleRenamer::new(Args {
+            srt_regex: Some(r"(?i)(s\d{2}e\d{2})".to_string()),
+            quiet: true,
+            fix_case: true,
+            ..test_args(temp_dir.path())
+        })?;
+        let outcomes = renamer.execute_renames(vec![RenameOperation {
+            from,
+            to,
+            episode_id: "s01e05".to_string(),
+            archive_entry: None,
+        }]);
+        assert_eq!(outcomes[0].status, OperationStatus::Renamed);
+        let names: Vec<_> = fs::read_dir(temp_dir.path())?
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(names, vec!["Show.S01E05.srt"]);
         Ok(())
     }
-}
\ No newline at end of file
+}
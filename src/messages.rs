@@ -0,0 +1,783 @@
+//! Catálogo de mensajes para el usuario en español e inglés.
+//!
+//! Cada mensaje es una variante de [`Msg`] con sus datos; [`Msg::text`] lo
+//! traduce al idioma elegido con `--lang`.
+
+use std::env;
+use std::ffi::OsStr;
+use std::path::Path;
+
+use clap::ValueEnum;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Lang {
+    En,
+    Es,
+}
+
+impl Lang {
+    /// Idioma según `LC_ALL`, `LC_MESSAGES` o `LANG`, en ese orden.
+    pub fn from_env() -> Self {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|var| env::var(var).ok())
+            .find(|value| !value.is_empty())
+            .map_or(Lang::Es, |value| Self::from_locale(&value))
+    }
+
+    /// Español para locales `es*` y para `C`/`POSIX` (el idioma histórico de
+    /// la herramienta); inglés para cualquier otro.
+    pub fn from_locale(locale: &str) -> Self {
+        let language = locale.split(['_', '.', '@', '-']).next().unwrap_or_default();
+        match language.to_lowercase().as_str() {
+            "es" | "c" | "posix" | "" => Lang::Es,
+            _ => Lang::En,
+        }
+    }
+}
+
+pub enum Msg<'a> {
+    Usage,
+    NoMatcher,
+    InvalidSubtitleRegex(&'a str),
+    InvalidVideoRegex(&'a str),
+    InvalidPartRegex(&'a str),
+    DirectoryNotFound(&'a Path),
+    OverlappingExtensions(&'a str),
+    ReadDirectory(&'a Path),
+    FileAccess(String),
+    PruningDirectory(&'a Path),
+    ReadHashManifest(&'a Path),
+    InvalidHashManifest(&'a Path),
+    HashManifestLine { line: usize, content: &'a str },
+    HashNotInManifest { crc: u32, name: &'a OsStr },
+    HashFailed { path: &'a Path, error: String },
+    FoundFiles { subtitles: usize, videos: usize },
+    VideoInOtherDirectory { id: &'a str, name: &'a OsStr },
+    NoVideoForEpisode { id: &'a str, name: &'a OsStr },
+    NothingToRename,
+    LowMatchRate { matched: usize, total: usize, rate: f64, min_rate: f64 },
+    DirectoryHasOtherFiles(&'a Path),
+    CaseOnlyRename { from: &'a OsStr, to: &'a OsStr },
+    NumberedDestination { taken: &'a OsStr, free: &'a OsStr },
+    NoFreeNumberedName { name: &'a OsStr, attempts: usize, id: &'a str },
+    DestinationExists { name: &'a OsStr, id: &'a str },
+    DryRunRename { from: &'a OsStr, to: &'a OsStr },
+    Renamed { from: &'a OsStr, to: &'a OsStr },
+    RenameError { name: &'a OsStr, error: String },
+    SummaryHeader,
+    SummarySucceeded(usize),
+    SummaryErrors(usize),
+    SummaryDryRun,
+    CreatePlan(&'a Path),
+    ReadPlan(&'a Path),
+    InvalidPlan(&'a Path),
+    UnsupportedPlanVersion { path: &'a Path, version: Option<f64> },
+    PlanWithoutOperations(&'a Path),
+    PlanMissingField { key: &'a str, path: &'a Path },
+    PlanSourceMissing(&'a Path),
+    PlanSourceChanged(&'a Path),
+    PlanSaved { count: usize, path: &'a Path },
+    WriteMetrics(&'a Path),
+    RenameOneMissing(&'a Path),
+    ReviewPageHeader { page: usize, pages: usize },
+    ReviewOperation { index: usize, from: &'a OsStr, to: &'a OsStr },
+    ReviewPagePrompt,
+    ReviewItemPrompt { index: usize, from: &'a OsStr, to: &'a OsStr },
+    SuspiciousMatch { subtitle: &'a OsStr, video: &'a OsStr, id: &'a str },
+    HistoryNotWritable(&'a Path),
+    NoHistoryLocation,
+    InvalidShowKeyRegex(&'a str),
+    InvalidRegex(&'a str),
+    CaptureGroupOutOfRange { regex: &'a str, group: usize },
+    RegexMatch { name: &'a OsStr, id: &'a str },
+    RegexNoMatch(&'a OsStr),
+    DateRegexGroups(&'a str),
+    FailFastStopped { remaining: usize },
+    InvalidSanitizeChar(char),
+    ReviewApplyPrompt(usize),
+    ReviewCancelled,
+    InvalidMetaJson { path: &'a Path, error: String },
+    OutsideOriginalDirectory { from: &'a Path, to: &'a Path },
+    TableExtension,
+    TableSucceeded,
+    TableSkipped,
+    TableErrors,
+    TableTotal,
+    SampleMore(usize),
+    DryRunExtract { archive: &'a OsStr, entry: &'a str, to: &'a OsStr },
+    Extracted { archive: &'a OsStr, entry: &'a str, to: &'a OsStr },
+    ReadArchive { path: &'a Path, error: String },
+    NoSubtitleForVideo { id: &'a str, name: &'a OsStr },
+    VerifyMissing(&'a Path),
+    VerificationFailed(usize),
+    InvalidTemplate { template: &'a str, error: String },
+    ReadTemplateFile(&'a Path),
+    InvalidTemplateLine { path: &'a Path, line: usize, error: String },
+    UnknownTemplateToken { token: &'a str, template: &'a str, known: String },
+    RemovedEmptyDirectory(&'a Path),
+    DryRunRemoveDirectory(&'a Path),
+    RemoveDirectoryError { path: &'a Path, error: String },
+    DuplicateVideoSkipped { id: &'a str, chosen: &'a OsStr, skipped: &'a OsStr },
+    MtimeMatchWarning,
+    MtimeCountMismatch { subtitles: usize, videos: usize },
+    ReadModifiedTime(&'a Path),
+    ExplainFile(&'a Path),
+    ExplainNotFound(&'a str),
+    ExplainExtension { extension: &'a str, list: &'a str },
+    ExplainIgnoredExtension(&'a str),
+    ExplainKey { pattern: &'a str, key: Option<&'a str> },
+    ExplainRenamed(&'a Path),
+    ExplainUnmatched,
+    ExplainAlreadyNamed,
+    ExplainVideoTarget(usize),
+    InvalidStripRegex(&'a str),
+    AnchoredRegexHint(&'a str),
+    OpenEvents(&'a Path),
+    EventsFdUnsupported,
+    AlreadySubtitled { name: &'a OsStr, id: &'a str, from: &'a OsStr },
+    NotWritable { dir: &'a Path, error: String },
+    UnknownNormalizeGroup { group: &'a str, regex: &'a str },
+    ParallelFallback,
+    InvalidAltRegex(&'a str),
+    EmitSkippedArchives(usize),
+    InvalidDirConfig { path: &'a Path, error: String },
+    TooManyOperations { count: usize, max: usize },
+    DuplicateSubtitles { id: &'a str, count: usize },
+    DuplicateVideos { id: &'a str, count: usize },
+    NoDuplicates,
+    RiskMovesDirectory,
+    RiskRenumbered,
+    RiskySummary(usize),
+    VideoAlreadySubtitled(&'a OsStr),
+    ScanArchive(&'a Path),
+    EpisodeOffsetWarning(i64),
+    EpisodeOffsetOutOfRange { name: &'a OsStr, id: &'a str },
+    DuplicateTarget { to: &'a Path, sources: String },
+    DuplicateTargets(usize),
+    ReadFileManifest(&'a Path),
+    InvalidFileManifest { path: &'a Path, reason: &'a str },
+    InvalidMarkPrefix(&'a str),
+    DiffHeader(&'a Path),
+    DiffNewMatch { from: &'a str, to: &'a str },
+    DiffDroppedMatch { from: &'a str, to: &'a str },
+    DiffNewOrphan(&'a str),
+    DiffResolvedOrphan(&'a str),
+    DiffNewConflict(&'a str),
+    DiffNoChanges,
+    DiffNoPrevious(&'a Path),
+    NoPlanStoreLocation,
+    SubtitleTooLarge { name: &'a OsStr, size: u64, max: u64 },
+    VideoTooSmall { name: &'a OsStr, size: u64, min: u64 },
+    DotSaved { path: &'a Path, matches: usize },
+    CreateDot(&'a Path),
+    EditItemPrompt { index: usize, from: &'a OsStr, to: &'a OsStr },
+    EditNamePrompt,
+    InvalidEditedName(&'a str),
+    ReadTitleMap(&'a Path),
+    TitleMapLine { path: &'a Path, line: usize, content: &'a str },
+    TitleMapped { name: &'a OsStr, id: &'a str },
+    ReadOnlySource(String),
+    TallyGroup(&'a str),
+    TableValue,
+    TableSubtitles,
+    TableVideos,
+    TallyEmpty,
+    DirectoryLocked { path: &'a Path, owner: String },
+    LockFailed(&'a Path),
+    ShowKeyAutoNeedsDate,
+    IncompleteDirectorySkipped { dir: &'a Path, missing: usize, skipped: usize },
+    MatchedGroups { name: &'a OsStr, groups: String },
+    WriteSummaryJson(&'a Path),
+    LowercaseTwinExists { from: &'a OsStr, to: &'a OsStr },
+    DirectoryRenameBlocked { dir: &'a OsStr, name: &'a OsStr },
+    ConfigDefault,
+    ConfigDerived,
+    InvalidRate(&'a str),
+    InvalidSize(&'a str),
+    ConfigLine { line: usize, error: String },
+    ConfigMissingEquals,
+    ConfigTrailingText(&'a str),
+    ConfigUnknownKey(&'a str),
+    ConfigExpectedString,
+    ConfigUnclosedArray,
+    ConfigUnclosedQuote,
+    ConfigUnsupportedEscape(char),
+    ConfigExpectedQuoted,
+    DotSubtitles,
+    DotVideos,
+    ManifestExpectedArray,
+    ManifestMissingPath,
+    EmitSkippedExisting { from: &'a OsStr, to: &'a OsStr },
+    EmitSkippedRepeated { from: &'a OsStr, to: &'a OsStr },
+    EmitSkippedNonUtf8(&'a Path),
+    EmitNeedsUtilLinux,
+    ExitCodesHelp,
+}
+
+impl Msg<'_> {
+    pub fn text(&self, lang: Lang) -> String {
+        match lang {
+            Lang::Es => self.es(),
+            Lang::En => self.en(),
+        }
+    }
+
+    fn es(&self) -> String {
+        match self {
+            Msg::Usage => "❌ Debes proporcionar al menos un regex.\n\
+                \n📚 Ejemplos de uso:\n\
+                \x20 # Básico con regex para ambos tipos de archivo:\n\
+                \x20 sub-renamer --srt-regex 'S(\\d{2})E(\\d{2})' --mkv-regex 'S(\\d{2})E(\\d{2})'\n\
+                \n  # Con extensiones múltiples y modo recursivo:\n\
+                \x20 sub-renamer --srt-regex 'S(\\d{2})E(\\d{2})' --srt-ext srt,ass,vtt --video-ext mkv,mp4,avi --recursive\n\
+                \n  # Modo de prueba (no renombra realmente):\n\
+                \x20 sub-renamer --srt-regex 'S(\\d{2})E(\\d{2})' --dry-run\n\
+                \n  # En directorio específico:\n\
+                \x20 sub-renamer --srt-regex 'S(\\d{2})E(\\d{2})' --directory /path/to/episodes\n\
+                \n  # Películas divididas en partes (CD1/CD2, Part 1/Part 2):\n\
+                \x20 sub-renamer --part-regex '(?i)(?:cd|part)\\s*(\\d+)'\n\
+                \n  # Fansubs con el CRC32 del video en el nombre ([A1B2C3D4]):\n\
+                \x20 sub-renamer --match-crc --srt-ext ass\n\
+                \n  # Sin regex: el primer número del nombre es el episodio:\n\
+                \x20 sub-renamer --first-number"
+                .to_string(),
+            Msg::NoMatcher => {
+                "❌ Debes proporcionar al menos un regex (--srt-regex, --mkv-regex o --part-regex)".to_string()
+            }
+            Msg::InvalidSubtitleRegex(re) => format!("Regex inválido para subtítulos: {}", re),
+            Msg::InvalidVideoRegex(re) => format!("Regex inválido para videos: {}", re),
+            Msg::InvalidPartRegex(re) => format!("Regex inválido para partes: {}", re),
+            Msg::DirectoryNotFound(dir) => format!("❌ El directorio {:?} no existe", dir),
+            Msg::OverlappingExtensions(exts) => format!(
+                "❌ Extensiones presentes en --srt-ext y --video-ext a la vez: {} (cada extensión debe estar en una sola lista)",
+                exts
+            ),
+            Msg::ReadDirectory(dir) => format!("No se pudo leer el directorio {:?}", dir),
+            Msg::FileAccess(error) => format!("⚠️ Error accediendo a archivo: {}", error),
+            Msg::PruningDirectory(dir) => format!("✂️ Omitiendo directorio: {:?}", dir),
+            Msg::ReadHashManifest(path) => format!("No se pudo leer el manifiesto de hashes {:?}", path),
+            Msg::InvalidHashManifest(path) => format!("Manifiesto de hashes inválido {:?}", path),
+            Msg::HashManifestLine { line, content } => {
+                format!("línea {}: se esperaba '<crc32> <id>', encontrado {:?}", line, content)
+            }
+            Msg::HashNotInManifest { crc, name } => {
+                format!("🔎 Hash {:08x} no registrado en el manifiesto: {:?}", crc, name)
+            }
+            Msg::HashFailed { path, error } => format!("⚠️ No se pudo calcular el hash de {:?}: {}", path, error),
+            Msg::FoundFiles { subtitles, videos } => {
+                format!("📊 Encontrados {} subtítulos y {} videos", subtitles, videos)
+            }
+            Msg::VideoInOtherDirectory { id, name } => format!(
+                "⚠️ El video del episodio '{}' está en otro directorio (subtítulo: {:?}, --colocated-only)",
+                id, name
+            ),
+            Msg::NoVideoForEpisode { id, name } => {
+                format!("⚠️ No se encontró video para episodio '{}' (subtítulo: {:?})", id, name)
+            }
+            Msg::NothingToRename => "ℹ️ No hay archivos para renombrar".to_string(),
+            Msg::LowMatchRate { matched, total, rate, min_rate } => format!(
+                "❌ Solo {} de {} subtítulos ({:.0}%) tienen video, por debajo del mínimo de {:.0}% (--min-match-rate); revisa el regex",
+                matched, total, rate * 100.0, min_rate * 100.0
+            ),
+            Msg::DirectoryHasOtherFiles(dir) => {
+                format!("⚠️ No se renombra el directorio {:?}: contiene otros archivos", dir)
+            }
+            Msg::CaseOnlyRename { from, to } => format!(
+                "ℹ️ Ya tiene el nombre efectivo (solo difiere en mayúsculas): {:?} -> {:?} (usa --fix-case para aplicarlo)",
+                from, to
+            ),
+            Msg::NumberedDestination { taken, free } => format!("🔢 Destino ocupado {:?}, usando {:?}", taken, free),
+            Msg::NoFreeNumberedName { name, attempts, id } => format!(
+                "⚠️ No se encontró un nombre libre para {:?} tras {} intentos (episodio: {})",
+                name, attempts, id
+            ),
+            Msg::DestinationExists { name, id } => {
+                format!("⚠️ El archivo de destino ya existe: {:?} (episodio: {})", name, id)
+            }
+            Msg::DryRunRename { from, to } => format!("🔄 [DRY RUN] {:?} -> {:?}", from, to),
+            Msg::Renamed { from, to } => format!("✅ Renombrado: {:?} -> {:?}", from, to),
+            Msg::RenameError { name, error } => format!("❌ Error renombrando {:?}: {}", name, error),
+            Msg::SummaryHeader => "\n📈 Resumen:".to_string(),
+            Msg::SummarySucceeded(n) => format!("  ✅ Éxitos: {}", n),
+            Msg::SummaryErrors(n) => format!("  ❌ Errores: {}", n),
+            Msg::SummaryDryRun => {
+                "  ℹ️ Modo de prueba activado - no se renombraron archivos realmente".to_string()
+            }
+            Msg::CreatePlan(path) => format!("No se pudo crear el archivo de plan {:?}", path),
+            Msg::ReadPlan(path) => format!("No se pudo leer el archivo de plan {:?}", path),
+            Msg::InvalidPlan(path) => format!("Plan inválido {:?}", path),
+            Msg::UnsupportedPlanVersion { path, version } => {
+                format!("❌ Versión de plan no soportada en {:?}: {:?}", path, version)
+            }
+            Msg::PlanWithoutOperations(path) => format!("El plan {:?} no contiene operaciones", path),
+            Msg::PlanMissingField { key, path } => {
+                format!("Operación sin campo '{}' en el plan {:?}", key, path)
+            }
+            Msg::PlanSourceMissing(path) => format!("⚠️ El archivo del plan ya no existe, se omite: {:?}", path),
+            Msg::PlanSourceChanged(path) => {
+                format!("⚠️ El archivo cambió desde que se creó el plan, se omite: {:?}", path)
+            }
+            Msg::PlanSaved { count, path } => format!("💾 Plan con {} operaciones guardado en {:?}", count, path),
+            Msg::WriteMetrics(path) => format!("No se pudo escribir el archivo de métricas {:?}", path),
+            Msg::RenameOneMissing(path) => format!("El archivo indicado en --rename-one no existe: {:?}", path),
+            Msg::ReviewPageHeader { page, pages } => format!("📄 Página {} de {}", page, pages),
+            Msg::ReviewOperation { index, from, to } => format!("  {}. {:?} -> {:?}", index, from, to),
+            Msg::ReviewPagePrompt => {
+                "¿Aprobar? [s]í esta página / [n]o / [t]odas las restantes / [u]na a una / [q] terminar: ".to_string()
+            }
+            Msg::ReviewItemPrompt { index, from, to } => {
+                format!("  {}. {:?} -> {:?} ¿Renombrar? [s/n/t/q]: ", index, from, to)
+            }
+            Msg::SuspiciousMatch { subtitle, video, id } => format!(
+                "⚠️ Posible colisión de ID {}: {:?} y {:?} casi no comparten palabras",
+                id, subtitle, video
+            ),
+            Msg::HistoryNotWritable(path) => format!("No se puede escribir en el archivo de historial {:?}", path),
+            Msg::NoHistoryLocation => {
+                "No se encontró el directorio de datos para el historial; usa --history-file".to_string()
+            }
+            Msg::InvalidShowKeyRegex(re) => format!("Regex inválido para la serie: {}", re),
+            Msg::InvalidRegex(re) => format!("Regex inválido: {}", re),
+            Msg::CaptureGroupOutOfRange { regex, group } => {
+                format!("El regex {} no tiene el grupo de captura {}", regex, group)
+            }
+            Msg::RegexMatch { name, id } => format!("✅ {:?} -> {}", name, id),
+            Msg::RegexNoMatch(name) => format!("❌ {:?}: sin coincidencia", name),
+            Msg::DateRegexGroups(re) => {
+                format!("El regex {} debe tener tres grupos de captura (año, mes y día) para --date-format", re)
+            }
+            Msg::FailFastStopped { remaining } => {
+                format!("⛔ Detenido en el primer error (--fail-fast); {} operaciones sin intentar", remaining)
+            }
+            Msg::InvalidSanitizeChar(c) => format!("--sanitize-char no puede ser un carácter inválido en nombres: {:?}", c),
+            Msg::ReviewApplyPrompt(count) => format!("¿Aplicar estos {} renombrados? [s/n]: ", count),
+            Msg::ReviewCancelled => "🚫 No se aplicó ningún cambio".to_string(),
+            Msg::InvalidMetaJson { path, error } => format!("⚠️ Metadatos JSON inválidos en {:?}: {}", path, error),
+            Msg::OutsideOriginalDirectory { from, to } => format!(
+                "Operación fuera del directorio original rechazada por --rename-subtitles-in-place-only: {:?} -> {:?}",
+                from, to
+            ),
+            Msg::TableExtension => "Extensión".to_string(),
+            Msg::TableSucceeded => "Éxitos".to_string(),
+            Msg::TableSkipped => "Omitidos".to_string(),
+            Msg::TableErrors => "Errores".to_string(),
+            Msg::TableTotal => "Total".to_string(),
+            Msg::SampleMore(n) => format!("   ... y {} más", n),
+            Msg::DryRunExtract { archive, entry, to } => format!("🔄 [DRY RUN] {:?} ({}) -> {:?}", archive, entry, to),
+            Msg::Extracted { archive, entry, to } => format!("📦 Extraído: {:?} ({}) -> {:?}", archive, entry, to),
+            Msg::ReadArchive { path, error } => format!("⚠️ No se pudo leer el zip {:?}: {}", path, error),
+            Msg::NoSubtitleForVideo { id, name } => {
+                format!("⚠️ No se encontró subtítulo para episodio '{}' (video: {:?})", id, name)
+            }
+            Msg::VerifyMissing(path) => format!("❌ Verificación: el archivo renombrado no existe en {:?}", path),
+            Msg::VerificationFailed(n) => format!("La verificación posterior encontró {} renombrados sin efecto", n),
+            Msg::InvalidTemplate { template, error } => format!("Plantilla inválida {:?}: {}", template, error),
+            Msg::ReadTemplateFile(path) => format!("No se pudo leer el archivo de plantillas {:?}", path),
+            Msg::InvalidTemplateLine { path, line, error } => {
+                format!("Plantilla inválida en {:?}, línea {}: {}", path, line, error)
+            }
+            Msg::UnknownTemplateToken { token, template, known } => format!(
+                "Marcador desconocido {{{}}} en la plantilla {:?} (disponibles: {})",
+                token, template, known
+            ),
+            Msg::RemovedEmptyDirectory(path) => format!("🗑️ Directorio vacío eliminado: {:?}", path),
+            Msg::DryRunRemoveDirectory(path) => format!("🗑️ [DRY RUN] Se eliminaría el directorio vacío {:?}", path),
+            Msg::RemoveDirectoryError { path, error } => format!("❌ No se pudo eliminar el directorio {:?}: {}", path, error),
+            Msg::DuplicateVideoSkipped { id, chosen, skipped } => format!(
+                "⚠️ Varios videos para {}: se usa {:?} (mayor resolución) y se ignora {:?}",
+                id, chosen, skipped
+            ),
+            Msg::MtimeMatchWarning => "⚠️ --match-by-mtime empareja por fecha de modificación, sin mirar los nombres: revisa cada renombrado antes de confirmar".to_string(),
+            Msg::MtimeCountMismatch { subtitles, videos } => format!(
+                "❌ --match-by-mtime necesita tantos subtítulos como videos (hay {} subtítulos y {} videos)",
+                subtitles, videos
+            ),
+            Msg::ReadModifiedTime(path) => format!("No se pudo leer la fecha de modificación de {:?}", path),
+            Msg::ExplainFile(path) => format!("🔎 Análisis de {:?}", path),
+            Msg::ExplainNotFound(name) => format!("❌ No se encontró {:?} entre los archivos analizados", name),
+            Msg::ExplainExtension { extension, list } => format!("  extensión {:?}: coincide con {}", extension, list),
+            Msg::ExplainIgnoredExtension(extension) => format!("  extensión {:?}: no está en --srt-ext ni en --video-ext, el archivo se ignora", extension),
+            Msg::ExplainKey { pattern, key: Some(key) } => format!("  regex {}: clave capturada {:?}", pattern, key),
+            Msg::ExplainKey { pattern, key: None } => format!("  regex {}: no coincide, el archivo se ignora", pattern),
+            Msg::ExplainRenamed(to) => format!("  decisión: renombrar a {:?}", to),
+            Msg::ExplainUnmatched => "  decisión: no hay con qué emparejarlo, no se renombra".to_string(),
+            Msg::ExplainAlreadyNamed => "  decisión: ya tiene el nombre correcto".to_string(),
+            Msg::ExplainVideoTarget(count) => format!("  decisión: video de destino de {} subtítulo(s)", count),
+            Msg::InvalidStripRegex(re) => format!("Regex inválido para --strip-before-match: {}", re),
+            Msg::AnchoredRegexHint(re) => format!(
+                "💡 El regex {} no coincidió con ningún archivo. Con ^ o $ tiene que abarcar el nombre completo; prueba a quitar los anclajes",
+                re
+            ),
+            Msg::OpenEvents(path) => format!("No se pudo abrir el destino de eventos {:?}", path),
+            Msg::EventsFdUnsupported => "❌ --events-fd solo está disponible en sistemas Unix; usa --events-file con una tubería con nombre".to_string(),
+            Msg::AlreadySubtitled { name, id, from } => format!("⏭️ {:?} ya es el subtítulo de {}: se omite {:?}", name, id, from),
+            Msg::NotWritable { dir, error } => format!("❌ No se puede escribir en {:?}: {}", dir, error),
+            Msg::UnknownNormalizeGroup { group, regex } => format!("❌ El regex {} no tiene un grupo con nombre {:?} (--normalize-groups)", regex, group),
+            Msg::ParallelFallback => "⚠️ --parallel-execute: el plan tiene destinos repetidos, encadenados o ya existentes; se ejecuta en orden".to_string(),
+            Msg::InvalidAltRegex(re) => format!("Regex inválido en --alt-regex: {}", re),
+            Msg::EmitSkippedArchives(count) => format!("⚠️ {} extracción(es) de zip no se pueden expresar con rename y se omiten", count),
+            Msg::InvalidDirConfig { path, error } => format!("❌ Configuración inválida en {:?}: {}", path, error),
+            Msg::TooManyOperations { count, max } => format!(
+                "❌ El plan tiene {} operaciones y el límite de --max-ops es {}. Revísalo con --dry-run y usa --force o sube el límite si es correcto",
+                count, max
+            ),
+            Msg::DuplicateSubtitles { id, count } => format!("📑 {}: {} subtítulos", id, count),
+            Msg::DuplicateVideos { id, count } => format!("🎬 {}: {} videos", id, count),
+            Msg::NoDuplicates => "✅ Ningún ID de episodio corresponde a más de un subtítulo o video".to_string(),
+            Msg::RiskMovesDirectory => "cambia de directorio".to_string(),
+            Msg::RiskRenumbered => "el destino original existía; se usa un nombre numerado".to_string(),
+            Msg::RiskySummary(count) => format!("⚠️ {} operación(es) marcadas con ! merecen una revisión más atenta", count),
+            Msg::VideoAlreadySubtitled(name) => format!("⏭️ {:?} ya tiene subtítulo; se omite (--only-missing)", name),
+            Msg::ScanArchive(path) => format!("❌ No se pudo leer el archivo {:?} (se admiten zip, tar y tar.gz)", path),
+            Msg::EpisodeOffsetWarning(offset) => format!("⚠️ --episode-offset {:+} desplaza el episodio de todos los subtítulos por igual; revisa el plan antes de aplicarlo", offset),
+            Msg::EpisodeOffsetOutOfRange { name, id } => format!("⏭️ {:?}: el episodio de {} queda por debajo de 0 con --episode-offset; se omite", name, id),
+            Msg::DuplicateTarget { to, sources } => format!("❌ Varios archivos se renombrarían a {:?}: {}", to, sources),
+            Msg::DuplicateTargets(count) => format!("Se aborta sin renombrar nada: {} destinos repetidos se sobrescribirían entre sí", count),
+            Msg::ReadFileManifest(path) => format!("❌ No se pudo leer el manifiesto de archivos {:?}", path),
+            Msg::InvalidFileManifest { path, reason } => format!("❌ Manifiesto de archivos inválido {:?}: {}", path, reason),
+            Msg::InvalidMarkPrefix(prefix) => format!("--mark-unmatched no puede estar vacío ni contener caracteres inválidos en nombres: {:?}", prefix),
+            Msg::DiffHeader(path) => format!("🔍 Cambios respecto al plan anterior ({:?}):", path),
+            Msg::DiffNewMatch { from, to } => format!("  ➕ {} -> {}", from, to),
+            Msg::DiffDroppedMatch { from, to } => format!("  ➖ {} -> {} (ya no está en el plan)", from, to),
+            Msg::DiffNewOrphan(path) => format!("  ❓ Nuevo sin emparejar: {}", path),
+            Msg::DiffResolvedOrphan(path) => format!("  ✅ Ya no está sin emparejar: {}", path),
+            Msg::DiffNewConflict(path) => format!("  ⚠️ Nuevo conflicto: varios archivos irían a {}", path),
+            Msg::DiffNoChanges => "✅ El plan no cambió desde la ejecución anterior".to_string(),
+            Msg::DiffNoPrevious(path) => format!("ℹ️ No hay plan anterior para comparar; se guarda este en {:?}", path),
+            Msg::NoPlanStoreLocation => "No se encontró dónde guardar el último plan: indica un archivo con --diff-last ARCHIVO".to_string(),
+            Msg::SubtitleTooLarge { name, size, max } => format!("⚠️ {:?} pesa {} bytes (más que --max-subtitle-size {}); probablemente no es un subtítulo, se omite", name, size, max),
+            Msg::VideoTooSmall { name, size, min } => format!("⚠️ {:?} pesa {} bytes (menos que --min-video-size {}); probablemente no es un video, se omite", name, size, min),
+            Msg::DotSaved { path, matches } => format!("💾 Grafo con {} emparejamientos guardado en {:?}", matches, path),
+            Msg::CreateDot(path) => format!("No se pudo crear el archivo DOT {:?}", path),
+            Msg::EditItemPrompt { index, from, to } => {
+                format!("  {}. {:?} -> {:?} [s]í / [n]o / [e]ditar destino / [q] terminar: ", index, from, to)
+            }
+            Msg::EditNamePrompt => "     Nuevo nombre (vacío para dejarlo como estaba): ".to_string(),
+            Msg::InvalidEditedName(name) => format!("     ❌ {:?} no es un nombre de archivo válido (sin directorios)", name),
+            Msg::ReadTitleMap(path) => format!("No se pudo leer el mapa de títulos {:?}", path),
+            Msg::TitleMapLine { path, line, content } => format!("Línea {} inválida en el mapa de títulos {:?} (se esperaba 'título,ID'): {:?}", line, path, content),
+            Msg::TitleMapped { name, id } => format!("🏷️ {:?}: identificado por su título como {}", name, id),
+            Msg::ReadOnlySource(error) => format!("el archivo es de solo lectura ({}); usa --handle-readonly para renombrarlo conservando el atributo", error),
+            Msg::TallyGroup(group) => format!("📊 Grupo {}:", group),
+            Msg::TableValue => "Valor".to_string(),
+            Msg::TableSubtitles => "Subtítulos".to_string(),
+            Msg::TableVideos => "Videos".to_string(),
+            Msg::TallyEmpty => "ℹ️ Ningún archivo produjo grupos de captura que contar".to_string(),
+            Msg::DirectoryLocked { path, owner } => format!("🔒 Otra instancia{} ya está trabajando en este directorio (bloqueo {:?}); se aborta sin hacer nada", owner, path),
+            Msg::LockFailed(path) => format!("No se pudo crear o bloquear el archivo {:?}", path),
+            Msg::ShowKeyAutoNeedsDate => "--show-key-regex auto necesita --date-format: la serie es lo que precede a la fecha".to_string(),
+            Msg::IncompleteDirectorySkipped { dir, missing, skipped } => format!("⏭️  {:?}: {} subtítulo(s) sin video; se omiten sus {} renombrado(s)", dir, missing, skipped),
+            Msg::MatchedGroups { name, groups } => format!("🔎 {:?}: grupos con coincidencia {}", name, groups),
+            Msg::WriteSummaryJson(path) => format!("No se pudo escribir el resumen JSON en {:?}", path),
+            Msg::LowercaseTwinExists { from, to } => format!("⚠️ No se cambia la extensión de {:?}: ya existe otro archivo {:?}", from, to),
+            Msg::DirectoryRenameBlocked { dir, name } => format!("⏭️ No se renombra la carpeta {:?}: {:?} no se renombró", dir, name),
+            Msg::ConfigDefault => "predeterminado".to_string(),
+            Msg::ConfigDerived => "derivado".to_string(),
+            Msg::InvalidRate(value) => format!("'{}' no está entre 0.0 y 1.0", value),
+            Msg::InvalidSize(value) => format!("'{}' no es un tamaño válido (ej: 500K, 5M, 1G)", value),
+            Msg::ConfigLine { line, error } => format!("línea {}: {}", line, error),
+            Msg::ConfigMissingEquals => "falta '='".to_string(),
+            Msg::ConfigTrailingText(rest) => format!("texto inesperado tras el valor: {:?}", rest),
+            Msg::ConfigUnknownKey(key) => format!("clave desconocida {:?}", key),
+            Msg::ConfigExpectedString => "se esperaba una cadena".to_string(),
+            Msg::ConfigUnclosedArray => "falta ',' o ']' en el arreglo".to_string(),
+            Msg::ConfigUnclosedQuote => "falta la comilla de cierre".to_string(),
+            Msg::ConfigUnsupportedEscape(c) => format!("escape no soportado: \\{}", c),
+            Msg::ConfigExpectedQuoted => "se esperaba una cadena entre comillas".to_string(),
+            Msg::DotSubtitles => "subtítulos".to_string(),
+            Msg::DotVideos => "videos".to_string(),
+            Msg::ManifestExpectedArray => "se esperaba un arreglo".to_string(),
+            Msg::ManifestMissingPath => "falta \"path\"".to_string(),
+            Msg::EmitSkippedExisting { from, to } => format!("⚠️ Se omite {:?} -> {:?}: el destino ya existe", from, to),
+            Msg::EmitSkippedRepeated { from, to } => format!("⚠️ Se omite {:?} -> {:?}: otra operación ya usa ese destino", from, to),
+            Msg::EmitSkippedNonUtf8(path) => format!("⚠️ Se omite {:?}: la ruta no es UTF-8 y no se puede escribir en el script", path),
+            Msg::EmitNeedsUtilLinux => "Este script necesita rename(1) de util-linux".to_string(),
+            Msg::ExitCodesHelp => "Códigos de salida:\n  0  ejecución correcta\n  1  error durante la ejecución (o faltan los regex)\n  2  argumentos inválidos\n  N  no había nada que renombrar, si se indica --no-op-exit-code N".to_string(),
+        }
+    }
+
+    fn en(&self) -> String {
+        match self {
+            Msg::Usage => "❌ You must provide at least one regex.\n\
+                \n📚 Usage examples:\n\
+                \x20 # Basic, with a regex for both file types:\n\
+                \x20 sub-renamer --srt-regex 'S(\\d{2})E(\\d{2})' --mkv-regex 'S(\\d{2})E(\\d{2})'\n\
+                \n  # Multiple extensions and recursive mode:\n\
+                \x20 sub-renamer --srt-regex 'S(\\d{2})E(\\d{2})' --srt-ext srt,ass,vtt --video-ext mkv,mp4,avi --recursive\n\
+                \n  # Dry run (does not actually rename):\n\
+                \x20 sub-renamer --srt-regex 'S(\\d{2})E(\\d{2})' --dry-run\n\
+                \n  # In a specific directory:\n\
+                \x20 sub-renamer --srt-regex 'S(\\d{2})E(\\d{2})' --directory /path/to/episodes\n\
+                \n  # Movies split into parts (CD1/CD2, Part 1/Part 2):\n\
+                \x20 sub-renamer --part-regex '(?i)(?:cd|part)\\s*(\\d+)'\n\
+                \n  # Fansubs with the video's CRC32 in the name ([A1B2C3D4]):\n\
+                \x20 sub-renamer --match-crc --srt-ext ass\n\
+                \n  # No regex: the first number in the name is the episode:\n\
+                \x20 sub-renamer --first-number"
+                .to_string(),
+            Msg::NoMatcher => {
+                "❌ You must provide at least one regex (--srt-regex, --mkv-regex or --part-regex)".to_string()
+            }
+            Msg::InvalidSubtitleRegex(re) => format!("Invalid subtitle regex: {}", re),
+            Msg::InvalidVideoRegex(re) => format!("Invalid video regex: {}", re),
+            Msg::InvalidPartRegex(re) => format!("Invalid part regex: {}", re),
+            Msg::DirectoryNotFound(dir) => format!("❌ Directory {:?} does not exist", dir),
+            Msg::OverlappingExtensions(exts) => format!(
+                "❌ Extensions listed in both --srt-ext and --video-ext: {} (each extension must be in only one list)",
+                exts
+            ),
+            Msg::ReadDirectory(dir) => format!("Could not read directory {:?}", dir),
+            Msg::FileAccess(error) => format!("⚠️ Error accessing file: {}", error),
+            Msg::PruningDirectory(dir) => format!("✂️ Skipping directory: {:?}", dir),
+            Msg::ReadHashManifest(path) => format!("Could not read hash manifest {:?}", path),
+            Msg::InvalidHashManifest(path) => format!("Invalid hash manifest {:?}", path),
+            Msg::HashManifestLine { line, content } => {
+                format!("line {}: expected '<crc32> <id>', found {:?}", line, content)
+            }
+            Msg::HashNotInManifest { crc, name } => {
+                format!("🔎 Hash {:08x} is not registered in the manifest: {:?}", crc, name)
+            }
+            Msg::HashFailed { path, error } => format!("⚠️ Could not hash {:?}: {}", path, error),
+            Msg::FoundFiles { subtitles, videos } => {
+                format!("📊 Found {} subtitles and {} videos", subtitles, videos)
+            }
+            Msg::VideoInOtherDirectory { id, name } => format!(
+                "⚠️ The video for episode '{}' is in another directory (subtitle: {:?}, --colocated-only)",
+                id, name
+            ),
+            Msg::NoVideoForEpisode { id, name } => {
+                format!("⚠️ No video found for episode '{}' (subtitle: {:?})", id, name)
+            }
+            Msg::NothingToRename => "ℹ️ No files to rename".to_string(),
+            Msg::LowMatchRate { matched, total, rate, min_rate } => format!(
+                "❌ Only {} of {} subtitles ({:.0}%) have a video, below the {:.0}% minimum (--min-match-rate); check the regex",
+                matched, total, rate * 100.0, min_rate * 100.0
+            ),
+            Msg::DirectoryHasOtherFiles(dir) => {
+                format!("⚠️ Not renaming directory {:?}: it contains other files", dir)
+            }
+            Msg::CaseOnlyRename { from, to } => format!(
+                "ℹ️ Already effectively named (differs only in case): {:?} -> {:?} (use --fix-case to apply it)",
+                from, to
+            ),
+            Msg::NumberedDestination { taken, free } => format!("🔢 Destination {:?} is taken, using {:?}", taken, free),
+            Msg::NoFreeNumberedName { name, attempts, id } => format!(
+                "⚠️ No free name found for {:?} after {} attempts (episode: {})",
+                name, attempts, id
+            ),
+            Msg::DestinationExists { name, id } => {
+                format!("⚠️ Destination file already exists: {:?} (episode: {})", name, id)
+            }
+            Msg::DryRunRename { from, to } => format!("🔄 [DRY RUN] {:?} -> {:?}", from, to),
+            Msg::Renamed { from, to } => format!("✅ Renamed: {:?} -> {:?}", from, to),
+            Msg::RenameError { name, error } => format!("❌ Error renaming {:?}: {}", name, error),
+            Msg::SummaryHeader => "\n📈 Summary:".to_string(),
+            Msg::SummarySucceeded(n) => format!("  ✅ Succeeded: {}", n),
+            Msg::SummaryErrors(n) => format!("  ❌ Errors: {}", n),
+            Msg::SummaryDryRun => "  ℹ️ Dry run enabled - no files were actually renamed".to_string(),
+            Msg::CreatePlan(path) => format!("Could not create plan file {:?}", path),
+            Msg::ReadPlan(path) => format!("Could not read plan file {:?}", path),
+            Msg::InvalidPlan(path) => format!("Invalid plan {:?}", path),
+            Msg::UnsupportedPlanVersion { path, version } => {
+                format!("❌ Unsupported plan version in {:?}: {:?}", path, version)
+            }
+            Msg::PlanWithoutOperations(path) => format!("Plan {:?} contains no operations", path),
+            Msg::PlanMissingField { key, path } => format!("Operation without field '{}' in plan {:?}", key, path),
+            Msg::PlanSourceMissing(path) => format!("⚠️ File from the plan no longer exists, skipping: {:?}", path),
+            Msg::PlanSourceChanged(path) => {
+                format!("⚠️ File changed since the plan was created, skipping: {:?}", path)
+            }
+            Msg::PlanSaved { count, path } => format!("💾 Plan with {} operations saved to {:?}", count, path),
+            Msg::WriteMetrics(path) => format!("Could not write metrics file {:?}", path),
+            Msg::RenameOneMissing(path) => format!("File given to --rename-one does not exist: {:?}", path),
+            Msg::ReviewPageHeader { page, pages } => format!("📄 Page {} of {}", page, pages),
+            Msg::ReviewOperation { index, from, to } => format!("  {}. {:?} -> {:?}", index, from, to),
+            Msg::ReviewPagePrompt => {
+                "Approve? [y]es this page / [n]o / [a]ll remaining / [o]ne by one / [q]uit: ".to_string()
+            }
+            Msg::ReviewItemPrompt { index, from, to } => {
+                format!("  {}. {:?} -> {:?} Rename? [y/n/a/q]: ", index, from, to)
+            }
+            Msg::SuspiciousMatch { subtitle, video, id } => format!(
+                "⚠️ Possible ID collision for {}: {:?} and {:?} share almost no words",
+                id, subtitle, video
+            ),
+            Msg::HistoryNotWritable(path) => format!("Cannot write to history file {:?}", path),
+            Msg::NoHistoryLocation => "Could not find a data directory for the history; use --history-file".to_string(),
+            Msg::InvalidShowKeyRegex(re) => format!("Invalid show key regex: {}", re),
+            Msg::InvalidRegex(re) => format!("Invalid regex: {}", re),
+            Msg::CaptureGroupOutOfRange { regex, group } => format!("Regex {} has no capture group {}", regex, group),
+            Msg::RegexMatch { name, id } => format!("✅ {:?} -> {}", name, id),
+            Msg::RegexNoMatch(name) => format!("❌ {:?}: no match", name),
+            Msg::DateRegexGroups(re) => {
+                format!("Regex {} needs three capture groups (year, month and day) for --date-format", re)
+            }
+            Msg::FailFastStopped { remaining } => {
+                format!("⛔ Stopped at the first error (--fail-fast); {} operations not attempted", remaining)
+            }
+            Msg::InvalidSanitizeChar(c) => format!("--sanitize-char cannot be a character that is invalid in file names: {:?}", c),
+            Msg::ReviewApplyPrompt(count) => format!("Apply these {} renames? [y/n]: ", count),
+            Msg::ReviewCancelled => "🚫 No changes were applied".to_string(),
+            Msg::InvalidMetaJson { path, error } => format!("⚠️ Invalid JSON metadata in {:?}: {}", path, error),
+            Msg::OutsideOriginalDirectory { from, to } => format!(
+                "Operation outside the original directory refused by --rename-subtitles-in-place-only: {:?} -> {:?}",
+                from, to
+            ),
+            Msg::TableExtension => "Extension".to_string(),
+            Msg::TableSucceeded => "Succeeded".to_string(),
+            Msg::TableSkipped => "Skipped".to_string(),
+            Msg::TableErrors => "Errors".to_string(),
+            Msg::TableTotal => "Total".to_string(),
+            Msg::SampleMore(n) => format!("   ... and {} more", n),
+            Msg::DryRunExtract { archive, entry, to } => format!("🔄 [DRY RUN] {:?} ({}) -> {:?}", archive, entry, to),
+            Msg::Extracted { archive, entry, to } => format!("📦 Extracted: {:?} ({}) -> {:?}", archive, entry, to),
+            Msg::ReadArchive { path, error } => format!("⚠️ Could not read zip {:?}: {}", path, error),
+            Msg::NoSubtitleForVideo { id, name } => {
+                format!("⚠️ No subtitle found for episode '{}' (video: {:?})", id, name)
+            }
+            Msg::VerifyMissing(path) => format!("❌ Verification: renamed file does not exist at {:?}", path),
+            Msg::VerificationFailed(n) => format!("Post-run verification found {} renames that did not take effect", n),
+            Msg::InvalidTemplate { template, error } => format!("Invalid template {:?}: {}", template, error),
+            Msg::ReadTemplateFile(path) => format!("Could not read template file {:?}", path),
+            Msg::InvalidTemplateLine { path, line, error } => format!("Invalid template in {:?}, line {}: {}", path, line, error),
+            Msg::UnknownTemplateToken { token, template, known } => {
+                format!("Unknown token {{{}}} in template {:?} (available: {})", token, template, known)
+            }
+            Msg::RemovedEmptyDirectory(path) => format!("🗑️ Removed empty directory: {:?}", path),
+            Msg::DryRunRemoveDirectory(path) => format!("🗑️ [DRY RUN] Would remove empty directory {:?}", path),
+            Msg::RemoveDirectoryError { path, error } => format!("❌ Could not remove directory {:?}: {}", path, error),
+            Msg::DuplicateVideoSkipped { id, chosen, skipped } => format!(
+                "⚠️ Several videos for {}: using {:?} (highest resolution), ignoring {:?}",
+                id, chosen, skipped
+            ),
+            Msg::MtimeMatchWarning => "⚠️ --match-by-mtime pairs files by modification time, ignoring their names: check every rename before confirming".to_string(),
+            Msg::MtimeCountMismatch { subtitles, videos } => format!(
+                "❌ --match-by-mtime needs as many subtitles as videos (found {} subtitles and {} videos)",
+                subtitles, videos
+            ),
+            Msg::ReadModifiedTime(path) => format!("Could not read the modification time of {:?}", path),
+            Msg::ExplainFile(path) => format!("🔎 Trace for {:?}", path),
+            Msg::ExplainNotFound(name) => format!("❌ {:?} is not among the scanned files", name),
+            Msg::ExplainExtension { extension, list } => format!("  extension {:?}: matches {}", extension, list),
+            Msg::ExplainIgnoredExtension(extension) => format!("  extension {:?}: not in --srt-ext or --video-ext, the file is ignored", extension),
+            Msg::ExplainKey { pattern, key: Some(key) } => format!("  regex {}: captured key {:?}", pattern, key),
+            Msg::ExplainKey { pattern, key: None } => format!("  regex {}: no match, the file is ignored", pattern),
+            Msg::ExplainRenamed(to) => format!("  decision: rename to {:?}", to),
+            Msg::ExplainUnmatched => "  decision: nothing to pair it with, not renamed".to_string(),
+            Msg::ExplainAlreadyNamed => "  decision: already has the right name".to_string(),
+            Msg::ExplainVideoTarget(count) => format!("  decision: target video for {} subtitle(s)", count),
+            Msg::InvalidStripRegex(re) => format!("Invalid --strip-before-match regex: {}", re),
+            Msg::AnchoredRegexHint(re) => format!(
+                "💡 The regex {} matched no files. With ^ or $ it has to cover the whole file name; try removing the anchors",
+                re
+            ),
+            Msg::OpenEvents(path) => format!("Could not open the event stream {:?}", path),
+            Msg::EventsFdUnsupported => "❌ --events-fd is only available on Unix systems; use --events-file with a named pipe".to_string(),
+            Msg::AlreadySubtitled { name, id, from } => format!("⏭️ {:?} is already the subtitle for {}: skipping {:?}", name, id, from),
+            Msg::NotWritable { dir, error } => format!("❌ Cannot write to {:?}: {}", dir, error),
+            Msg::UnknownNormalizeGroup { group, regex } => format!("❌ The regex {} has no group named {:?} (--normalize-groups)", regex, group),
+            Msg::ParallelFallback => "⚠️ --parallel-execute: the plan has repeated, chained or existing destinations; running sequentially".to_string(),
+            Msg::InvalidAltRegex(re) => format!("Invalid --alt-regex: {}", re),
+            Msg::EmitSkippedArchives(count) => format!("⚠️ {} zip extraction(s) cannot be expressed with rename and were left out", count),
+            Msg::InvalidDirConfig { path, error } => format!("❌ Invalid configuration in {:?}: {}", path, error),
+            Msg::TooManyOperations { count, max } => format!(
+                "❌ The plan has {} operations and the --max-ops limit is {}. Check it with --dry-run, then use --force or raise the limit if it is right",
+                count, max
+            ),
+            Msg::DuplicateSubtitles { id, count } => format!("📑 {}: {} subtitles", id, count),
+            Msg::DuplicateVideos { id, count } => format!("🎬 {}: {} videos", id, count),
+            Msg::NoDuplicates => "✅ No episode ID maps to more than one subtitle or video".to_string(),
+            Msg::RiskMovesDirectory => "moves to another directory".to_string(),
+            Msg::RiskRenumbered => "the original destination existed; using a numbered name".to_string(),
+            Msg::RiskySummary(count) => format!("⚠️ {} operation(s) marked with ! deserve a closer look", count),
+            Msg::VideoAlreadySubtitled(name) => format!("⏭️ {:?} already has a subtitle; skipped (--only-missing)", name),
+            Msg::ScanArchive(path) => format!("❌ Could not read archive {:?} (zip, tar and tar.gz are supported)", path),
+            Msg::EpisodeOffsetWarning(offset) => format!("⚠️ --episode-offset {:+} shifts the episode of every subtitle alike; review the plan before applying it", offset),
+            Msg::EpisodeOffsetOutOfRange { name, id } => format!("⏭️ {:?}: episode {} falls below 0 with --episode-offset; skipped", name, id),
+            Msg::DuplicateTarget { to, sources } => format!("❌ Several files would be renamed to {:?}: {}", to, sources),
+            Msg::DuplicateTargets(count) => format!("Aborting without renaming anything: {} repeated targets would overwrite each other", count),
+            Msg::ReadFileManifest(path) => format!("❌ Could not read file manifest {:?}", path),
+            Msg::InvalidFileManifest { path, reason } => format!("❌ Invalid file manifest {:?}: {}", path, reason),
+            Msg::InvalidMarkPrefix(prefix) => format!("--mark-unmatched cannot be empty or contain characters that are invalid in file names: {:?}", prefix),
+            Msg::DiffHeader(path) => format!("🔍 Changes since the previous plan ({:?}):", path),
+            Msg::DiffNewMatch { from, to } => format!("  ➕ {} -> {}", from, to),
+            Msg::DiffDroppedMatch { from, to } => format!("  ➖ {} -> {} (no longer planned)", from, to),
+            Msg::DiffNewOrphan(path) => format!("  ❓ Newly unmatched: {}", path),
+            Msg::DiffResolvedOrphan(path) => format!("  ✅ No longer unmatched: {}", path),
+            Msg::DiffNewConflict(path) => format!("  ⚠️ New conflict: several files would go to {}", path),
+            Msg::DiffNoChanges => "✅ The plan has not changed since the previous run".to_string(),
+            Msg::DiffNoPrevious(path) => format!("ℹ️ No previous plan to compare against; saving this one to {:?}", path),
+            Msg::NoPlanStoreLocation => "No location found to store the last plan: pass a file with --diff-last FILE".to_string(),
+            Msg::SubtitleTooLarge { name, size, max } => format!("⚠️ {:?} is {} bytes (above --max-subtitle-size {}); probably not a subtitle, skipped", name, size, max),
+            Msg::VideoTooSmall { name, size, min } => format!("⚠️ {:?} is {} bytes (below --min-video-size {}); probably not a video, skipped", name, size, min),
+            Msg::DotSaved { path, matches } => format!("💾 Graph with {} matches saved to {:?}", matches, path),
+            Msg::CreateDot(path) => format!("Could not create DOT file {:?}", path),
+            Msg::EditItemPrompt { index, from, to } => {
+                format!("  {}. {:?} -> {:?} [y]es / [n]o / [e]dit target / [q]uit: ", index, from, to)
+            }
+            Msg::EditNamePrompt => "     New name (empty to keep it): ".to_string(),
+            Msg::InvalidEditedName(name) => format!("     ❌ {:?} is not a valid file name (no directories)", name),
+            Msg::ReadTitleMap(path) => format!("Could not read title map {:?}", path),
+            Msg::TitleMapLine { path, line, content } => format!("Invalid line {} in title map {:?} (expected 'title,ID'): {:?}", line, path, content),
+            Msg::TitleMapped { name, id } => format!("🏷️ {:?}: identified by its title as {}", name, id),
+            Msg::ReadOnlySource(error) => format!("the file is read-only ({}); use --handle-readonly to rename it and keep the attribute", error),
+            Msg::TallyGroup(group) => format!("📊 Group {}:", group),
+            Msg::TableValue => "Value".to_string(),
+            Msg::TableSubtitles => "Subtitles".to_string(),
+            Msg::TableVideos => "Videos".to_string(),
+            Msg::TallyEmpty => "ℹ️ No file produced capture groups to count".to_string(),
+            Msg::DirectoryLocked { path, owner } => format!("🔒 Another instance{} is already working on this directory (lock {:?}); aborting without doing anything", owner, path),
+            Msg::LockFailed(path) => format!("Could not create or lock file {:?}", path),
+            Msg::ShowKeyAutoNeedsDate => "--show-key-regex auto requires --date-format: the show is whatever precedes the date".to_string(),
+            Msg::IncompleteDirectorySkipped { dir, missing, skipped } => format!("⏭️  {:?}: {} subtitle(s) without a video; skipping its {} rename(s)", dir, missing, skipped),
+            Msg::MatchedGroups { name, groups } => format!("🔎 {:?}: matched groups {}", name, groups),
+            Msg::WriteSummaryJson(path) => format!("Could not write the JSON summary to {:?}", path),
+            Msg::LowercaseTwinExists { from, to } => format!("⚠️ Not lowercasing the extension of {:?}: a different file {:?} already exists", from, to),
+            Msg::DirectoryRenameBlocked { dir, name } => format!("⏭️ Not renaming folder {:?}: {:?} was not renamed", dir, name),
+            Msg::ConfigDefault => "default".to_string(),
+            Msg::ConfigDerived => "derived".to_string(),
+            Msg::InvalidRate(value) => format!("'{}' is not between 0.0 and 1.0", value),
+            Msg::InvalidSize(value) => format!("'{}' is not a valid size (e.g. 500K, 5M, 1G)", value),
+            Msg::ConfigLine { line, error } => format!("line {}: {}", line, error),
+            Msg::ConfigMissingEquals => "missing '='".to_string(),
+            Msg::ConfigTrailingText(rest) => format!("unexpected text after the value: {:?}", rest),
+            Msg::ConfigUnknownKey(key) => format!("unknown key {:?}", key),
+            Msg::ConfigExpectedString => "expected a string".to_string(),
+            Msg::ConfigUnclosedArray => "missing ',' or ']' in the array".to_string(),
+            Msg::ConfigUnclosedQuote => "missing closing quote".to_string(),
+            Msg::ConfigUnsupportedEscape(c) => format!("unsupported escape: \\{}", c),
+            Msg::ConfigExpectedQuoted => "expected a quoted string".to_string(),
+            Msg::DotSubtitles => "subtitles".to_string(),
+            Msg::DotVideos => "videos".to_string(),
+            Msg::ManifestExpectedArray => "expected an array".to_string(),
+            Msg::ManifestMissingPath => "missing \"path\"".to_string(),
+            Msg::EmitSkippedExisting { from, to } => format!("⚠️ Leaving out {:?} -> {:?}: the target already exists", from, to),
+            Msg::EmitSkippedRepeated { from, to } => format!("⚠️ Leaving out {:?} -> {:?}: another operation already uses that target", from, to),
+            Msg::EmitSkippedNonUtf8(path) => format!("⚠️ Leaving out {:?}: the path is not UTF-8 and cannot be written to the script", path),
+            Msg::EmitNeedsUtilLinux => "This script needs util-linux rename(1)".to_string(),
+            Msg::ExitCodesHelp => "Exit codes:\n  0  successful run\n  1  error during the run (or missing regexes)\n  2  invalid arguments\n  N  nothing to rename, when --no-op-exit-code N is given".to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lang_from_locale() {
+        assert_eq!(Lang::from_locale("es_ES.UTF-8"), Lang::Es);
+        assert_eq!(Lang::from_locale("es"), Lang::Es);
+        assert_eq!(Lang::from_locale("C.UTF-8"), Lang::Es);
+        assert_eq!(Lang::from_locale("en_US.UTF-8"), Lang::En);
+        assert_eq!(Lang::from_locale("de_DE"), Lang::En);
+    }
+
+    #[test]
+    fn test_messages_are_translated() {
+        let msg = Msg::FoundFiles { subtitles: 2, videos: 3 };
+        assert_eq!(msg.text(Lang::Es), "📊 Encontrados 2 subtítulos y 3 videos");
+        assert_eq!(msg.text(Lang::En), "📊 Found 2 subtitles and 3 videos");
+    }
+}
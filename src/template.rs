@@ -0,0 +1,156 @@
+//! Plantillas de nombres de destino con marcadores `{nombre}`.
+//!
+//! Una plantilla que usa un marcador sin valor no se puede aplicar; con una
+//! lista de plantillas se usa la primera que tenga todos sus valores.
+
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    Token(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    source: String,
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplateError {
+    /// Una `{` sin su `}`
+    Unclosed,
+    /// Un marcador `{}` vacío
+    EmptyToken,
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemplateError::Unclosed => write!(f, "falta '}}' para cerrar un marcador"),
+            TemplateError::EmptyToken => write!(f, "marcador vacío '{{}}'"),
+        }
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+impl Template {
+    /// Interpreta `source`; `{{` y `}}` representan llaves literales.
+    pub fn parse(source: &str) -> Result<Self, TemplateError> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = source.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut token = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => token.push(c),
+                            None => return Err(TemplateError::Unclosed),
+                        }
+                    }
+                    let token = token.trim();
+                    if token.is_empty() {
+                        return Err(TemplateError::EmptyToken);
+                    }
+                    if !literal.is_empty() {
+                        parts.push(Part::Literal(std::mem::take(&mut literal)));
+                    }
+                    parts.push(Part::Token(token.to_string()));
+                }
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+        Ok(Template { source: source.to_string(), parts })
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Marcadores usados por la plantilla.
+    pub fn tokens(&self) -> impl Iterator<Item = &str> {
+        self.parts.iter().filter_map(|part| match part {
+            Part::Token(token) => Some(token.as_str()),
+            Part::Literal(_) => None,
+        })
+    }
+
+    /// Nombre resultante, o `None` si falta el valor de algún marcador.
+    pub fn render(&self, values: &HashMap<&str, String>) -> Option<String> {
+        let mut out = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Literal(text) => out.push_str(text),
+                Part::Token(token) => out.push_str(values.get(token.as_str())?),
+            }
+        }
+        Some(out)
+    }
+}
+
+/// Plantillas de un archivo de reglas: una por línea, en orden de preferencia.
+/// Las líneas vacías y las que empiezan con `#` se ignoran.
+pub fn parse_list(content: &str) -> Result<Vec<Template>, (usize, TemplateError)> {
+    content
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(number, line)| Template::parse(line).map_err(|e| (number, e)))
+        .collect()
+}
+
+/// Primera plantilla de `templates` que se puede aplicar con `values`.
+pub fn render_first(templates: &[Template], values: &HashMap<&str, String>) -> Option<String> {
+    templates.iter().find_map(|template| template.render(values))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_render() {
+        let template = Template::parse("{video_stem}.{{{lang}}}.{ext}").unwrap();
+        assert_eq!(template.tokens().collect::<Vec<_>>(), ["video_stem", "lang", "ext"]);
+
+        let mut values = HashMap::from([("video_stem", "Show".to_string()), ("ext", "srt".to_string())]);
+        assert_eq!(template.render(&values), None);
+        values.insert("lang", "es".to_string());
+        assert_eq!(template.render(&values).as_deref(), Some("Show.{es}.srt"));
+
+        assert_eq!(Template::parse("{video_stem"), Err(TemplateError::Unclosed));
+        assert_eq!(Template::parse("{ }.srt"), Err(TemplateError::EmptyToken));
+    }
+
+    #[test]
+    fn test_fallback_list() {
+        let templates = parse_list("# preferida con desfase\n{video_stem}.{offset}.{ext}\n\n{video_stem}.{ext}\n").unwrap();
+        assert_eq!(templates.len(), 2);
+
+        let mut values = HashMap::from([("video_stem", "Show".to_string()), ("ext", "srt".to_string())]);
+        assert_eq!(render_first(&templates, &values).as_deref(), Some("Show.srt"));
+        values.insert("offset", "+2s".to_string());
+        assert_eq!(render_first(&templates, &values).as_deref(), Some("Show.+2s.srt"));
+
+        assert_eq!(parse_list("{ok}\n{roto"), Err((2, TemplateError::Unclosed)));
+    }
+}